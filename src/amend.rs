@@ -0,0 +1,173 @@
+use anyhow::{bail, Result};
+use rand::{seq::SliceRandom, Rng};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    config::{Config, Room},
+    score::Solution,
+    solver::{best, generate_solutions},
+};
+
+/// A room from the saved solution, and how many people it can hold
+struct Slot {
+    name: Option<String>,
+    people: Vec<String>,
+    capacity: usize,
+    /// Whether the room is re-solved, rather than kept as it was
+    open: bool,
+}
+
+/// Takes people out of a saved solution and adds others to it, while keeping
+/// every room nobody left or joined as it was. Rooms someone left are
+/// re-solved together with everyone joining, along with as few rooms with
+/// spare beds as it takes to fit them, and new rooms if even that isn't
+/// enough. Returns `None` if the re-solved rooms can't keep every
+/// [never_pair] pair apart
+pub fn amend(
+    config: &Config,
+    saved: &Solution,
+    remove: &[String],
+    add: &[String],
+    rng: &mut impl Rng,
+) -> Result<Option<Solution>> {
+    let placed = saved.rooms.iter().flatten().collect::<BTreeSet<_>>();
+    for person in remove {
+        if !placed.contains(person) {
+            bail!("'{}' isn't in a room in the saved solution", person);
+        }
+    }
+    let people = config.people();
+    for person in add {
+        if placed.contains(person) {
+            bail!("'{}' already has a room in the saved solution", person);
+        }
+        if !people.contains(person) {
+            bail!(
+                "'{}' isn't in the config, so add them with `picker add-person` first",
+                person
+            );
+        }
+    }
+
+    let mut slots = saved
+        .rooms
+        .iter()
+        .enumerate()
+        .map(|(i, room)| {
+            let name = saved.names.get(i).cloned();
+            let capacity = match &name {
+                Some(name) => config.rooms.get(name).map_or(room.len(), |x| x.capacity),
+                None if config.settings.group_sizes.is_some() => room.len(),
+                None => config.settings.room_size,
+            };
+            Slot {
+                people: room
+                    .iter()
+                    .filter(|x| !remove.contains(x))
+                    .cloned()
+                    .collect(),
+                capacity: capacity.max(room.len()),
+                open: room.iter().any(|x| remove.contains(x)),
+                name,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Makes room for everyone joining, first with the beds people left and
+    // then with the spare beds in rooms that were kept
+    let beds = |slots: &[Slot]| -> usize {
+        slots
+            .iter()
+            .filter(|x| x.open)
+            .map(|x| x.capacity - x.people.len())
+            .sum()
+    };
+    for i in 0..slots.len() {
+        if beds(&slots) >= add.len() {
+            break;
+        }
+        let slot = &mut slots[i];
+        if !slot.open && slot.capacity > slot.people.len() {
+            slot.open = true;
+        }
+    }
+    let mut empty = config.rooms.iter().filter(|x| !saved.names.contains(x.0));
+    while beds(&slots) < add.len() {
+        let slot = if config.rooms.is_empty() {
+            Slot {
+                name: None,
+                people: vec![],
+                capacity: config.settings.room_size.max(1),
+                open: true,
+            }
+        } else if let Some((name, room)) = empty.next() {
+            Slot {
+                name: Some(name.clone()),
+                people: vec![],
+                capacity: room.capacity,
+                open: true,
+            }
+        } else {
+            bail!("There aren't enough free beds in [rooms] for everyone joining");
+        };
+        slots.push(slot);
+    }
+
+    // The open rooms are solved as a smaller config of their own, where each
+    // has a name that sorts in the same order as the rooms, so that the
+    // solution's rooms can be put back where they came from
+    let open = slots
+        .iter()
+        .enumerate()
+        .filter(|x| x.1.open)
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+    let disturbed = open
+        .iter()
+        .flat_map(|&i| slots[i].people.clone())
+        .chain(add.iter().cloned())
+        .collect::<Vec<_>>();
+    if !disturbed.is_empty() {
+        let mut sub = config.clone();
+        sub.people = disturbed.clone();
+        sub.rooms = open
+            .iter()
+            .map(|&i| {
+                let room = Room {
+                    capacity: slots[i].capacity,
+                };
+                (format!("{:06}", i), room)
+            })
+            .collect::<BTreeMap<_, _>>();
+        sub.settings.group_sizes = None;
+        sub.settings.odd_policy = None;
+        sub.must_pair
+            .retain(|a, b| disturbed.contains(a) && disturbed.contains(b));
+
+        let solutions = generate_solutions(&sub, rng)?;
+        let Some(solution) = best(&solutions, &sub).choose(rng).copied() else {
+            return Ok(None);
+        };
+        for &i in &open {
+            slots[i].people.clear();
+        }
+        if solution.names.len() != solution.rooms.len() {
+            bail!("The solver didn't say which room is which, so try another solver");
+        }
+        for (room, name) in solution.rooms.iter().zip(&solution.names) {
+            let i = name.parse::<usize>()?;
+            slots[i].people = room.clone();
+        }
+    }
+
+    slots.retain(|x| !x.people.is_empty());
+    let names = if !config.rooms.is_empty() && slots.iter().all(|x| x.name.is_some()) {
+        slots.iter().filter_map(|x| x.name.clone()).collect()
+    } else {
+        vec![]
+    };
+    let rooms = slots.into_iter().map(|x| x.people).collect();
+    let mut solution = Solution::new(rooms, names, config);
+    solution.sort();
+    Ok(Some(solution))
+}
@@ -0,0 +1,106 @@
+use anyhow::{bail, Context, Result};
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::HashMap, fs, io::ErrorKind, path::Path};
+
+use crate::{config::Config, score::Solution};
+
+/// What everyone is called in place of their real name, for `--anonymize`.
+/// Each person keeps the same pseudonym for as long as the key file that
+/// maps them back is kept
+#[derive(Default)]
+pub struct Pseudonyms {
+    /// Each real name's pseudonym
+    names: HashMap<String, String>,
+}
+
+impl Pseudonyms {
+    /// Reads the pseudonyms already handed out from a key file, which
+    /// doesn't have to exist yet, and gives anyone in the config without one
+    /// the next `Person N`. Newcomers are numbered in a random order, so that
+    /// the numbers don't give away the order of the config. The key file is
+    /// written back if anyone was added
+    pub fn load(key: &Path, config: &Config, rng: &mut impl Rng) -> Result<Self> {
+        let mut order = vec![];
+        match fs::read(key) {
+            Ok(contents) => {
+                let mut reader = csv::Reader::from_reader(contents.as_slice());
+                for record in reader.deserialize::<(String, String)>() {
+                    order.push(record.with_context(|| {
+                        format!(
+                            "{} isn't a key file, which has a pseudonym and a name on each line",
+                            key.display()
+                        )
+                    })?);
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error).with_context(|| format!("Couldn't read {}", key.display()))
+            }
+        }
+
+        let mut pseudonyms = Self::default();
+        for (pseudonym, name) in &order {
+            if pseudonyms.names.values().any(|x| x == pseudonym) {
+                bail!(
+                    "'{}' is given to more than one person in {}",
+                    pseudonym,
+                    key.display()
+                );
+            }
+            pseudonyms.names.insert(name.clone(), pseudonym.clone());
+        }
+
+        let mut newcomers = config
+            .people()
+            .into_iter()
+            .filter(|x| !pseudonyms.names.contains_key(x))
+            .collect::<Vec<_>>();
+        if newcomers.is_empty() {
+            return Ok(pseudonyms);
+        }
+        newcomers.shuffle(rng);
+        let mut number = order.len();
+        for name in newcomers {
+            let pseudonym = loop {
+                number += 1;
+                let pseudonym = format!("Person {}", number);
+                if !pseudonyms.names.values().any(|x| *x == pseudonym) {
+                    break pseudonym;
+                }
+            };
+            pseudonyms.names.insert(name.clone(), pseudonym.clone());
+            order.push((pseudonym, name));
+        }
+
+        let mut writer = csv::Writer::from_path(key)
+            .with_context(|| format!("Couldn't write {}", key.display()))?;
+        writer.write_record(["pseudonym", "name"])?;
+        for record in &order {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+        Ok(pseudonyms)
+    }
+
+    /// A person's pseudonym. Anyone without one, such as someone who isn't
+    /// in the config, keeps their name
+    pub fn get(&self, name: &str) -> String {
+        self.names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    pub fn config(&self, config: &mut Config) {
+        config.rename_people(|x| self.get(x));
+    }
+
+    /// Renames everyone in a solution read from a file. Its counts don't
+    /// depend on anyone's name, so they stay as they are
+    pub fn solution(&self, solution: &mut Solution) {
+        for person in solution.rooms.iter_mut().flatten() {
+            *person = self.get(person);
+        }
+    }
+}
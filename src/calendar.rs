@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Error, Result};
+use std::{io::Write, time::SystemTime};
+
+use crate::{cli::Cadence, date::Date, score::Solution, store::hash_config};
+
+/// The error for output that has no dates to put on a calendar
+pub fn unsupported() -> Error {
+    anyhow!("Only `picker schedule` with --start can be printed with --format ics")
+}
+
+/// Escapes text for an iCalendar property value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Writes a content line, folding it onto continuation lines so that none is
+/// longer than the 75 bytes iCalendar allows
+fn line<W: Write>(writer: &mut W, text: &str) -> Result<()> {
+    let mut rest = text;
+    let mut limit = 75;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        write!(writer, "{}\r\n ", &rest[..end])?;
+        rest = &rest[end..];
+        // The space starting each continuation line counts towards it
+        limit = 74;
+    }
+    write!(writer, "{}\r\n", rest)?;
+    Ok(())
+}
+
+/// Everyone in a room as a list for an event title, e.g. `alice, bob & carol`
+fn members(room: &[String]) -> String {
+    match room {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} & {}", rest.join(", "), last),
+    }
+}
+
+/// Writes a schedule as an iCalendar file, with an all-day event on each
+/// round's date for every room, e.g. `Roommates: alice & bob`, so that
+/// everyone can add the rounds to their calendar. Each event's id comes from
+/// its date and who is in the room, so importing the same schedule again
+/// updates the events instead of adding them twice
+pub fn write_calendar<W: Write>(
+    rounds: &[Solution],
+    dates: &[String],
+    title: &str,
+    mut writer: W,
+) -> Result<()> {
+    let stamp = humantime::format_rfc3339_seconds(SystemTime::now())
+        .to_string()
+        .replace(['-', ':'], "");
+    line(&mut writer, "BEGIN:VCALENDAR")?;
+    line(&mut writer, "VERSION:2.0")?;
+    line(&mut writer, "PRODID:-//picker//schedule//EN")?;
+    line(&mut writer, "CALSCALE:GREGORIAN")?;
+    for (i, (round, date)) in rounds.iter().zip(dates).enumerate() {
+        let start = date.parse::<Date>()?;
+        let end = start.after(Cadence::Day, 1);
+        for (j, room) in round.rooms.iter().enumerate() {
+            let id = hash_config(format!("{} {}", date, room.join("\n")).as_bytes());
+            let mut description = format!("Round {}", i + 1);
+            if let Some(name) = round.names.get(j) {
+                description.push_str(&format!(", room {}", name));
+            }
+            line(&mut writer, "BEGIN:VEVENT")?;
+            line(&mut writer, &format!("UID:{}@picker", id))?;
+            line(&mut writer, &format!("DTSTAMP:{}", stamp))?;
+            line(
+                &mut writer,
+                &format!("DTSTART;VALUE=DATE:{}", start.to_string().replace('-', "")),
+            )?;
+            line(
+                &mut writer,
+                &format!("DTEND;VALUE=DATE:{}", end.to_string().replace('-', "")),
+            )?;
+            line(
+                &mut writer,
+                &format!(
+                    "SUMMARY:{}",
+                    escape(&format!("{}: {}", title, members(room)))
+                ),
+            )?;
+            line(
+                &mut writer,
+                &format!("DESCRIPTION:{}", escape(&description)),
+            )?;
+            line(&mut writer, "END:VEVENT")?;
+        }
+    }
+    line(&mut writer, "END:VCALENDAR")?;
+    Ok(())
+}
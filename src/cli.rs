@@ -0,0 +1,631 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, time::Duration};
+
+use crate::{config::Algorithm, date::Date};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "picker",
+    version,
+    about = "Pairs people into rooms based on their preferences",
+    after_help = "Exit codes:
+  0  success
+  1  any other error
+  2  the config couldn't be parsed or failed validation, or the arguments were invalid
+  3  the best solution found still has unpreferred matchups
+  4  no solution keeps every never_pair matchup apart
+  130  quit with Ctrl+C. During a search, the first Ctrl+C stops it and
+       prints the best solution found so far instead
+
+Environment variables:
+  PICKER_SOLUTIONS, PICKER_SEED and PICKER_TIMEOUT override the matching
+  [settings] values, and are overridden by command line options. No other
+  settings are read from the environment"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Only print the final result, without any progress logs
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more detail while running. Can be repeated for even more
+    #[arg(long, short, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Never color the output
+    #[arg(long, global = true)]
+    pub no_color: bool,
+}
+
+impl Cli {
+    pub fn verbosity(&self) -> u8 {
+        if self.quiet {
+            0
+        } else {
+            1 + self.verbose
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Generate solutions for a config file and print an optimal one
+    #[command(after_help = "Environment variables:
+  PICKER_SOLUTIONS  like --solutions, overriding settings.solutions
+  PICKER_TIMEOUT    like --timeout, overriding settings.timeout
+  PICKER_SEED       like --seed, overriding settings.seed
+These are the only settings read from the environment, and command line
+options take precedence over them")]
+    Solve(SolveArgs),
+    /// Check a config file for problems without solving it
+    Validate(ValidateArgs),
+    /// Write a commented starter config file
+    Init(InitArgs),
+    /// Print statistics about the generated candidate solutions
+    Stats(StatsArgs),
+    /// Explain why each room in a solution was formed
+    Explain(ExplainArgs),
+    /// Assign everyone one other person to give a gift to, Secret Santa
+    /// style, instead of sharing rooms
+    Santa(SantaArgs),
+    /// Seat everyone in the grid from [layout], scoring who sits next to who
+    /// instead of who shares a room
+    Seat(SeatArgs),
+    /// Split everyone into the teams from [teams], keeping friends together
+    /// and an attribute balanced between teams
+    Teams(TeamsArgs),
+    /// Solve the config for several rounds in a row, keeping apart anyone who
+    /// already shared a room in an earlier round
+    Schedule(ScheduleArgs),
+    /// List, show or remove the runs recorded by `picker solve --record`
+    History(HistoryArgs),
+    /// Take people out of a saved solution or add them to it, only re-solving
+    /// the rooms that change
+    Amend(AmendArgs),
+    /// Show what changed between two solutions written by `picker solve
+    /// --format json`
+    Diff(DiffArgs),
+    /// Draw the preferences and a solution's rooms as a Graphviz DOT graph
+    Graph(GraphArgs),
+    /// Re-solve a config file every time it is saved
+    Watch(WatchArgs),
+    /// Convert a preference survey export into a config file
+    Import(ImportArgs),
+    /// Add a person to a TOML config file, keeping its comments and layout
+    AddPerson(AddPersonArgs),
+    /// Remove a person from a TOML config file, along with every mention of
+    /// them in other people's lists
+    RemovePerson(RemovePersonArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct AddPersonArgs {
+    /// Name of the person to add
+    pub name: String,
+
+    /// Path to the config file
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Comma separated names the person would like to share a room with
+    #[arg(long, value_delimiter = ',')]
+    pub preferred: Vec<String>,
+
+    /// Comma separated names the person would rather not share a room with
+    #[arg(long, value_delimiter = ',')]
+    pub unpreferred: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RemovePersonArgs {
+    /// Name of the person to remove
+    pub name: String,
+
+    /// Path to the config file
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Path to the survey export
+    pub survey: PathBuf,
+
+    /// Format of the survey export
+    #[arg(long, value_enum, default_value_t = ImportFormat::Csv)]
+    pub format: ImportFormat,
+
+    /// Shorthand for `--format google-forms`
+    #[arg(long)]
+    pub google_forms: bool,
+
+    /// TOML file saying which form questions hold the respondent's name and
+    /// their `preferred` and `unpreferred` answers. Needed for Google Forms
+    #[arg(long)]
+    pub mapping: Option<PathBuf>,
+
+    /// Header of the column holding each person's name
+    #[arg(long, default_value = "name")]
+    pub name_column: String,
+
+    /// Write the config to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ImportFormat {
+    /// One row per person, with `preferred` and `unpreferred` columns
+    Csv,
+    /// A Google Forms response export, read with a `--mapping` file
+    GoogleForms,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// Format used to print each new solution
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+}
+
+#[derive(Args, Debug)]
+pub struct SantaArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// Format used to print who gives to whom
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the assignment to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct SeatArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// Format used to print the seating chart
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the seating chart to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct TeamsArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// Format used to print the teams
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the teams to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ScheduleArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// How many rounds to schedule
+    #[arg(long, required_unless_present = "round_robin")]
+    pub rounds: Option<usize>,
+
+    /// Schedule a full round robin instead, where everyone shares a room with
+    /// everyone else exactly once, with the most preferred rounds first
+    #[arg(long, conflicts_with = "rounds")]
+    pub round_robin: bool,
+
+    /// Date of the first round, e.g. 2024-09-02, so that each round is
+    /// printed with its date
+    #[arg(long, value_name = "DATE")]
+    pub start: Option<Date>,
+
+    /// How far apart the rounds' dates are
+    #[arg(long, value_enum, default_value_t = Cadence::Week, requires = "start")]
+    pub every: Cadence,
+
+    /// What each event is called with `--format ics`, before who is in the
+    /// room, e.g. "Lab partners"
+    #[arg(long, default_value = "Roommates")]
+    pub title: String,
+
+    /// Format used to print the schedule
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the schedule to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct AmendArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// The solution to change, as written by `picker solve --format json`
+    #[arg(long)]
+    pub solution: PathBuf,
+
+    /// Comma separated names of people leaving
+    #[arg(long, value_delimiter = ',', required_unless_present = "add")]
+    pub remove: Vec<String>,
+
+    /// Comma separated names of people joining, who need to be in the config
+    #[arg(long, value_delimiter = ',')]
+    pub add: Vec<String>,
+
+    /// Format used to print the amended solution
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the amended solution to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The earlier solution
+    pub old: PathBuf,
+
+    /// The later solution
+    pub new: PathBuf,
+
+    /// Format used to print the changes
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the changes to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// A solution previously written with `--format json` to draw the rooms
+    /// of. If not given, the config is solved first
+    #[arg(long, conflicts_with = "no_solution")]
+    pub solution: Option<PathBuf>,
+
+    /// Only draw the preferences, without solving the config
+    #[arg(long)]
+    pub no_solution: bool,
+
+    /// Write the graph to this file instead of stdout
+    #[arg(long, short, visible_alias = "out")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(Args, Debug)]
+pub struct ExplainArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// A solution previously written with `--format json`. If not given, the
+    /// config is solved first
+    #[arg(long)]
+    pub solution: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// Path to write the config file to
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Comma separated names to pre-populate the config with
+    #[arg(long, value_delimiter = ',')]
+    pub people: Vec<String>,
+
+    /// Overwrite the file if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Path to the config file, or `-` to read it from stdin
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Format of the config file. Detected from the file extension if not given
+    #[arg(long, value_enum)]
+    pub config_format: Option<ConfigFormat>,
+
+    /// Event from the config's `[event]` table to use
+    #[arg(long)]
+    pub event: Option<String>,
+}
+
+/// Options shared by every subcommand that generates solutions
+#[derive(Args, Debug)]
+pub struct GenerateArgs {
+    /// Path to the config file, or `-` to read it from stdin
+    #[arg(default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Format of the config file. Detected from the file extension if not given
+    #[arg(long, value_enum)]
+    pub config_format: Option<ConfigFormat>,
+
+    /// Event from the config's `[event]` table to use
+    #[arg(long)]
+    pub event: Option<String>,
+
+    /// Number of candidate solutions to generate, overriding `settings.solutions`
+    #[arg(long)]
+    pub solutions: Option<u64>,
+
+    /// Keep generating solutions until this much time has passed (e.g. `30s`),
+    /// instead of generating a fixed number of them
+    #[arg(long, value_parser = humantime::parse_duration, conflicts_with = "solutions")]
+    pub timeout: Option<Duration>,
+
+    /// Seed for the random number generator, overriding `settings.seed`
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// How to find solutions, overriding `settings.solver`
+    #[arg(long, value_enum)]
+    pub solver: Option<Algorithm>,
+
+    /// How many random starting points the hill climbing solver improves,
+    /// overriding `settings.restarts`
+    #[arg(long)]
+    pub restarts: Option<u64>,
+
+    /// Which side from [sides] proposes in the Gale–Shapley solver,
+    /// overriding `settings.proposing_side`
+    #[arg(long, value_name = "SIDE")]
+    pub proposing_side: Option<String>,
+
+    /// People who share a room of their own, e.g. `alice:bob`, which is kept
+    /// fixed while everyone else is arranged. Can be repeated, and adds to
+    /// [locked]
+    #[arg(long, value_name = "A:B")]
+    pub lock: Vec<String>,
+
+    /// Keep generating solutions after finding one that nothing could beat,
+    /// overriding `settings.early_stop`
+    #[arg(long)]
+    pub no_early_stop: bool,
+
+    /// Call everyone by a pseudonym such as `Person 3` in all output, so that
+    /// it can be shared. The pseudonyms and the names they stand for are kept
+    /// in this CSV file, and anyone already in it keeps their pseudonym
+    #[arg(long, value_name = "KEY_FILE")]
+    pub anonymize: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// How many people to list in the unpreferred matchup ranking
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+
+    /// How many pairs to list by how many of the optimal solutions they're in
+    #[arg(long, default_value_t = 10)]
+    pub top_pairs: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct SolveArgs {
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// Format used to print the chosen solution
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Write the chosen solution to this file instead of stdout
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+
+    /// Print the chosen solution by filling in this template instead of in a
+    /// `--format`, e.g. to write an announcement. Templates use Jinja syntax,
+    /// with `rooms`, `people`, `scores`, `summary` and `attributes` to fill
+    /// them in with, and are escaped for HTML if the file ends in `.html`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["format", "all", "top_k", "pareto"])]
+    pub template: Option<PathBuf>,
+
+    /// Print every optimal solution instead of one chosen at random
+    #[arg(long, conflicts_with = "top_k")]
+    pub all: bool,
+
+    /// Print up to this many optimal solutions instead of one
+    #[arg(long, value_name = "N")]
+    pub top_k: Option<usize>,
+
+    /// Also print `picker stats` for the candidate solutions to stderr,
+    /// including histograms of their counts
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Also print how each person did, worst off first, from whether they
+    /// got someone they listed to whether they share with someone they'd
+    /// rather not. Doesn't work with `--format csv`
+    #[arg(long, conflicts_with_all = ["all", "top_k", "pareto"])]
+    pub people: bool,
+
+    /// Validate the config and check whether it can be solved, without solving it
+    #[arg(long, conflicts_with_all = ["all", "top_k", "interactive"])]
+    pub dry_run: bool,
+
+    /// Print every solution that no other solution beats on fewer unpreferred,
+    /// more preferred and fewer accepted matchups all at once, one per set of
+    /// counts, to show what each trade-off between them costs
+    #[arg(long, conflicts_with_all = ["all", "top_k", "interactive", "dry_run"])]
+    pub pareto: bool,
+
+    /// Prompt to accept the solution or re-roll another optimal one
+    #[arg(long, conflicts_with_all = ["all", "top_k"])]
+    pub interactive: bool,
+
+    /// JSON file of the solutions used in earlier runs. Pairs that shared a
+    /// room before no longer count as preferred, and anyone in an unpreferred
+    /// matchup last time is kept out of one where possible. The file is only
+    /// read, unless `--commit` is given. Can be given more than once to
+    /// combine several people's histories
+    #[arg(long, conflicts_with_all = ["all", "top_k", "pareto"])]
+    pub history: Vec<PathBuf>,
+
+    /// Add the chosen solution to the first `--history` file, with the date,
+    /// seed and a hash of the config, so that later runs avoid repeating it
+    #[arg(long, requires = "history", conflicts_with_all = ["dry_run", "anonymize"])]
+    pub commit: bool,
+
+    /// Never put pairs from `--history` in a room together again, instead of
+    /// only no longer counting them as preferred
+    #[arg(long, requires = "history")]
+    pub forbid_repeats: bool,
+
+    /// How long it takes for a pair from `--history` to count half as much,
+    /// e.g. "4 weeks", overriding `settings.repeat_half_life`
+    #[arg(long, value_parser = humantime::parse_duration, requires = "history", conflicts_with = "forbid_repeats")]
+    pub half_life: Option<Duration>,
+
+    /// Record the run, with its seed and the chosen solution, in the history
+    /// database that `picker history` reads
+    #[arg(long, conflicts_with_all = ["all", "top_k", "pareto", "dry_run", "anonymize"])]
+    pub record: bool,
+
+    /// History database to record the run in, instead of
+    /// ~/.local/share/picker/history.db. Implies `--record`
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["all", "top_k", "pareto", "dry_run", "anonymize"])]
+    pub db: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub command: HistoryCommand,
+
+    /// History database to read, instead of ~/.local/share/picker/history.db
+    #[arg(long, value_name = "PATH", global = true)]
+    pub db: Option<PathBuf>,
+
+    /// Format used to print the runs
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    pub format: Format,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// List every recorded run, oldest first
+    List,
+    /// Print everything recorded about a run, including its solution
+    Show {
+        /// The run's id, as shown by `picker history list`
+        id: i64,
+    },
+    /// Remove the most recent run
+    Undo {
+        /// Remove the latest solution from this `picker solve --history` file
+        /// instead of the database
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Remove a run by its id
+    Drop {
+        /// The run's id, as shown by `picker history list`, or its position
+        /// from 1 in a `--file`
+        id: i64,
+
+        /// Remove the solution from this `picker solve --history` file
+        /// instead of the database
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Show how often each person has had preferred, accepted and unpreferred
+    /// roommates across every recorded run, and who they've shared with
+    Report {
+        /// Config whose preferences the runs are judged by
+        #[arg(long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Read the runs from this `picker solve --history` file instead of
+        /// the database
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Combine several `picker solve --history` files into one, in date
+    /// order, keeping only one copy of any run that is in more than one
+    Merge {
+        /// The history files to combine
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+
+        /// File to write the combined history to, instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// How often the rounds of a dated schedule happen
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cadence {
+    Day,
+    Week,
+    Fortnight,
+    /// On the same day of each month, or the month's last day if it is
+    /// shorter
+    Month,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// Colored, human readable output
+    Text,
+    /// Pretty printed JSON
+    Json,
+    /// One row per room, with a column for each person and then the room's
+    /// category
+    Csv,
+    /// A summary and a table of the rooms, ready to paste into a wiki or an
+    /// issue. Output without rooms is the text output in a code block
+    Markdown,
+    /// A standalone page with a summary and the rooms, colored by how good
+    /// each matchup is, for printing or emailing. Output without rooms is the
+    /// text output as it would be printed
+    Html,
+    /// An iCalendar file with an event for every room in every round, for
+    /// `picker schedule` with `--start`
+    Ics,
+}
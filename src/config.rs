@@ -0,0 +1,1521 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env, fmt, fs, io, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{cli::ConfigFormat, error::Failure, location::Locations, suggest::closest};
+use anyhow::{anyhow, Context, Result};
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Settings {
+    pub solutions: u64,
+    pub seed: Option<u64>,
+    /// When set, solutions are generated until this much time has passed,
+    /// instead of generating a fixed number of them
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub timeout: Option<Duration>,
+    /// How many people share each room
+    #[serde(default = "default_room_size")]
+    pub room_size: usize,
+    /// How many people go in each room, when the rooms aren't all the same
+    /// size. Overrides `room_size` and `odd_policy` when set, and has to add
+    /// up to everyone
+    pub group_sizes: Option<Vec<usize>>,
+    /// Only treat names as the same person if they are spelled exactly the
+    /// same, instead of ignoring case and surrounding whitespace
+    #[serde(default)]
+    pub strict_names: bool,
+    /// What to do with people that have no entry in [preferred] or
+    /// [unpreferred]. If not set, they are treated as listing nobody, with a
+    /// warning from `validate`
+    pub missing_policy: Option<MissingPolicy>,
+    /// What to do when the roster can't be split evenly into rooms of
+    /// `settings.room_size`. If not set, the last room is left with spare
+    /// beds, with a warning from `validate`
+    pub odd_policy: Option<OddPolicy>,
+    /// Whether a pair needs to list each other to count as preferred
+    #[serde(default)]
+    pub preference_mode: PreferenceMode,
+    /// How solutions are found. If not set, small rosters are solved
+    /// exhaustively and anything else randomly, as is any roster given
+    /// `--solutions` or `--timeout`, or their environment variables
+    pub solver: Option<Algorithm>,
+    /// The annealing solver's starting temperature. Higher temperatures
+    /// accept worse moves more often early on
+    #[serde(default = "default_temperature")]
+    pub temperature: f64,
+    /// How many moves the annealing solver tries
+    #[serde(default = "default_iterations")]
+    pub iterations: u64,
+    /// How many moves the tabu search solver makes
+    #[serde(default = "default_tabu_iterations")]
+    pub tabu_iterations: u64,
+    /// How many moves someone who was just swapped has to sit out in tabu
+    /// search, so that the search doesn't undo its own moves
+    #[serde(default = "default_tabu_tenure")]
+    pub tabu_tenure: u64,
+    /// How many random starting points the hill climbing solver improves
+    #[serde(default = "default_restarts")]
+    pub restarts: u64,
+    /// Whether the random and hill climbing solvers stop as soon as they find
+    /// a solution that nothing could beat
+    #[serde(default = "default_early_stop")]
+    pub early_stop: bool,
+    /// Whether the random and exhaustive solvers keep every solution on the
+    /// Pareto front for `--pareto`, instead of only the best ones
+    #[serde(skip)]
+    pub pareto: bool,
+    /// When set, the random and hill climbing solvers keep going until the
+    /// best solution stops improving, instead of making a fixed number of
+    /// attempts
+    pub convergence: Option<Convergence>,
+    /// How solutions are compared to find the best one
+    #[serde(default)]
+    pub objective: Objective,
+    /// How many solutions the genetic solver keeps in each generation
+    #[serde(default = "default_population")]
+    pub population: usize,
+    /// How many generations the genetic solver breeds
+    #[serde(default = "default_generations")]
+    pub generations: u64,
+    /// Which side from [sides] proposes in the Gale–Shapley solver. If not
+    /// set, the first side alphabetically does
+    pub proposing_side: Option<String>,
+    /// How long it takes for a pair from `picker solve --history` to count
+    /// half as much against sharing a room again, e.g. "4 weeks". If not
+    /// set, every earlier pair counts the same however old it is
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub repeat_half_life: Option<Duration>,
+    /// How many of the latest runs from `picker solve --history`, or rounds
+    /// of `picker schedule`, no pair may share a room again within. Pairs
+    /// from before then only count against sharing a room as they would
+    /// without it. If not set, no repeat is ruled out
+    pub repeat_window: Option<usize>,
+}
+
+#[derive(Deserialize, clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Algorithm {
+    /// Generate many random solutions and keep the best ones
+    Random,
+    /// Check every possible pairing, giving every optimal one. Only works
+    /// when every room is for two people, and for small rosters
+    Exhaustive,
+    /// Find an optimal pairing directly with a maximum weight matching. Only
+    /// works when every room is for two people
+    Exact,
+    /// Find a stable pairing, where no two people would both rather share a
+    /// room with each other. Only works when every room is for two people
+    Stable,
+    /// Improve many random solutions with swaps until no swap helps, and
+    /// keep the best
+    Hillclimb,
+    /// Make the best swap between rooms over and over, even when it makes
+    /// things worse, while recently swapped people sit out for a while
+    Tabu,
+    /// Start from a random solution and improve it by swapping people
+    /// between rooms, accepting fewer worse swaps as it cools down
+    Anneal,
+    /// Search every way of filling the rooms, skipping any branch that can't
+    /// beat the best solution found so far. Stops early at `settings.timeout`
+    BranchAndBound,
+    /// Solve the pairing as an integer linear program with CBC. Only works
+    /// when every room is for two people, and when picker is built with the
+    /// `ilp` feature
+    Ilp,
+    /// Breed a population of random solutions, combining the rooms of good
+    /// ones and mutating them with random swaps
+    Genetic,
+    /// Find a stable pairing across the two sides in [sides] with the
+    /// Gale–Shapley algorithm, which is the best stable pairing for whichever
+    /// side proposes. Only works when every room is for two people
+    GaleShapley,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PreferenceMode {
+    /// A pair is only as good as the lower of the tiers they listed each
+    /// other in
+    #[default]
+    Mutual,
+    /// A pair is as good as the higher of the tiers they listed each other in
+    Either,
+    /// Each person's wishes are scored on their own, so a solution's counts
+    /// are of people and who they share a room with, rather than of pairs
+    Directed,
+}
+
+/// The counts a solution can be compared on
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Count {
+    Unpreferred,
+    StronglyPreferred,
+    /// The total weight of the preferred matchups
+    Weight,
+    Preferred,
+    Acceptable,
+    Accepted,
+    /// How unhappy the worst-off person is with their least wanted roommate,
+    /// see `Solution::unfairness`
+    WorstOff,
+}
+
+/// How many counts there are in `Count`
+pub const COUNTS: usize = 7;
+
+/// The order solutions are compared in when no other is given
+const DEFAULT_ORDER: [Count; 5] = [
+    Count::Unpreferred,
+    Count::StronglyPreferred,
+    Count::Weight,
+    Count::Preferred,
+    Count::Acceptable,
+];
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Objective {
+    /// Compare one count at a time, only moving on to the next count when
+    /// two solutions tie. Fewer is better for `unpreferred` and `worst_off`,
+    /// and more is better for every other count
+    Lexicographic {
+        #[serde(default = "default_order")]
+        order: Vec<Count>,
+    },
+    /// Add up every matchup's weight for its kind, and compare the totals.
+    /// Ties are broken in the default lexicographic order
+    Weighted {
+        #[serde(default = "default_strongly_preferred_weight")]
+        strongly_preferred_weight: i64,
+        #[serde(default = "default_preferred_weight")]
+        preferred_weight: i64,
+        #[serde(default = "default_acceptable_weight")]
+        acceptable_weight: i64,
+        #[serde(default)]
+        accepted_weight: i64,
+        #[serde(default = "default_unpreferred_weight")]
+        unpreferred_weight: i64,
+    },
+}
+
+impl Default for Objective {
+    fn default() -> Self {
+        Self::Lexicographic {
+            order: default_order(),
+        }
+    }
+}
+
+impl Objective {
+    /// Turns a solution's counts, in the order of `Count`, into a key where
+    /// the larger key is the better solution. Keys are linear in the counts,
+    /// so the key of two sets of rooms together is the sum of their keys
+    pub fn key(&self, counts: [i64; COUNTS]) -> [i64; COUNTS] {
+        let signed = |count: Count| match count {
+            Count::Unpreferred | Count::WorstOff => -counts[count as usize],
+            _ => counts[count as usize],
+        };
+        // Counts listed twice only count the first time
+        let lexicographic = |order: &[Count], key: &mut [i64]| {
+            let mut seen = [false; COUNTS];
+            let order = order
+                .iter()
+                .filter(|&&x| !mem::replace(&mut seen[x as usize], true));
+            for (slot, &count) in key.iter_mut().zip(order) {
+                *slot = signed(count);
+            }
+        };
+        let mut key = [0; COUNTS];
+        match self {
+            Self::Lexicographic { order } => lexicographic(order, &mut key),
+            Self::Weighted {
+                strongly_preferred_weight,
+                preferred_weight,
+                acceptable_weight,
+                accepted_weight,
+                unpreferred_weight,
+            } => {
+                key[0] = [
+                    (Count::StronglyPreferred, strongly_preferred_weight),
+                    (Count::Preferred, preferred_weight),
+                    (Count::Acceptable, acceptable_weight),
+                    (Count::Accepted, accepted_weight),
+                    (Count::Unpreferred, unpreferred_weight),
+                ]
+                .iter()
+                .map(|(count, weight)| *weight * counts[*count as usize])
+                .sum();
+                lexicographic(&DEFAULT_ORDER, &mut key[1..]);
+            }
+        }
+        key
+    }
+
+    /// Which part of a key holds how unfair the rooms are, if solutions are
+    /// compared on it
+    pub fn worst_off_slot(&self) -> Option<usize> {
+        let mut probe = [0; COUNTS];
+        probe[Count::WorstOff as usize] = 1;
+        self.key(probe).iter().position(|&x| x != 0)
+    }
+
+    /// Whether solutions are compared on a count at all
+    pub fn uses(&self, count: Count) -> bool {
+        match self {
+            Self::Lexicographic { order } => order.contains(&count),
+            Self::Weighted { .. } => count != Count::WorstOff,
+        }
+    }
+
+    /// Whether this is the default objective, which finding a perfect
+    /// solution relies on
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Convergence {
+    /// How many attempts in a row can fail to beat the best solution before
+    /// giving up
+    pub window: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingPolicy {
+    /// Refuse to load the config
+    Error,
+    /// Treat them as listing nobody
+    Empty,
+    /// Treat them as preferring everyone, and listing nobody as unpreferred
+    AcceptAll,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OddPolicy {
+    /// Put the people left over into one of the other rooms, making a room
+    /// of three when rooms are for two
+    Triple,
+    /// Give the people left over a room of their own, leaving one person on
+    /// their own when rooms are for two
+    Single,
+    /// Refuse to solve the config
+    Error,
+}
+
+const fn default_room_size() -> usize {
+    2
+}
+
+const fn default_temperature() -> f64 {
+    2.0
+}
+
+const fn default_iterations() -> u64 {
+    200_000
+}
+
+const fn default_tabu_iterations() -> u64 {
+    1000
+}
+
+const fn default_tabu_tenure() -> u64 {
+    7
+}
+
+const fn default_early_stop() -> bool {
+    true
+}
+
+fn default_order() -> Vec<Count> {
+    DEFAULT_ORDER.to_vec()
+}
+
+const fn default_strongly_preferred_weight() -> i64 {
+    3
+}
+
+const fn default_preferred_weight() -> i64 {
+    2
+}
+
+const fn default_acceptable_weight() -> i64 {
+    1
+}
+
+const fn default_unpreferred_weight() -> i64 {
+    -10
+}
+
+const fn default_restarts() -> u64 {
+    100
+}
+
+const fn default_population() -> usize {
+    100
+}
+
+const fn default_generations() -> u64 {
+    500
+}
+
+/// Reads a setting override from an environment variable, if it is set
+fn env_override<T, E: fmt::Display>(
+    name: &str,
+    parse: impl Fn(&str) -> Result<T, E>,
+) -> Result<Option<T>> {
+    let Ok(text) = env::var(name) else {
+        return Ok(None);
+    };
+    let value = parse(text.trim())
+        .map_err(|x| Failure::Config(anyhow!("{} has an invalid value '{}': {}", name, text, x)))?;
+    Ok(Some(value))
+}
+
+impl Settings {
+    /// Overrides settings with the `PICKER_SOLUTIONS`, `PICKER_SEED` and
+    /// `PICKER_TIMEOUT` environment variables, where they are set. No other
+    /// settings are read from the environment
+    pub fn apply_env(&mut self) -> Result<()> {
+        let solutions = env_override("PICKER_SOLUTIONS", str::parse)?;
+        let timeout = env_override("PICKER_TIMEOUT", humantime::parse_duration)?;
+        if solutions.is_some() && timeout.is_some() {
+            return Err(Failure::Config(anyhow!(
+                "PICKER_SOLUTIONS and PICKER_TIMEOUT can't both be set"
+            ))
+            .into());
+        }
+
+        self.limit(solutions, timeout);
+        if let Some(seed) = env_override("PICKER_SEED", str::parse)? {
+            self.seed = Some(seed);
+        }
+        Ok(())
+    }
+
+    /// Asks for a number of solutions or a time limit, as `--solutions` and
+    /// `--timeout` and their environment variables do. Either only makes
+    /// sense when sampling, so small rosters aren't switched to the
+    /// exhaustive solver
+    pub fn limit(&mut self, solutions: Option<u64>, timeout: Option<Duration>) {
+        if let Some(solutions) = solutions {
+            self.solutions = solutions;
+            self.timeout = None;
+            self.convergence = None;
+        }
+        if let Some(timeout) = timeout {
+            self.timeout = Some(timeout);
+        }
+        if self.solver.is_none() && (solutions.is_some() || timeout.is_some()) {
+            self.solver = Some(Algorithm::Random);
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    humantime::parse_duration(&text)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+/// A preference list, written either as a list of names that all get a weight
+/// of 1, or as a table of names to weights, e.g. `{ bob = 3, carol = 1 }`
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(from = "PreferenceList")]
+pub struct Preferences {
+    weights: Vec<(String, u32)>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PreferenceList {
+    Names(Vec<String>),
+    Weighted(BTreeMap<String, u32>),
+}
+
+impl From<PreferenceList> for Preferences {
+    fn from(list: PreferenceList) -> Self {
+        let weights = match list {
+            PreferenceList::Names(names) => names.into_iter().map(|x| (x, 1)).collect(),
+            PreferenceList::Weighted(weights) => weights.into_iter().collect(),
+        };
+        Self { weights }
+    }
+}
+
+impl Preferences {
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.weights.iter().map(|x| &x.0)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names().any(|x| x == name)
+    }
+
+    pub fn weight(&self, name: &str) -> Option<u32> {
+        self.weights.iter().find(|x| x.0 == name).map(|x| x.1)
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.weights.retain(|x| x.0 != name);
+    }
+
+    /// Lists someone with a weight, unless they are already listed
+    pub fn insert(&mut self, name: &str, weight: u32) {
+        if !self.contains(name) {
+            self.weights.push((name.to_string(), weight));
+        }
+    }
+
+    /// Renames everyone listed, dropping anyone listed twice and returning
+    /// who was. The first weight they were listed with is kept
+    fn rename(&mut self, resolve: impl Fn(&str) -> String) -> Vec<String> {
+        let mut renamed = Self::default();
+        let mut repeated = vec![];
+        for (name, weight) in mem::take(&mut self.weights) {
+            let name = resolve(&name);
+            if renamed.contains(&name) {
+                repeated.push(name);
+            } else {
+                renamed.weights.push((name, weight));
+            }
+        }
+        *self = renamed;
+        repeated
+    }
+
+    /// Replaces any shorthands with the people they stand for, giving them
+    /// the shorthand's weight. Names listed explicitly keep their own weight
+    fn expand(&mut self, person: &str, shorthands: &Shorthands) -> Result<()> {
+        let mut expanded = vec![];
+        for (name, weight) in std::mem::take(&mut self.weights) {
+            match shorthands.expand(&name)? {
+                Some(names) => expanded.extend(names.iter().map(|x| (x, weight))),
+                None => self.weights.push((name, weight)),
+            }
+        }
+        for (other, weight) in expanded {
+            if other != person && !self.contains(other) {
+                self.weights.push((other.clone(), weight));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value describing a person, such as their grade or gender
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Attribute {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Renames everyone in a list, dropping anyone listed twice and returning
+/// who was
+fn rename_list(names: &mut Vec<String>, resolve: impl Fn(&str) -> String) -> Vec<String> {
+    let mut renamed = vec![];
+    let mut repeated = vec![];
+    for name in mem::take(names) {
+        let name = resolve(&name);
+        if renamed.contains(&name) {
+            repeated.push(name);
+        } else {
+            renamed.push(name);
+        }
+    }
+    *names = renamed;
+    repeated
+}
+
+/// Renames the keys of a table, describing any that end up as the same
+/// person in `clashes`
+fn rename_keys<V>(
+    table_name: &str,
+    table: &mut HashMap<String, V>,
+    resolve: impl Fn(&str) -> String,
+    clashes: &mut Vec<String>,
+) {
+    let mut keys = mem::take(table).into_iter().collect::<Vec<_>>();
+    keys.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut spellings = BTreeMap::<String, Vec<String>>::new();
+    for (key, value) in keys {
+        let name = resolve(&key);
+        spellings.entry(name.clone()).or_default().push(key);
+        table.entry(name).or_insert(value);
+    }
+    for (name, spellings) in spellings {
+        if spellings.len() > 1 {
+            let mut spellings = spellings
+                .iter()
+                .map(|x| format!("'{}'", x))
+                .collect::<Vec<_>>();
+            let last = spellings.pop().unwrap_or_default();
+            clashes.push(format!(
+                "'{}' has more than one entry in [{}], as {} and {}",
+                name,
+                table_name,
+                spellings.join(", "),
+                last
+            ));
+        }
+    }
+}
+
+/// An actual room that people can be put in
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Room {
+    pub capacity: usize,
+}
+
+/// A grid of seats for `picker seat`
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+/// How `picker teams` splits everyone up
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Teams {
+    /// How many teams to make
+    pub count: usize,
+    /// A numeric attribute from [attributes], e.g. `"skill"`, to keep even
+    /// between the teams
+    pub balance: Option<String>,
+    /// How far apart the teams' averages of `balance` are allowed to be
+    #[serde(default)]
+    pub tolerance: f64,
+}
+
+/// Stands for everyone else when used in a preference list
+pub const WILDCARD: &str = "*";
+/// Marks a reference to a group from [groups] in a preference list
+pub const GROUP_PREFIX: char = '@';
+
+/// What the shorthands in preference lists stand for
+struct Shorthands<'a> {
+    everyone: &'a [String],
+    groups: &'a HashMap<String, Vec<String>>,
+}
+
+impl Shorthands<'_> {
+    /// The people a name stands for, or `None` if it isn't a shorthand
+    fn expand(&self, name: &str) -> Result<Option<&[String]>> {
+        if name == WILDCARD {
+            return Ok(Some(self.everyone));
+        }
+        let Some(group) = name.strip_prefix(GROUP_PREFIX) else {
+            return Ok(None);
+        };
+        if let Some(members) = self.groups.get(group) {
+            return Ok(Some(members));
+        }
+        let hint = closest(group, self.groups.keys())
+            .map(|x| format!(", did you mean '{}{}'?", GROUP_PREFIX, x))
+            .unwrap_or_default();
+        Err(Failure::Config(anyhow!(
+            "'{}' refers to a group that isn't in [groups]{}",
+            name,
+            hint
+        ))
+        .into())
+    }
+
+    fn expand_list(&self, names: &mut Vec<String>, person: &str) -> Result<()> {
+        let mut expanded = vec![];
+        for name in std::mem::take(names) {
+            match self.expand(&name)? {
+                Some(others) => expanded.extend(others),
+                None => names.push(name),
+            }
+        }
+        for other in expanded {
+            if other != person && !names.contains(other) {
+                names.push(other.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Config {
+    pub settings: Settings,
+    /// Everyone to be put in a room. If empty, everyone with an entry in any
+    /// of the preference tables is used instead
+    #[serde(default)]
+    pub people: Vec<String>,
+    /// Pairs that list each other here are ranked above preferred matchups
+    #[serde(default)]
+    pub strongly_preferred: HashMap<String, Preferences>,
+    #[serde(default)]
+    pub preferred: HashMap<String, Preferences>,
+    /// Pairs that list each other here are ranked below preferred matchups,
+    /// but above pairs that merely don't mind each other
+    #[serde(default)]
+    pub acceptable: HashMap<String, Preferences>,
+    #[serde(default)]
+    pub unpreferred: HashMap<String, Vec<String>>,
+    /// Pairs that always share a room, whatever their preferences
+    #[serde(default)]
+    pub must_pair: HashMap<String, String>,
+    /// Pairs that may never share a room, unlike unpreferred ones which are
+    /// only avoided where possible
+    #[serde(default)]
+    pub never_pair: HashMap<String, Vec<String>>,
+    /// Rooms decided before solving, e.g. `front = ["alice", "bob"]`, which
+    /// are kept exactly as they are while everyone else is arranged. A key
+    /// that names a room from [rooms] puts them in that room, and otherwise
+    /// they get the smallest room that fits them
+    #[serde(default)]
+    pub locked: BTreeMap<String, Vec<String>>,
+    /// Named groups of people, which preference lists can refer to as
+    /// `"@name"` instead of listing every member
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+    /// Two sides, e.g. `mentors` and `mentees`, where every room takes one
+    /// person from each side. People on the same side never share a room
+    #[serde(default)]
+    pub sides: BTreeMap<String, Vec<String>>,
+    /// Values describing each person, e.g. `alice = { grade = 10 }`, for
+    /// constraints and scoring to use
+    #[serde(default)]
+    pub attributes: HashMap<String, BTreeMap<String, Attribute>>,
+    /// Named rooms to fill, e.g. `room_101 = { capacity = 2 }`. If empty,
+    /// everyone is put in unnamed rooms of `settings.room_size`
+    #[serde(default)]
+    pub rooms: BTreeMap<String, Room>,
+    /// The grid `picker seat` seats everyone in, e.g. `rows = 5, cols = 6`
+    pub layout: Option<Layout>,
+    /// How `picker teams` splits everyone into teams
+    pub teams: Option<Teams>,
+    /// Other names each person goes by, e.g. `bob = ["Bob", "Robert"]`, which
+    /// are replaced with the person's name before solving
+    #[serde(default)]
+    pub aliases: HashMap<String, Vec<String>>,
+    /// Where each key was written, if the config is a single TOML file
+    #[serde(skip)]
+    pub locations: Locations,
+    /// Keys that don't mean anything to picker, such as misspelt tables, as
+    /// either `table` or `settings.key`
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+    /// Names that were listed more than once in the same list, as the table,
+    /// whose list it was and the name. The repeats are dropped while loading
+    #[serde(skip)]
+    pub repeated: Vec<(String, Option<String>, String)>,
+}
+
+/// The tables whose keys are something other than a person
+const GROUPINGS: [&str; 3] = ["groups", "sides", "locked"];
+
+/// Every key a config can have at the top level
+pub const CONFIG_KEYS: [&str; 18] = [
+    "settings",
+    "people",
+    "strongly_preferred",
+    "preferred",
+    "acceptable",
+    "unpreferred",
+    "must_pair",
+    "never_pair",
+    "locked",
+    "groups",
+    "sides",
+    "attributes",
+    "rooms",
+    "layout",
+    "teams",
+    "aliases",
+    "include",
+    "event",
+];
+
+/// Every key the [settings] table can have
+pub const SETTINGS_KEYS: [&str; 23] = [
+    "solutions",
+    "seed",
+    "timeout",
+    "room_size",
+    "group_sizes",
+    "strict_names",
+    "missing_policy",
+    "odd_policy",
+    "preference_mode",
+    "solver",
+    "temperature",
+    "iterations",
+    "tabu_iterations",
+    "tabu_tenure",
+    "restarts",
+    "early_stop",
+    "convergence",
+    "objective",
+    "population",
+    "generations",
+    "proposing_side",
+    "repeat_half_life",
+    "repeat_window",
+];
+
+/// Finds the keys in a config that picker doesn't know about
+fn find_unknown_keys(document: &Value) -> Vec<String> {
+    let Some(document) = document.as_object() else {
+        return vec![];
+    };
+    let mut unknown = document
+        .keys()
+        .filter(|x| !CONFIG_KEYS.contains(&x.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    if let Some(settings) = document.get("settings").and_then(|x| x.as_object()) {
+        unknown.extend(
+            settings
+                .keys()
+                .filter(|x| !SETTINGS_KEYS.contains(&x.as_str()))
+                .map(|x| format!("settings.{}", x)),
+        );
+    }
+    unknown.sort();
+    unknown
+}
+
+fn lists(table: &HashMap<String, Vec<String>>, a: &str, b: &str) -> bool {
+    table.get(a).is_some_and(|x| x.iter().any(|x| x == b))
+}
+
+impl Config {
+    /// Everyone that needs a room, in a stable order
+    pub fn people(&self) -> Vec<String> {
+        if !self.people.is_empty() {
+            return self.people.clone();
+        }
+        self.strongly_preferred
+            .keys()
+            .chain(self.preferred.keys())
+            .chain(self.acceptable.keys())
+            .chain(self.unpreferred.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Replaces every alias from [aliases] with the name of the person it
+    /// belongs to, wherever a name can be written in the config. Unless
+    /// `settings.strict_names` is set, names that only differ in case or
+    /// surrounding whitespace are also treated as the same person, and written
+    /// the way they first appear in the people list or as a table key
+    fn resolve_names(&mut self) -> Result<()> {
+        let strict = self.settings.strict_names;
+        let normalize = |name: &str| {
+            if strict {
+                name.to_string()
+            } else {
+                name.trim().to_lowercase()
+            }
+        };
+        let spelling = |name: &str| {
+            if strict {
+                name.to_string()
+            } else {
+                name.trim().to_string()
+            }
+        };
+
+        let mut canonical = self.aliases.iter().collect::<Vec<_>>();
+        canonical.sort();
+        let mut aliases = HashMap::<String, &String>::new();
+        for (person, names) in canonical {
+            for alias in names {
+                match aliases.insert(normalize(alias), person) {
+                    Some(other) if normalize(other) != normalize(person) => {
+                        return Err(Failure::Config(anyhow!(
+                            "'{}' is an alias of both '{}' and '{}'",
+                            alias,
+                            other,
+                            person
+                        ))
+                        .into())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut keys = self
+            .strongly_preferred
+            .keys()
+            .chain(self.preferred.keys())
+            .chain(self.acceptable.keys())
+            .chain(self.unpreferred.keys())
+            .chain(self.never_pair.keys())
+            .chain(self.must_pair.keys())
+            .chain(self.attributes.keys())
+            .chain(self.aliases.keys())
+            .collect::<Vec<_>>();
+        keys.sort();
+        let mut spellings = HashMap::new();
+        for name in self.people.iter().chain(keys) {
+            // An alias's spelling says nothing about how the person's own name
+            // is written
+            let key = normalize(name);
+            if aliases.get(&key).is_some_and(|x| normalize(x) != key) {
+                continue;
+            }
+            spellings.entry(key).or_insert_with(|| spelling(name));
+        }
+
+        let resolve = |name: &str| {
+            let name = aliases.get(&normalize(name)).map_or(name, |x| x.as_str());
+            spellings
+                .get(&normalize(name))
+                .cloned()
+                .unwrap_or_else(|| spelling(name))
+        };
+
+        let mut clashes = vec![];
+        let mut repeated = vec![];
+        for name in rename_list(&mut self.people, resolve) {
+            repeated.push(("people".to_string(), None, name));
+        }
+        for (name, table) in [
+            ("strongly_preferred", &mut self.strongly_preferred),
+            ("preferred", &mut self.preferred),
+            ("acceptable", &mut self.acceptable),
+        ] {
+            rename_keys(name, table, resolve, &mut clashes);
+            for (person, list) in table.iter_mut() {
+                for other in list.rename(resolve) {
+                    repeated.push((name.to_string(), Some(person.clone()), other));
+                }
+            }
+        }
+        for (name, table) in [
+            ("unpreferred", &mut self.unpreferred),
+            ("never_pair", &mut self.never_pair),
+        ] {
+            rename_keys(name, table, resolve, &mut clashes);
+            for (person, list) in table.iter_mut() {
+                for other in rename_list(list, resolve) {
+                    repeated.push((name.to_string(), Some(person.clone()), other));
+                }
+            }
+        }
+        rename_keys("must_pair", &mut self.must_pair, resolve, &mut clashes);
+        for partner in self.must_pair.values_mut() {
+            *partner = resolve(partner);
+        }
+        for (group, members) in self.groups.iter_mut() {
+            for member in rename_list(members, resolve) {
+                repeated.push(("groups".to_string(), Some(group.clone()), member));
+            }
+        }
+        for (side, members) in self.sides.iter_mut() {
+            for member in rename_list(members, resolve) {
+                repeated.push(("sides".to_string(), Some(side.clone()), member));
+            }
+        }
+        for (room, members) in self.locked.iter_mut() {
+            for member in rename_list(members, resolve) {
+                repeated.push(("locked".to_string(), Some(room.clone()), member));
+            }
+        }
+        rename_keys("attributes", &mut self.attributes, resolve, &mut clashes);
+
+        if !clashes.is_empty() {
+            clashes.sort();
+            return Err(Failure::Config(anyhow!(
+                "The same person has more than one entry in a table:\n  {}",
+                clashes.join("\n  ")
+            ))
+            .into());
+        }
+        repeated.sort();
+        self.repeated = repeated;
+        Ok(())
+    }
+
+    /// Gives everyone a new name wherever a name can be written in the
+    /// config, for `--anonymize`. Each new name has to be different, and
+    /// [aliases] and where each key was written are dropped, as they would
+    /// give the old names away
+    pub fn rename_people(&mut self, rename: impl Fn(&str) -> String) {
+        let mut clashes = vec![];
+        rename_list(&mut self.people, &rename);
+        for (name, table) in [
+            ("strongly_preferred", &mut self.strongly_preferred),
+            ("preferred", &mut self.preferred),
+            ("acceptable", &mut self.acceptable),
+        ] {
+            rename_keys(name, table, &rename, &mut clashes);
+            for list in table.values_mut() {
+                list.rename(&rename);
+            }
+        }
+        for (name, table) in [
+            ("unpreferred", &mut self.unpreferred),
+            ("never_pair", &mut self.never_pair),
+        ] {
+            rename_keys(name, table, &rename, &mut clashes);
+            for list in table.values_mut() {
+                rename_list(list, &rename);
+            }
+        }
+        rename_keys("must_pair", &mut self.must_pair, &rename, &mut clashes);
+        for partner in self.must_pair.values_mut() {
+            *partner = rename(partner);
+        }
+        for members in self
+            .groups
+            .values_mut()
+            .chain(self.sides.values_mut())
+            .chain(self.locked.values_mut())
+        {
+            rename_list(members, &rename);
+        }
+        // Rooms locked from the command line are keyed by who is in them
+        self.locked = mem::take(&mut self.locked)
+            .into_iter()
+            .map(|(key, members)| {
+                if key.starts_with("--lock ") {
+                    (format!("--lock {}", members.join(":")), members)
+                } else {
+                    (key, members)
+                }
+            })
+            .collect();
+        rename_keys("attributes", &mut self.attributes, &rename, &mut clashes);
+        for (table, list, name) in &mut self.repeated {
+            // Lists in [groups], [sides] and [locked] belong to a group, side
+            // or room rather than a person
+            if let Some(person) = list
+                .as_mut()
+                .filter(|_| !GROUPINGS.contains(&table.as_str()))
+            {
+                *person = rename(person);
+            }
+            *name = rename(name);
+        }
+        self.aliases.clear();
+        self.locations = Locations::default();
+    }
+
+    /// Fills in the entries people are missing from [preferred] and
+    /// [unpreferred], according to `settings.missing_policy`
+    fn apply_missing_policy(&mut self) -> Result<()> {
+        let people = self.people();
+        match self.settings.missing_policy {
+            Some(MissingPolicy::Error) => {
+                let missing = people
+                    .iter()
+                    .filter(|x| {
+                        !self.preferred.contains_key(*x) || !self.unpreferred.contains_key(*x)
+                    })
+                    .map(|x| format!("'{}'", x))
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    return Err(Failure::Config(anyhow!(
+                        "{} are missing an entry in [preferred] or [unpreferred], which settings.missing_policy doesn't allow",
+                        missing.join(", ")
+                    ))
+                    .into());
+                }
+            }
+            Some(MissingPolicy::AcceptAll) => {
+                for person in people {
+                    self.preferred.entry(person).or_insert_with(|| {
+                        PreferenceList::Names(vec![WILDCARD.to_string()]).into()
+                    });
+                }
+            }
+            Some(MissingPolicy::Empty) | None => {}
+        }
+        Ok(())
+    }
+
+    /// Replaces every `"*"` in a preference list with everyone else, and
+    /// every `"@group"` with the members of that group
+    fn expand_shorthands(&mut self) -> Result<()> {
+        let everyone = self.people();
+        let shorthands = Shorthands {
+            everyone: &everyone,
+            groups: &self.groups,
+        };
+        for (name, table) in [
+            ("strongly_preferred", &mut self.strongly_preferred),
+            ("preferred", &mut self.preferred),
+            ("acceptable", &mut self.acceptable),
+        ] {
+            for (person, list) in table.iter_mut() {
+                list.expand(person, &shorthands)
+                    .with_context(|| format!("In [{}] for '{}'", name, person))?;
+            }
+        }
+        for (name, table) in [
+            ("unpreferred", &mut self.unpreferred),
+            ("never_pair", &mut self.never_pair),
+        ] {
+            for (person, list) in table.iter_mut() {
+                shorthands
+                    .expand_list(list, person)
+                    .with_context(|| format!("In [{}] for '{}'", name, person))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The rooms to fill and how many people fit in each, in a stable order.
+    /// Unnamed rooms are used if the config doesn't name any, either from
+    /// `settings.group_sizes` or with any people left over handled by
+    /// `settings.odd_policy`
+    pub fn capacities(&self) -> Vec<(Option<&str>, usize)> {
+        if let (true, Some(sizes)) = (self.rooms.is_empty(), &self.settings.group_sizes) {
+            return sizes
+                .iter()
+                .filter(|&&x| x > 0)
+                .map(|&x| (None, x))
+                .collect();
+        }
+        if self.rooms.is_empty() {
+            let size = self.settings.room_size;
+            let people = self.people().len();
+            let (count, leftover) = (people / size.max(1), people % size.max(1));
+            let mut capacities = vec![(None, size); count];
+            match self.settings.odd_policy {
+                _ if leftover == 0 => {}
+                Some(OddPolicy::Triple) if count > 0 => capacities[count - 1].1 += leftover,
+                Some(OddPolicy::Single) => capacities.push((None, leftover)),
+                _ => capacities.push((None, size)),
+            }
+            return capacities;
+        }
+        self.rooms
+            .iter()
+            .map(|(name, room)| (Some(name.as_str()), room.capacity))
+            .collect()
+    }
+
+    /// Whether everyone is paired up, with every room for two people apart
+    /// from one of their own for whoever `settings.odd_policy` leaves over
+    pub fn pair_rooms(&self) -> bool {
+        self.capacities()
+            .iter()
+            .all(|x| x.1 == 2 || *x == (None, 1))
+    }
+
+    /// Adds a room to [locked] from the command line, writing each name the
+    /// way the config does, or as it is if nobody in the config matches it
+    pub fn lock(&mut self, members: &[&str]) {
+        let people = self.people();
+        let same = |a: &str, b: &str| {
+            if self.settings.strict_names {
+                a == b
+            } else {
+                a.trim().to_lowercase() == b.trim().to_lowercase()
+            }
+        };
+        let members = members
+            .iter()
+            .map(|name| {
+                people
+                    .iter()
+                    .find(|person| {
+                        same(person, name)
+                            || self
+                                .aliases
+                                .get(*person)
+                                .is_some_and(|x| x.iter().any(|alias| same(alias, name)))
+                    })
+                    .cloned()
+                    .unwrap_or_else(|| name.to_string())
+            })
+            .collect::<Vec<_>>();
+        self.locked
+            .insert(format!("--lock {}", members.join(":")), members);
+    }
+
+    /// The preference tables from the highest tier to the lowest
+    pub fn tiers(&self) -> [(&str, &HashMap<String, Preferences>); 3] {
+        [
+            ("strongly_preferred", &self.strongly_preferred),
+            ("preferred", &self.preferred),
+            ("acceptable", &self.acceptable),
+        ]
+    }
+
+    /// How highly `a` listed `b`: 3 for strongly preferred, 2 for preferred,
+    /// 1 for acceptable and 0 if `a` didn't list `b` at all. People without an
+    /// entry in a table don't list anyone in it
+    pub fn tier(&self, a: &str, b: &str) -> u8 {
+        let tiers = self.tiers();
+        tiers
+            .iter()
+            .position(|(_, table)| table.get(a).is_some_and(|x| x.contains(b)))
+            .map_or(0, |i| (tiers.len() - i) as u8)
+    }
+
+    /// The weight `a` gave `b` in the highest tier they listed them in
+    pub fn weight(&self, a: &str, b: &str) -> u64 {
+        self.tiers()
+            .iter()
+            .find_map(|(_, table)| table.get(a).and_then(|x| x.weight(b)))
+            .unwrap_or_default() as u64
+    }
+
+    /// How much a pair wants to share a room, adding up the weight each of
+    /// them gave the other
+    pub fn pair_weight(&self, a: &str, b: &str) -> u64 {
+        self.weight(a, b) + self.weight(b, a)
+    }
+
+    /// Whether any preference was given a weight other than 1
+    pub fn weighted(&self) -> bool {
+        self.tiers()
+            .iter()
+            .flat_map(|(_, table)| table.values())
+            .any(|x| x.weights.iter().any(|x| x.1 != 1))
+    }
+
+    /// Whether the strongly preferred or acceptable tiers are used
+    pub fn tiered(&self) -> bool {
+        !self.strongly_preferred.is_empty() || !self.acceptable.is_empty()
+    }
+
+    /// The pairs that always share a room, in a stable order
+    pub fn must_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs = self
+            .must_pair
+            .iter()
+            .map(|(a, b)| (a.clone(), b.clone()))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        pairs
+    }
+
+    /// Who a person has to share a room with, if anyone
+    pub fn partner(&self, person: &str) -> Option<&str> {
+        self.must_pair.iter().find_map(|(a, b)| {
+            if a == person {
+                Some(b.as_str())
+            } else if b == person {
+                Some(a.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether `a` listed `b` as unpreferred
+    pub fn unprefers(&self, a: &str, b: &str) -> bool {
+        lists(&self.unpreferred, a, b)
+    }
+
+    /// Whether `a` and `b` are forbidden from sharing a room, by either of them
+    /// listing the other in [never_pair] or by both being on the same side in
+    /// [sides]
+    pub fn forbids(&self, a: &str, b: &str) -> bool {
+        lists(&self.never_pair, a, b)
+            || lists(&self.never_pair, b, a)
+            || self
+                .sides
+                .values()
+                .any(|x| x.iter().any(|x| x == a) && x.iter().any(|x| x == b))
+    }
+
+    /// Whether any matchups are forbidden, so that failing to find a solution
+    /// means they couldn't all be kept apart
+    pub fn has_forbidden(&self) -> bool {
+        !self.never_pair.is_empty() || !self.sides.is_empty()
+    }
+}
+
+/// Works out the format of a config file from its extension, falling back to
+/// TOML for anything unrecognised
+pub fn detect_format(path: &Path) -> ConfigFormat {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("yaml" | "yml") => ConfigFormat::Yaml,
+        Some("json") => ConfigFormat::Json,
+        _ => ConfigFormat::Toml,
+    }
+}
+
+fn read_text(path: &Path) -> Result<String> {
+    if path == Path::new("-") {
+        Ok(io::read_to_string(io::stdin())?)
+    } else {
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))
+    }
+}
+
+fn parse_text<T: DeserializeOwned>(text: &str, format: ConfigFormat) -> Result<T> {
+    let result = match format {
+        ConfigFormat::Toml => toml::from_str(text).map_err(|x| Failure::Config(x.into()))?,
+        ConfigFormat::Yaml => serde_yaml::from_str(text).map_err(|x| Failure::Config(x.into()))?,
+        ConfigFormat::Json => serde_json::from_str(text).map_err(|x| Failure::Config(x.into()))?,
+    };
+    Ok(result)
+}
+
+/// Merges `other` into `base`, with tables merged key by key and anything
+/// else in `other` replacing what was in `base`
+fn merge(base: &mut Value, other: Value) {
+    match (base, other) {
+        (Value::Object(base), Value::Object(other)) => {
+            for (key, value) in other {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, other) => *base = other,
+    }
+}
+
+/// Merges the files a config includes, in order, underneath the config
+/// itself. Included paths are relative to the file that includes them
+fn resolve_includes(path: &Path, mut document: Value, seen: &mut Vec<PathBuf>) -> Result<Value> {
+    let includes = match document.as_object_mut().and_then(|x| x.remove("include")) {
+        Some(includes) => Vec::<PathBuf>::deserialize(includes).map_err(|x| {
+            Failure::Config(anyhow!(
+                "`include` in {} must be a list of paths: {}",
+                path.display(),
+                x
+            ))
+        })?,
+        None => vec![],
+    };
+
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let mut merged = Value::Object(Default::default());
+    for include in includes {
+        let include = directory.join(include);
+        let canonical = include
+            .canonicalize()
+            .with_context(|| format!("Couldn't find included config {}", include.display()))?;
+        if seen.contains(&canonical) {
+            return Err(Failure::Config(anyhow!(
+                "{} ends up including itself",
+                canonical.display()
+            ))
+            .into());
+        }
+
+        seen.push(canonical.clone());
+        let text = read_text(&canonical)?;
+        let included = parse_text(&text, detect_format(&canonical))
+            .with_context(|| format!("Couldn't parse included config {}", canonical.display()))?;
+        merge(&mut merged, resolve_includes(&canonical, included, seen)?);
+        seen.pop();
+    }
+    merge(&mut merged, document);
+    Ok(merged)
+}
+
+/// Merges an event's section over the rest of the config, so that it only
+/// has to list what is different about the event
+fn select_event(path: &Path, mut document: Value, event: Option<&str>) -> Result<Value> {
+    let events = document.as_object_mut().and_then(|x| x.remove("event"));
+    let Some(Value::Object(mut events)) = events else {
+        return match (events, event) {
+            (Some(_), _) => Err(Failure::Config(anyhow!(
+                "`event` in {} must be a table of events",
+                path.display()
+            ))
+            .into()),
+            (None, Some(event)) => Err(Failure::Config(anyhow!(
+                "--event {} was given, but the config doesn't have any events",
+                event
+            ))
+            .into()),
+            (None, None) => Ok(document),
+        };
+    };
+
+    let names = events.keys().cloned().collect::<Vec<_>>().join(", ");
+    let Some(event) = event else {
+        return Err(Failure::Config(anyhow!(
+            "The config has events, choose one of them with --event: {}",
+            names
+        ))
+        .into());
+    };
+    let Some(section) = events.remove(event) else {
+        let hint = match closest(event, events.keys()) {
+            Some(name) => format!("did you mean '{}'?", name),
+            None => format!("expected one of: {}", names),
+        };
+        return Err(Failure::Config(anyhow!(
+            "The config has no event called '{}', {}",
+            event,
+            hint
+        ))
+        .into());
+    };
+    merge(&mut document, section);
+    Ok(document)
+}
+
+/// Parses a config from TOML text the way `parse_config` reads a file, for
+/// tests
+#[cfg(test)]
+pub fn parse_toml(text: &str) -> Result<Config> {
+    let mut result: Config = parse_text(text, ConfigFormat::Toml)?;
+    result.resolve_names()?;
+    result.apply_missing_policy()?;
+    result.expand_shorthands()?;
+    Ok(result)
+}
+
+/// A random roster for tests, where each of `people` people lists a few of
+/// the others in each tier, some with weights, and a few as unpreferred.
+/// `settings` is added to the [settings] table
+#[cfg(test)]
+pub fn random_config(rng: &mut impl rand::Rng, people: usize, settings: &str) -> Config {
+    let names = (0..people).map(|x| format!("p{}", x)).collect::<Vec<_>>();
+    let mut tables = [
+        ("strongly_preferred", 0.1, String::new()),
+        ("preferred", 0.3, String::new()),
+        ("acceptable", 0.2, String::new()),
+        ("unpreferred", 0.2, String::new()),
+    ];
+    for (i, name) in names.iter().enumerate() {
+        for (table, chance, text) in &mut tables {
+            let mut listed = vec![];
+            for (j, other) in names.iter().enumerate() {
+                if j == i || !rng.gen_bool(*chance) {
+                    continue;
+                }
+                listed.push(match *table {
+                    "unpreferred" => format!("\"{}\"", other),
+                    _ => format!("\"{}\" = {}", other, rng.gen_range(1..4)),
+                });
+            }
+            let list = match *table {
+                "unpreferred" => format!("[{}]", listed.join(", ")),
+                _ => format!("{{ {} }}", listed.join(", ")),
+            };
+            text.push_str(&format!("{} = {}\n", name, list));
+        }
+    }
+    let mut text = format!("people = {:?}\n", names);
+    text.push_str(&format!("[settings]\nsolutions = 1000\n{}\n", settings));
+    for (table, _, entries) in tables {
+        text.push_str(&format!("[{}]\n{}", table, entries));
+    }
+    parse_toml(&text).unwrap()
+}
+
+/// Reads a config file, where a path of `-` means the config is read from
+/// stdin. Files listed in its `include` are merged in first, with later files
+/// overriding earlier ones and the config itself overriding all of them. If
+/// the config has an `[event]` table, the chosen event is merged over the rest
+pub fn parse_config<T: AsRef<Path>>(
+    path: T,
+    format: Option<ConfigFormat>,
+    event: Option<&str>,
+) -> Result<Config> {
+    let path = path.as_ref();
+    let format = format.unwrap_or_else(|| detect_format(path));
+    let text = read_text(path)?;
+    let document = parse_text::<Value>(&text, format)?;
+
+    // Parsing the text directly keeps the line numbers in error messages, so
+    // the merged document is only used when it's needed
+    let direct = document.get("include").is_none() && document.get("event").is_none();
+    let mut result: Config = if direct && event.is_none() {
+        let mut result: Config = parse_text(&text, format)?;
+        result.unknown_keys = find_unknown_keys(&document);
+        result
+    } else {
+        let mut seen = path.canonicalize().into_iter().collect();
+        let merged = resolve_includes(path, document, &mut seen)?;
+        let merged = select_event(path, merged, event)?;
+        let unknown_keys = find_unknown_keys(&merged);
+        let mut result: Config =
+            serde_json::from_value(merged).map_err(|x| Failure::Config(x.into()))?;
+        result.unknown_keys = unknown_keys;
+        result
+    };
+    if format == ConfigFormat::Toml {
+        result.locations = Locations::parse(path, &text);
+    }
+    result.resolve_names()?;
+    result.apply_missing_policy()?;
+    result.expand_shorthands()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odd_policies_place_the_people_left_over() {
+        let config = |settings: &str| {
+            parse_toml(&format!(
+                "people = [\"a\", \"b\", \"c\", \"d\", \"e\"]\n[settings]\nsolutions = 1\n{}",
+                settings
+            ))
+            .unwrap()
+        };
+        let sizes = |config: &Config| config.capacities().iter().map(|x| x.1).collect::<Vec<_>>();
+        assert_eq!(sizes(&config("")), [2, 2, 2]);
+        assert_eq!(sizes(&config("odd_policy = \"triple\"")), [2, 3]);
+        assert_eq!(sizes(&config("odd_policy = \"single\"")), [2, 2, 1]);
+        assert_eq!(
+            sizes(&config("odd_policy = \"single\"\nroom_size = 3")),
+            [3, 2]
+        );
+        assert!(config("odd_policy = \"single\"").pair_rooms());
+    }
+
+    #[test]
+    fn a_number_of_solutions_means_sampling() {
+        let config = parse_toml(
+            "people = [\"a\", \"b\"]\n[settings]\nsolutions = 1\nconvergence = { window = 5 }",
+        )
+        .unwrap();
+        let mut settings = config.settings.clone();
+        settings.limit(Some(3), None);
+        assert_eq!(settings.solutions, 3);
+        assert!(settings.convergence.is_none());
+        assert_eq!(settings.solver, Some(Algorithm::Random));
+
+        let mut settings = config.settings;
+        settings.solver = Some(Algorithm::Exact);
+        settings.limit(None, Some(Duration::from_secs(1)));
+        assert_eq!(settings.solver, Some(Algorithm::Exact));
+    }
+}
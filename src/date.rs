@@ -0,0 +1,91 @@
+use anyhow::{anyhow, bail, Error, Result};
+use std::{fmt, str::FromStr};
+
+use crate::cli::Cadence;
+
+/// A day on the calendar, written as `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+fn leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    /// Days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    fn days(self) -> i64 {
+        let year = self.year - (self.month <= 2) as i64;
+        let era = year.div_euclid(400);
+        let of_era = year - era * 400;
+        let month = (self.month as i64 + 9) % 12;
+        let of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let of_era_days = of_era * 365 + of_era / 4 - of_era / 100 + of_year;
+        era * 146097 + of_era_days - 719468
+    }
+
+    fn from_days(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let of_era = days - era * 146097;
+        let year_of_era = (of_era - of_era / 1460 + of_era / 36524 - of_era / 146096) / 365;
+        let of_year = of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * of_year + 2) / 153;
+        let day = (of_year - (153 * month + 2) / 5 + 1) as u32;
+        let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+        Self { year, month, day }
+    }
+
+    /// The date a number of steps of the cadence later. Months keep the same
+    /// day of the month where they can, and otherwise use their last day
+    pub fn after(self, cadence: Cadence, steps: u32) -> Self {
+        let days = match cadence {
+            Cadence::Day => 1,
+            Cadence::Week => 7,
+            Cadence::Fortnight => 14,
+            Cadence::Month => {
+                let months = self.year * 12 + self.month as i64 - 1 + steps as i64;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                let day = self.day.min(days_in_month(year, month));
+                return Self { year, month, day };
+            }
+        };
+        Self::from_days(self.days() + days * steps as i64)
+    }
+}
+
+impl FromStr for Date {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = || anyhow!("'{}' isn't a date like 2024-09-02", text);
+        let mut parts = text.trim().splitn(3, '-');
+        let mut part = || parts.next().ok_or_else(invalid);
+        let year = part()?.parse::<i64>().map_err(|_| invalid())?;
+        let month = part()?.parse::<u32>().map_err(|_| invalid())?;
+        let day = part()?.parse::<u32>().map_err(|_| invalid())?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            bail!("{} has no day {} in month {}", year, day, month);
+        }
+        Ok(Self { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
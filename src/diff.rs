@@ -0,0 +1,183 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::{collections::BTreeSet, io::Write};
+
+use crate::{
+    calendar,
+    cli::Format,
+    output::write_plain,
+    score::{pairs, Solution},
+};
+
+/// What changed between two solutions
+#[derive(Serialize, Debug)]
+pub struct Diff {
+    /// Rooms with exactly the same people in both
+    pub unchanged: Vec<Vec<String>>,
+    /// Pairs that shared a room in the old solution but not the new one
+    pub broken: Vec<(String, String)>,
+    /// Pairs that share a room in the new solution but didn't in the old one
+    pub new: Vec<(String, String)>,
+    /// People only in the new solution
+    pub joined: Vec<String>,
+    /// People only in the old solution
+    pub left: Vec<String>,
+    /// How each count changed, from the old solution to the new one
+    pub delta: Vec<Change>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Change {
+    pub count: &'static str,
+    #[serde(skip)]
+    pub label: &'static str,
+    pub old: u64,
+    pub new: u64,
+}
+
+/// Every pair of people sharing a room, in name order
+fn pair_set(solution: &Solution) -> BTreeSet<(String, String)> {
+    solution
+        .rooms
+        .iter()
+        .flat_map(|x| pairs(x))
+        .map(|(a, b)| {
+            let (a, b) = if a < b { (a, b) } else { (b, a) };
+            (a.clone(), b.clone())
+        })
+        .collect()
+}
+
+fn room_set(room: &[String]) -> BTreeSet<&String> {
+    room.iter().collect()
+}
+
+/// Compares two solutions room by room and pair by pair
+pub fn diff(old: &Solution, new: &Solution) -> Diff {
+    let unchanged = new
+        .rooms
+        .iter()
+        .filter(|room| old.rooms.iter().any(|x| room_set(x) == room_set(room)))
+        .cloned()
+        .collect();
+    let (old_pairs, new_pairs) = (pair_set(old), pair_set(new));
+    let everyone = |x: &Solution| x.rooms.iter().flatten().cloned().collect::<BTreeSet<_>>();
+    let (before, after) = (everyone(old), everyone(new));
+    let delta = [
+        (
+            "strongly_preferred",
+            "strongly preferred matchups:",
+            old.strongly_preferred,
+            new.strongly_preferred,
+        ),
+        (
+            "preferred",
+            "preferred matchups:",
+            old.preferred,
+            new.preferred,
+        ),
+        (
+            "acceptable",
+            "acceptable matchups:",
+            old.acceptable,
+            new.acceptable,
+        ),
+        ("accepted", "accepted matchups:", old.accepted, new.accepted),
+        (
+            "unpreferred",
+            "unpreferred matchups:",
+            old.unpreferred,
+            new.unpreferred,
+        ),
+        ("weight", "preferred weight:", old.weight, new.weight),
+    ]
+    .into_iter()
+    .map(|(count, label, old, new)| Change {
+        count,
+        label,
+        old,
+        new,
+    })
+    .collect();
+    Diff {
+        unchanged,
+        broken: old_pairs.difference(&new_pairs).cloned().collect(),
+        new: new_pairs.difference(&old_pairs).cloned().collect(),
+        joined: after.difference(&before).cloned().collect(),
+        left: before.difference(&after).cloned().collect(),
+        delta,
+    }
+}
+
+/// Prints what changed, in the given format
+pub fn write_diff<W: Write>(diff: &Diff, format: Format, mut writer: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            writeln!(writer, "{}", "DIFF".green().bold())?;
+            // Counts that are zero in both solutions don't say anything
+            let changes = diff
+                .delta
+                .iter()
+                .filter(|x| x.old > 0 || x.new > 0 || x.count == "preferred")
+                .collect::<Vec<_>>();
+            let mut lines = vec![
+                ("unchanged rooms:", diff.unchanged.len().to_string()),
+                ("broken pairs:", diff.broken.len().to_string()),
+                ("new pairs:", diff.new.len().to_string()),
+            ];
+            for change in changes {
+                lines.push((
+                    change.label,
+                    format!(
+                        "{} -> {} ({:+})",
+                        change.old,
+                        change.new,
+                        change.new as i64 - change.old as i64
+                    ),
+                ));
+            }
+            let width = lines.iter().map(|x| x.0.len()).max().unwrap_or_default() + 2;
+            for (label, value) in lines {
+                writeln!(writer, " {:<width$}{}", label, value.blue())?;
+            }
+            for (title, people) in [("JOINED", &diff.joined), ("LEFT", &diff.left)] {
+                if !people.is_empty() {
+                    writeln!(writer, "{}", title.green().bold())?;
+                    for person in people {
+                        writeln!(writer, " {}", person.blue())?;
+                    }
+                }
+            }
+            for (title, pairs) in [("BROKEN", &diff.broken), ("NEW", &diff.new)] {
+                if !pairs.is_empty() {
+                    writeln!(writer, "{}", title.green().bold())?;
+                    for (a, b) in pairs {
+                        writeln!(writer, " {} & {}", a.blue(), b.blue())?;
+                    }
+                }
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_diff(diff, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, diff)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record(["change", "person_a", "person_b"])?;
+            for (change, pairs) in [("broken", &diff.broken), ("new", &diff.new)] {
+                for (a, b) in pairs {
+                    writer.write_record([change, a, b])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
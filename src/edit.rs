@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Context, Result};
+use std::{fs, path::Path};
+use toml_edit::{Array, Document, Item, Table, TableLike, Value};
+
+use crate::{
+    cli::ConfigFormat,
+    config::{detect_format, GROUP_PREFIX, WILDCARD},
+    error::Failure,
+};
+
+/// Tables with one entry per person
+const PERSON_TABLES: [&str; 8] = [
+    "strongly_preferred",
+    "preferred",
+    "acceptable",
+    "unpreferred",
+    "must_pair",
+    "never_pair",
+    "attributes",
+    "aliases",
+];
+
+/// Tables of named lists of people
+const LIST_TABLES: [&str; 3] = ["groups", "sides", "locked"];
+
+/// Tables that name people in their values, either in a list, a weighted
+/// table, or on their own
+const NAMING_TABLES: [&str; 6] = [
+    "strongly_preferred",
+    "preferred",
+    "acceptable",
+    "unpreferred",
+    "must_pair",
+    "never_pair",
+];
+
+/// A TOML config, edited without losing its comments or layout
+pub struct Editor {
+    document: Document,
+    strict: bool,
+}
+
+impl Editor {
+    pub fn read(path: &Path) -> Result<Self> {
+        if path == Path::new("-") || detect_format(path) != ConfigFormat::Toml {
+            return Err(anyhow!("Only TOML config files can be edited in place"));
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        let document = text
+            .parse::<Document>()
+            .map_err(|x| Failure::Config(x.into()))?;
+        let strict = document
+            .get("settings")
+            .and_then(|x| x.get("strict_names"))
+            .and_then(Item::as_bool)
+            .unwrap_or(false);
+        Ok(Self { document, strict })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.document.to_string())
+            .with_context(|| format!("Couldn't write {}", path.display()))
+    }
+
+    /// Whether the person is already named in the people list or as a key of
+    /// any table with one entry per person
+    fn contains(&self, name: &str) -> bool {
+        let root = self.document.as_table();
+        let listed = root
+            .get("people")
+            .and_then(Item::as_array)
+            .is_some_and(|x| {
+                x.iter()
+                    .filter_map(Value::as_str)
+                    .any(|x| same_name(self.strict, name, x))
+            });
+        listed
+            || PERSON_TABLES.iter().any(|table| {
+                root.get(table)
+                    .and_then(Item::as_table_like)
+                    .is_some_and(|x| x.iter().any(|(key, _)| same_name(self.strict, name, key)))
+            })
+    }
+
+    /// Adds a person to the people list, if the config has one, along with
+    /// their [preferred] and [unpreferred] entries
+    pub fn add_person(
+        &mut self,
+        name: &str,
+        preferred: &[String],
+        unpreferred: &[String],
+    ) -> Result<()> {
+        if name.is_empty() || name.starts_with(GROUP_PREFIX) || name == WILDCARD {
+            return Err(anyhow!("'{}' can't be used as a name", name));
+        }
+        if self.contains(name) {
+            return Err(anyhow!("'{}' is already in the config", name));
+        }
+
+        let root = self.document.as_table_mut();
+        if let Some(people) = root.get_mut("people").and_then(Item::as_array_mut) {
+            push(people, name);
+        }
+        for (table, names) in [("preferred", preferred), ("unpreferred", unpreferred)] {
+            let table = root
+                .entry(table)
+                .or_insert_with(|| Item::Table(Table::new()))
+                .as_table_like_mut()
+                .ok_or_else(|| anyhow!("[{}] isn't a table", table))?;
+            let mut list = Array::new();
+            for other in names {
+                push(&mut list, other);
+            }
+            table.insert(name, Item::Value(Value::Array(list)));
+        }
+        Ok(())
+    }
+
+    /// Removes a person from the config, along with their own entries and
+    /// every mention of them in other people's entries, [groups] and [sides].
+    /// Returns how many places they were removed from
+    pub fn remove_person(&mut self, name: &str) -> Result<usize> {
+        let strict = self.strict;
+        let same = |other: &str| same_name(strict, name, other);
+
+        // Each [event] section can have its own people and preferences too
+        let mut removed = remove_from(self.document.as_table_mut(), &same);
+        if let Some(events) = self
+            .document
+            .get_mut("event")
+            .and_then(Item::as_table_like_mut)
+        {
+            for (_, event) in events.iter_mut() {
+                if let Some(event) = event.as_table_like_mut() {
+                    removed += remove_from(event, &same);
+                }
+            }
+        }
+        if removed == 0 {
+            return Err(anyhow!("'{}' isn't in the config", name));
+        }
+        Ok(removed)
+    }
+}
+
+/// Whether two names are the same person, following the same rules as
+/// `settings.strict_names` does when loading the config
+fn same_name(strict: bool, name: &str, other: &str) -> bool {
+    if strict {
+        name == other
+    } else {
+        name.trim().to_lowercase() == other.trim().to_lowercase()
+    }
+}
+
+/// Appends a name to a list, spaced like the last name already in it so that
+/// lists written one name per line stay that way
+fn push(list: &mut Array, name: &str) {
+    let decor = (list.len() > 1).then(|| list.get(list.len() - 1).unwrap().decor().clone());
+    list.push(name);
+    if let Some(decor) = decor {
+        let index = list.len() - 1;
+        *list.get_mut(index).unwrap().decor_mut() = decor;
+    }
+}
+
+/// Removes every mention of a person from one section of the config
+fn remove_from(section: &mut dyn TableLike, same: &dyn Fn(&str) -> bool) -> usize {
+    let mut removed = 0;
+    if let Some(people) = section.get_mut("people").and_then(Item::as_array_mut) {
+        removed += retain(people, same);
+    }
+    for table in PERSON_TABLES {
+        let Some(table) = section.get_mut(table).and_then(Item::as_table_like_mut) else {
+            continue;
+        };
+        let keys = table
+            .iter()
+            .map(|x| x.0.to_string())
+            .filter(|x| same(x))
+            .collect::<Vec<_>>();
+        for key in keys {
+            table.remove(&key);
+            removed += 1;
+        }
+    }
+    for table in NAMING_TABLES {
+        let Some(table) = section.get_mut(table).and_then(Item::as_table_like_mut) else {
+            continue;
+        };
+        // A must_pair entry only names one partner, so it goes entirely
+        let partnered = table
+            .iter()
+            .filter(|x| x.1.as_str().is_some_and(same))
+            .map(|x| x.0.to_string())
+            .collect::<Vec<_>>();
+        for key in partnered {
+            table.remove(&key);
+            removed += 1;
+        }
+        for (_, item) in table.iter_mut() {
+            removed += scrub(item, same);
+        }
+    }
+    for table in LIST_TABLES {
+        let Some(table) = section.get_mut(table).and_then(Item::as_table_like_mut) else {
+            continue;
+        };
+        for (_, item) in table.iter_mut() {
+            removed += scrub(item, same);
+        }
+    }
+    removed
+}
+
+/// Removes a person from a list of names or a table of weighted names
+fn scrub(item: &mut Item, same: &dyn Fn(&str) -> bool) -> usize {
+    if let Some(list) = item.as_array_mut() {
+        return retain(list, same);
+    }
+    let Some(table) = item.as_table_like_mut() else {
+        return 0;
+    };
+    let keys = table
+        .iter()
+        .map(|x| x.0.to_string())
+        .filter(|x| same(x))
+        .collect::<Vec<_>>();
+    for key in &keys {
+        table.remove(key);
+    }
+    keys.len()
+}
+
+/// Removes a person from a list of names
+fn retain(list: &mut Array, same: &dyn Fn(&str) -> bool) -> usize {
+    let mut removed = 0;
+    let mut index = 0;
+    while index < list.len() {
+        if !list.get(index).and_then(Value::as_str).is_some_and(same) {
+            index += 1;
+            continue;
+        }
+        let value = list.remove(index);
+        // The new first name takes over the spacing after the `[`
+        if index == 0 {
+            if let Some(first) = list.get_mut(0) {
+                *first.decor_mut() = value.decor().clone();
+            }
+        }
+        removed += 1;
+    }
+    removed
+}
@@ -0,0 +1,62 @@
+use std::{error, fmt};
+
+/// Exit code for anything that doesn't have a more specific one
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code when the config couldn't be parsed or failed validation
+pub const EXIT_CONFIG: u8 = 2;
+/// Exit code when the best solution still contains unpreferred matchups
+pub const EXIT_UNPREFERRED: u8 = 3;
+/// Exit code when no solution avoids every forbidden pair
+pub const EXIT_FORBIDDEN: u8 = 4;
+/// Exit code when Ctrl+C quits the program, following the shell convention
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// Failures that end the program with their own exit code, so that scripts
+/// can tell them apart
+#[derive(Debug)]
+pub enum Failure {
+    Config(anyhow::Error),
+    Unpreferred(u64),
+    /// No solution without unpreferred matchups can exist
+    Infeasible,
+    /// No solution avoiding every [never_pair] matchup was found
+    Forbidden,
+}
+
+impl Failure {
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::Config(_) => EXIT_CONFIG,
+            Self::Unpreferred(_) | Self::Infeasible => EXIT_UNPREFERRED,
+            Self::Forbidden => EXIT_FORBIDDEN,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(error) => write!(f, "{:#}", error),
+            Self::Unpreferred(count) => write!(
+                f,
+                "The best solution found still has {} unpreferred matchup(s)",
+                count
+            ),
+            Self::Infeasible => write!(f, "Every solution has unpreferred matchups"),
+            Self::Forbidden => write!(
+                f,
+                "No solution was found that keeps every [never_pair] matchup apart"
+            ),
+        }
+    }
+}
+
+impl error::Error for Failure {}
+
+/// Works out which exit code an error should end the program with
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .chain()
+        .find_map(|x| x.downcast_ref::<Failure>())
+        .map_or(EXIT_FAILURE, Failure::exit_code)
+}
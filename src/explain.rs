@@ -0,0 +1,110 @@
+use std::io::Write;
+
+use crate::{
+    config::Config,
+    output::{self, label},
+    score::{classify, classify_room, pairs, Category, Solution},
+};
+use anyhow::Result;
+use colored::Colorize;
+
+/// How `a` listed `b`, e.g. "a prefers b", for each preference tier
+fn listing(a: &str, b: &str, tier: u8) -> Option<String> {
+    match tier {
+        3 => Some(format!("{} strongly prefers {}", a, b)),
+        2 => Some(format!("{} prefers {}", a, b)),
+        1 => Some(format!("{} finds {} acceptable", a, b)),
+        _ => None,
+    }
+}
+
+/// Describes who listed whom in the preference tables
+fn reasons(a: &str, b: &str, config: &Config) -> Vec<String> {
+    let mut result = vec![];
+    let a_tier = config.tier(a, b);
+    let b_tier = config.tier(b, a);
+    match (a_tier, b_tier) {
+        (3, 3) => result.push(format!("{} and {} strongly prefer each other", a, b)),
+        (2, 2) => result.push(format!("{} and {} prefer each other", a, b)),
+        (1, 1) => result.push(format!("{} and {} find each other acceptable", a, b)),
+        _ => {
+            for (x, y, tier, other_tier) in [(a, b, a_tier, b_tier), (b, a, b_tier, a_tier)] {
+                if let Some(listing) = listing(x, y, tier) {
+                    if other_tier == 0 {
+                        result.push(format!("{}, but not the other way round", listing));
+                    } else {
+                        result.push(listing);
+                    }
+                }
+            }
+        }
+    }
+    if config.partner(a) == Some(b) {
+        result.push(format!("{} and {} must share a room", a, b));
+    }
+    if config.unprefers(a, b) {
+        result.push(format!("{} listed {} as unpreferred", a, b));
+    }
+    if config.unprefers(b, a) {
+        result.push(format!("{} listed {} as unpreferred", b, a));
+    }
+    result
+}
+
+/// Lists a person's attributes, e.g. "grade 10, gender f"
+fn describe(person: &str, config: &Config) -> Option<String> {
+    let attributes = config.attributes.get(person)?;
+    let text = attributes
+        .iter()
+        .map(|(name, value)| format!("{} {}", name, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(text)
+}
+
+/// Explains why each room in a solution was formed
+pub fn write_explanation<W: Write>(
+    solution: &Solution,
+    config: &Config,
+    mut writer: W,
+) -> Result<()> {
+    writeln!(writer, "{}", "EXPLANATION".green().bold())?;
+    for (i, room) in solution.rooms.iter().enumerate() {
+        let category = classify_room(room, config);
+        writeln!(
+            writer,
+            " {} ({})",
+            output::describe_room(solution, i),
+            label(category)
+        )?;
+
+        // Rooms of more than two explain each pair in them separately
+        let indent = if room.len() > 2 { "    " } else { "  " };
+        for (a, b) in pairs(room) {
+            let category = classify(a, b, config);
+            if room.len() > 2 {
+                writeln!(writer, "  {} & {} ({})", a, b, label(category))?;
+            }
+            let mut reasons = reasons(a, b, config);
+            if category == Category::Accepted && reasons.is_empty() {
+                reasons.push("neither listed the other".to_string());
+            }
+            for reason in reasons {
+                writeln!(writer, "{}- {}", indent, reason)?;
+            }
+        }
+        let forced = room.iter().any(|x| config.partner(x).is_some());
+        if category == Category::Unpreferred && !forced {
+            writeln!(
+                writer,
+                "  - forced fallback, as nobody acceptable was left to pair with"
+            )?;
+        }
+        for person in room {
+            if let Some(attributes) = describe(person, config) {
+                writeln!(writer, "  - {} has {}", person, attributes)?;
+            }
+        }
+    }
+    Ok(())
+}
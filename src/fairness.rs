@@ -0,0 +1,222 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+use std::{collections::BTreeMap, io::Write};
+
+use crate::{
+    calendar,
+    cli::Format,
+    config::Config,
+    output::write_plain,
+    score::{classify, pairs, Category},
+};
+
+const CATEGORIES: [Category; 5] = [
+    Category::StronglyPreferred,
+    Category::Preferred,
+    Category::Acceptable,
+    Category::Accepted,
+    Category::Unpreferred,
+];
+
+/// How each person has fared over a series of runs
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub runs: usize,
+    pub people: Vec<Standing>,
+    /// How many runs each pair of people shared a room in, in the order of
+    /// `people`
+    pub partners: Vec<Vec<usize>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Standing {
+    pub name: String,
+    /// How many runs the person was in
+    pub runs: usize,
+    pub strongly_preferred: usize,
+    pub preferred: usize,
+    pub acceptable: usize,
+    pub accepted: usize,
+    pub unpreferred: usize,
+}
+
+impl Standing {
+    fn get(&self, category: Category) -> usize {
+        match category {
+            Category::StronglyPreferred => self.strongly_preferred,
+            Category::Preferred => self.preferred,
+            Category::Acceptable => self.acceptable,
+            Category::Accepted => self.accepted,
+            Category::Unpreferred => self.unpreferred,
+        }
+    }
+
+    fn count(&mut self, category: Category) -> &mut usize {
+        match category {
+            Category::StronglyPreferred => &mut self.strongly_preferred,
+            Category::Preferred => &mut self.preferred,
+            Category::Acceptable => &mut self.acceptable,
+            Category::Accepted => &mut self.accepted,
+            Category::Unpreferred => &mut self.unpreferred,
+        }
+    }
+}
+
+/// Counts the matchups each person has been in across the rooms of every
+/// run, judged by the current config, and how often each pair shared a room
+pub fn report(runs: &[Vec<Vec<String>>], config: &Config) -> Report {
+    let mut names = config.people();
+    for person in runs.iter().flatten().flatten() {
+        if !names.contains(person) {
+            names.push(person.clone());
+        }
+    }
+    let index = names
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (x.clone(), i))
+        .collect::<BTreeMap<_, _>>();
+    let mut people = names
+        .into_iter()
+        .map(|name| Standing {
+            name,
+            runs: 0,
+            strongly_preferred: 0,
+            preferred: 0,
+            acceptable: 0,
+            accepted: 0,
+            unpreferred: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut partners = vec![vec![0; people.len()]; people.len()];
+    for rooms in runs {
+        for room in rooms {
+            for person in room {
+                people[index[person]].runs += 1;
+            }
+            for (a, b) in pairs(room) {
+                let (i, j) = (index[a], index[b]);
+                let category = classify(a, b, config);
+                *people[i].count(category) += 1;
+                *people[j].count(category) += 1;
+                partners[i][j] += 1;
+                partners[j][i] += 1;
+            }
+        }
+    }
+    Report {
+        runs: runs.len(),
+        people,
+        partners,
+    }
+}
+
+/// Prints the report, in the given format
+pub fn write_report<W: Write>(
+    report: &Report,
+    config: &Config,
+    format: Format,
+    mut writer: W,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            writeln!(writer, "{}", "FAIRNESS".green().bold())?;
+            writeln!(writer, " {:<8}{}", "runs:", report.runs.to_string().blue())?;
+            // Tiers the config doesn't use would only ever be empty columns
+            let shown = CATEGORIES
+                .into_iter()
+                .filter(|x| {
+                    config.tiered()
+                        || !matches!(x, Category::StronglyPreferred | Category::Acceptable)
+                })
+                .collect::<Vec<_>>();
+            let width = report
+                .people
+                .iter()
+                .map(|x| x.name.len())
+                .max()
+                .unwrap_or_default()
+                .max(6);
+            let mut header = format!(" {:<width$}  {:>4}", "person", "runs");
+            for category in &shown {
+                header.push_str(&format!("  {}", category.repr()));
+            }
+            writeln!(writer, "{}", header.bold())?;
+            for standing in &report.people {
+                let mut line = format!(
+                    " {}  {:>4}",
+                    format!("{:<width$}", standing.name).blue(),
+                    standing.runs
+                );
+                for &category in &shown {
+                    let count = standing.get(category);
+                    line.push_str(&format!("  {:>w$}", count, w = category.repr().len()));
+                }
+                writeln!(writer, "{}", line)?;
+            }
+
+            // Columns are numbered, as names would make the matrix too wide
+            writeln!(writer)?;
+            writeln!(writer, "{}", "PARTNERS".green().bold())?;
+            let mut header = format!(" {:>3} {:<width$}", "", "");
+            for i in 0..report.people.len() {
+                header.push_str(&format!(" {:>3}", i + 1));
+            }
+            writeln!(writer, "{}", header.bold())?;
+            for (i, standing) in report.people.iter().enumerate() {
+                let mut line = format!(
+                    " {:>3} {}",
+                    (i + 1).to_string().bold(),
+                    format!("{:<width$}", standing.name).blue()
+                );
+                for (j, &count) in report.partners[i].iter().enumerate() {
+                    let cell = match count {
+                        _ if i == j => " ".to_string(),
+                        0 => ".".to_string(),
+                        _ => count.to_string(),
+                    };
+                    line.push_str(&format!(" {:>3}", cell));
+                }
+                writeln!(writer, "{}", line)?;
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_report(report, config, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, report)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            let mut header = vec![
+                "person",
+                "runs",
+                "strongly_preferred",
+                "preferred",
+                "acceptable",
+                "accepted",
+                "unpreferred",
+            ];
+            header.extend(report.people.iter().map(|x| x.name.as_str()));
+            writer.write_record(header)?;
+            for (standing, partners) in report.people.iter().zip(&report.partners) {
+                let mut record = vec![standing.name.clone(), standing.runs.to_string()];
+                record.extend(
+                    CATEGORIES
+                        .iter()
+                        .map(|&x| standing.get(x))
+                        .chain(partners.iter().copied())
+                        .map(|x| x.to_string()),
+                );
+                writer.write_record(record)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
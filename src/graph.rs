@@ -0,0 +1,96 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{
+    config::Config,
+    score::{pairs, Solution},
+};
+
+/// How an edge for how someone listed someone else is drawn, by tier from
+/// `Config::tier`, or for [unpreferred] after them
+const STYLES: [&str; 4] = [
+    "color=\"#7a9a01\"",
+    "color=\"#2e8b57\"",
+    "color=\"#006400\", penwidth=2",
+    // Unpreferred
+    "color=\"#c0392b\", style=dashed",
+];
+
+/// Quotes a name for DOT, so that any name is a valid node id
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes the preferences as a Graphviz DOT graph, with everyone as a node
+/// and an arrow for every person they listed, colored by the list. Mutual
+/// listings are drawn as one edge with an arrow at each end. If a solution is
+/// given, everyone who shares a room in it is joined by a bold black edge,
+/// labelled with the room's name if it has one
+pub fn write_dot<W: Write>(
+    config: &Config,
+    solution: Option<&Solution>,
+    mut writer: W,
+) -> Result<()> {
+    let people = config.people();
+    writeln!(writer, "digraph picker {{")?;
+    writeln!(writer, "    node [shape=box, style=rounded];")?;
+    for person in &people {
+        writeln!(writer, "    {};", quote(person))?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "    // Preferences")?;
+    // Unpreferred wins over a preference, as it does when scoring
+    let style = |a: &str, b: &str| {
+        if config.unprefers(a, b) {
+            Some(STYLES[3])
+        } else {
+            config.tier(a, b).checked_sub(1).map(|x| STYLES[x as usize])
+        }
+    };
+    for (i, a) in people.iter().enumerate() {
+        for b in &people[i + 1..] {
+            match (style(a, b), style(b, a)) {
+                (Some(x), Some(y)) if x == y => {
+                    writeln!(
+                        writer,
+                        "    {} -> {} [{}, dir=both];",
+                        quote(a),
+                        quote(b),
+                        x
+                    )?;
+                }
+                (x, y) => {
+                    if let Some(x) = x {
+                        writeln!(writer, "    {} -> {} [{}];", quote(a), quote(b), x)?;
+                    }
+                    if let Some(y) = y {
+                        writeln!(writer, "    {} -> {} [{}];", quote(b), quote(a), y)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(solution) = solution {
+        writeln!(writer)?;
+        writeln!(writer, "    // Rooms")?;
+        for (i, room) in solution.rooms.iter().enumerate() {
+            let label = match solution.names.get(i) {
+                Some(name) => format!(", label={}", quote(name)),
+                None => String::new(),
+            };
+            for (a, b) in pairs(room) {
+                writeln!(
+                    writer,
+                    "    {} -> {} [dir=none, penwidth=4, color=black{}];",
+                    quote(a),
+                    quote(b),
+                    label
+                )?;
+            }
+        }
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
@@ -0,0 +1,422 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    config::Config,
+    score::{classify, pairs, Category, Solution},
+    validate::never_pairs_separable,
+};
+
+/// The solutions used in earlier runs, oldest first, as written with
+/// `--format json`. Entries are kept as they were read, so that saving the
+/// history again doesn't change them
+#[derive(Clone)]
+pub struct History {
+    path: PathBuf,
+    entries: Vec<Value>,
+}
+
+/// The part of a past solution the history needs
+#[derive(Deserialize)]
+struct Entry {
+    rooms: Vec<Vec<String>>,
+    /// When the entry was added, which older histories don't have
+    date: Option<String>,
+}
+
+/// A pair of people that shared a room in an earlier run, with the run's
+/// position and when it was added to the history if that was recorded
+type Past = (String, String, usize, Option<SystemTime>);
+
+/// Keeps the unlucky people from the latest run, see `History::unlucky`,
+/// out of unpreferred matchups, so that the same people don't keep drawing
+/// the short straw. Their unpreferred matchups are added to [never_pair]
+/// before solving, as long as everyone can still be put in a room, so this
+/// comes before `settings.objective` and a solution with more unpreferred
+/// matchups overall can win if it spares them. Returns how many matchups
+/// were ruled out
+pub fn spare(config: &mut Config, unlucky: &BTreeSet<String>) -> usize {
+    if never_pairs_separable(config) != Some(true) {
+        return 0;
+    }
+    let mut spared = 0;
+    for person in unlucky {
+        let others = config
+            .people()
+            .into_iter()
+            .filter(|x| {
+                x != person
+                    && config.partner(person) != Some(x.as_str())
+                    && !config.forbids(person, x)
+                    && classify(person, x, config) == Category::Unpreferred
+            })
+            .collect::<Vec<_>>();
+        // Ruling out all of someone's unpreferred matchups at once is
+        // usually possible, and saves a search for each of them
+        let before = config.never_pair.get(person).map_or(0, Vec::len);
+        config
+            .never_pair
+            .entry(person.clone())
+            .or_default()
+            .extend(others.iter().cloned());
+        if never_pairs_separable(config) == Some(true) {
+            spared += others.len();
+            continue;
+        }
+        config.never_pair.get_mut(person).unwrap().truncate(before);
+        for other in others {
+            config.never_pair.get_mut(person).unwrap().push(other);
+            if never_pairs_separable(config) == Some(true) {
+                spared += 1;
+            } else {
+                config.never_pair.get_mut(person).unwrap().pop();
+            }
+        }
+    }
+    config.never_pair.retain(|_, x| !x.is_empty());
+    spared
+}
+
+impl History {
+    /// Reads a history file, which doesn't have to exist yet
+    pub fn read(path: &Path) -> Result<Self> {
+        let entries = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| {
+                format!(
+                    "{} isn't a history file, which is a JSON list of solutions",
+                    path.display()
+                )
+            })?,
+            Err(error) if error.kind() == ErrorKind::NotFound => vec![],
+            Err(error) => {
+                return Err(error).with_context(|| format!("Couldn't read {}", path.display()))
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The rooms of every solution in the history, oldest first
+    pub fn rooms(&self) -> Result<Vec<Vec<Vec<String>>>> {
+        (0..self.entries.len())
+            .map(|i| Ok(self.entry(i)?.rooms))
+            .collect()
+    }
+
+    /// When a solution was added to the history, if that was recorded
+    fn date(&self, i: usize) -> Result<Option<SystemTime>> {
+        let Some(date) = self.entry(i)?.date else {
+            return Ok(None);
+        };
+        let parsed = humantime::parse_rfc3339_weak(&date).map_err(|x| {
+            anyhow!(
+                "Entry {} in {} has a date of '{}', which isn't valid: {}",
+                i + 1,
+                self.path.display(),
+                date,
+                x
+            )
+        })?;
+        Ok(Some(parsed))
+    }
+
+    fn entry(&self, i: usize) -> Result<Entry> {
+        Entry::deserialize(&self.entries[i]).map_err(|x| {
+            anyhow!(
+                "Entry {} in {} isn't a solution: {}",
+                i + 1,
+                self.path.display(),
+                x
+            )
+        })
+    }
+
+    /// Every pair of people that shared a room in an earlier run
+    fn pairs(&self) -> Result<Vec<Past>> {
+        let mut past = vec![];
+        for i in 0..self.entries.len() {
+            let date = self.date(i)?;
+            for room in &self.entry(i)?.rooms {
+                past.extend(pairs(room).map(|(a, b)| (a.clone(), b.clone(), i, date)));
+            }
+        }
+        Ok(past)
+    }
+
+    /// Everyone who was in an unpreferred matchup in the latest run, and so
+    /// shouldn't have to be in one again straight away
+    pub fn unlucky(&self, config: &Config) -> Result<BTreeSet<String>> {
+        let mut unlucky = BTreeSet::new();
+        let Some(latest) = self.entries.len().checked_sub(1) else {
+            return Ok(unlucky);
+        };
+        for room in &self.entry(latest)?.rooms {
+            for (a, b) in pairs(room) {
+                if classify(a, b, config) == Category::Unpreferred {
+                    unlucky.insert(a.clone());
+                    unlucky.insert(b.clone());
+                }
+            }
+        }
+        Ok(unlucky)
+    }
+
+    /// Keeps the pairs from earlier runs from sharing a room again. Forbidden
+    /// pairs, and pairs from the latest `window` runs, are added to
+    /// [never_pair]. Otherwise, without a half-life, they only stop counting
+    /// as anything better than accepted. With one, each time a pair shared a
+    /// room counts for half as much for every half-life that has passed
+    /// since, and runs without a date count in full. Pairs adding up to at
+    /// least a half count as accepted, pairs adding up to at least a quarter
+    /// drop one tier, and older pairs are forgotten. A repeat is never made
+    /// into an unpreferred matchup, which is for what people asked for. Pairs
+    /// from [must_pair] always share a room, so they are left alone
+    pub fn apply(
+        &self,
+        config: &mut Config,
+        forbid: bool,
+        half_life: Option<Duration>,
+        window: Option<usize>,
+    ) -> Result<()> {
+        let now = SystemTime::now();
+        let recent = self.entries.len().saturating_sub(window.unwrap_or(0));
+        let mut penalties = BTreeMap::<_, f64>::new();
+        for (a, b, i, date) in self.pairs()? {
+            if config.partner(&a) == Some(b.as_str()) {
+                continue;
+            }
+            if forbid || i >= recent {
+                config.never_pair.entry(a).or_default().push(b);
+                continue;
+            }
+            let penalty = match (half_life, date) {
+                (Some(half_life), Some(date)) => {
+                    let age = now.duration_since(date).unwrap_or_default().as_secs_f64();
+                    0.5f64.powf(age / half_life.as_secs_f64().max(f64::MIN_POSITIVE))
+                }
+                _ => 1.0,
+            };
+            let pair = if a < b { (a, b) } else { (b, a) };
+            *penalties.entry(pair).or_default() += penalty;
+        }
+        for ((a, b), penalty) in penalties {
+            let accepted = half_life.is_none() || penalty >= 0.5;
+            if !accepted && penalty < 0.25 {
+                continue;
+            }
+            for (person, other) in [(&a, &b), (&b, &a)] {
+                let mut tables = [
+                    &mut config.strongly_preferred,
+                    &mut config.preferred,
+                    &mut config.acceptable,
+                ];
+                for tier in 0..tables.len() {
+                    let Some(list) = tables[tier].get_mut(person) else {
+                        continue;
+                    };
+                    let Some(weight) = list.weight(other) else {
+                        continue;
+                    };
+                    list.remove(other);
+                    if let (false, Some(lower)) = (accepted, tables.get_mut(tier + 1)) {
+                        lower
+                            .entry(person.clone())
+                            .or_default()
+                            .insert(other, weight);
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a solution to the end of the history, along with the seed and
+    /// config it came from, and writes it out
+    pub fn append(
+        &mut self,
+        solution: &Solution,
+        seed: u64,
+        config_hash: Option<&str>,
+    ) -> Result<()> {
+        let mut entry = serde_json::to_value(solution)?;
+        if let Value::Object(fields) = &mut entry {
+            let date = humantime::format_rfc3339_seconds(SystemTime::now());
+            fields.insert("date".to_string(), date.to_string().into());
+            fields.insert("seed".to_string(), seed.into());
+            if let Some(hash) = config_hash {
+                fields.insert("config_hash".to_string(), hash.into());
+            }
+        }
+        self.entries.push(entry);
+        self.write()
+    }
+
+    /// Removes a solution from the history, counting from 0, and writes it out
+    pub fn remove(&mut self, index: usize) -> Result<()> {
+        self.entries.remove(index);
+        self.write()
+    }
+
+    /// Adds the solutions from another history that this one doesn't have
+    /// yet, and puts them all in date order. Solutions without a date can't
+    /// be told apart from another run that happened to get the same rooms,
+    /// so they are all kept, in their place before every dated one. Nothing
+    /// is written out
+    pub fn merge(&mut self, other: &History) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        for i in 0..self.entries.len() {
+            seen.extend(self.key(i)?);
+        }
+        let mut dated = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            dated.push((self.date(i)?, entry.clone()));
+        }
+        for (i, entry) in other.entries.iter().enumerate() {
+            if other.key(i)?.is_none_or(|x| seen.insert(x)) {
+                dated.push((other.date(i)?, entry.clone()));
+            }
+        }
+        // The sort is stable, so solutions from the same moment stay in the
+        // order they were read
+        dated.sort_by_key(|x| x.0);
+        self.entries = dated.into_iter().map(|x| x.1).collect();
+        Ok(())
+    }
+
+    /// What makes two dated solutions the same run, whatever order their
+    /// rooms and roommates were written in, or `None` without a date
+    fn key(&self, i: usize) -> Result<Option<(String, Vec<Vec<String>>)>> {
+        let entry = self.entry(i)?;
+        let Some(date) = entry.date else {
+            return Ok(None);
+        };
+        let mut rooms = entry.rooms;
+        for room in &mut rooms {
+            room.sort();
+        }
+        rooms.sort();
+        Ok(Some((date, rooms)))
+    }
+
+    /// Prints the history as JSON, as it would be written to its file
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, &self.entries)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Writes the history to a file next to it first and then moves that into
+    /// place, so that the history is never left half written
+    fn write(&self) -> Result<()> {
+        let text = serde_json::to_string_pretty(&self.entries)?;
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, text + "\n")
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .with_context(|| format!("Couldn't write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_toml;
+
+    #[test]
+    fn spares_the_unlucky_where_rooms_can_still_be_filled() {
+        let mut config = parse_toml(
+            r#"
+            people = ["a", "b", "c", "d"]
+
+            [settings]
+            solutions = 1
+
+            [unpreferred]
+            a = ["b", "c"]
+            "#,
+        )
+        .unwrap();
+        let unlucky = BTreeSet::from(["a".to_string()]);
+        assert_eq!(spare(&mut config, &unlucky), 2);
+        assert!(config.forbids("a", "b") && config.forbids("a", "c"));
+
+        // With nobody else left for them, someone has to share with a
+        config.never_pair.clear();
+        config
+            .never_pair
+            .insert("d".to_string(), vec!["a".to_string()]);
+        assert_eq!(spare(&mut config, &unlucky), 1);
+        assert!(config.forbids("a", "b") != config.forbids("a", "c"));
+    }
+
+    #[test]
+    fn merges_only_dated_runs_with_the_same_rooms() {
+        let history = |entries: serde_json::Value| History {
+            path: PathBuf::new(),
+            entries: serde_json::from_value(entries).unwrap(),
+        };
+        let mut merged = history(serde_json::json!([
+            { "rooms": [["a", "b"], ["c", "d"]] },
+            { "rooms": [["a", "c"], ["b", "d"]], "date": "2026-01-01T00:00:00Z" },
+        ]));
+        merged
+            .merge(&history(serde_json::json!([
+                { "rooms": [["d", "c"], ["b", "a"]] },
+                { "rooms": [["b", "d"], ["a", "c"]], "date": "2026-01-01T00:00:00Z" },
+            ])))
+            .unwrap();
+        assert_eq!(merged.len(), 3);
+    }
+
+    #[test]
+    fn repeats_fade_without_becoming_unpreferred() {
+        let mut config = parse_toml(
+            r#"
+            people = ["a", "b", "c", "d"]
+
+            [settings]
+            solutions = 1
+
+            [strongly_preferred]
+            a = ["b", "c"]
+            b = ["a"]
+            c = ["a"]
+            "#,
+        )
+        .unwrap();
+        let week = Duration::from_secs(7 * 24 * 60 * 60);
+        let date =
+            |age: Duration| humantime::format_rfc3339_seconds(SystemTime::now() - age).to_string();
+        let history = History {
+            path: PathBuf::new(),
+            entries: serde_json::from_value(serde_json::json!([
+                // Three weeks ago with a two week half-life counts for about
+                // a third, and this week for nearly all of it
+                { "rooms": [["a", "c"], ["b", "d"]], "date": date(3 * week) },
+                { "rooms": [["a", "b"], ["c", "d"]], "date": date(Duration::ZERO) },
+            ]))
+            .unwrap(),
+        };
+        history
+            .apply(&mut config, false, Some(2 * week), None)
+            .unwrap();
+        assert_eq!(classify("a", "b", &config), Category::Accepted);
+        assert_eq!(classify("a", "c", &config), Category::Preferred);
+        assert!(config.unpreferred.is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use minijinja::{value::Serde, Environment};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+    config::Config,
+    output::{heading_label, summary},
+    score::{classify, classify_room, pairs, room_weight, Category, Outcome, Solution},
+};
+
+const TEMPLATE: &str = include_str!("templates/report.html");
+
+/// Everything the report template is filled in with
+#[derive(Serialize, Default)]
+struct Report {
+    title: String,
+    /// Lines shown under the title, before any solutions
+    summary: Vec<Line>,
+    /// Output that has no rooms to lay out, shown as it would be printed
+    text: Option<String>,
+    sections: Vec<Section>,
+}
+
+#[derive(Serialize)]
+struct Line {
+    label: String,
+    value: String,
+}
+
+/// A solution in the report, after its own heading if there is more than one
+#[derive(Serialize)]
+pub struct Section {
+    heading: Option<String>,
+    summary: Vec<Line>,
+    /// Whether the rooms have a weight column
+    weighted: bool,
+    rooms: Vec<RoomView>,
+    /// How each person did, if that was asked for
+    people: Vec<PersonView>,
+}
+
+impl Section {
+    /// Adds how each person did after the rooms
+    pub fn with_people(mut self, people: &[Outcome]) -> Self {
+        self.people = people
+            .iter()
+            .map(|x| PersonView {
+                name: x.name.clone(),
+                category: x.category.repr(),
+                class: class(x.category),
+            })
+            .collect();
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct PersonView {
+    name: String,
+    category: &'static str,
+    class: String,
+}
+
+#[derive(Serialize)]
+struct RoomView {
+    name: String,
+    people: Vec<String>,
+    category: &'static str,
+    /// The room's category as a CSS class
+    class: String,
+    weight: u64,
+    /// Each pair in the room, for rooms of more than two
+    pairs: Vec<PairView>,
+}
+
+#[derive(Serialize)]
+struct PairView {
+    a: String,
+    b: String,
+    category: &'static str,
+    class: String,
+}
+
+fn class(category: Category) -> String {
+    category.repr().replace(' ', "-")
+}
+
+fn line(label: &str, value: String) -> Line {
+    Line {
+        label: heading_label(label),
+        value,
+    }
+}
+
+/// Lays out a solution for the report, with each room colored by how good
+/// its worst matchup is and each pair in a bigger room colored by its own
+pub fn section(solution: &Solution, config: &Config, heading: Option<String>) -> Section {
+    let rooms = solution
+        .rooms
+        .iter()
+        .enumerate()
+        .map(|(i, room)| {
+            let category = classify_room(room, config);
+            let pairs = if room.len() > 2 {
+                pairs(room)
+                    .map(|(a, b)| {
+                        let category = classify(a, b, config);
+                        PairView {
+                            a: a.clone(),
+                            b: b.clone(),
+                            category: category.repr(),
+                            class: class(category),
+                        }
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            RoomView {
+                name: solution
+                    .names
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| (i + 1).to_string()),
+                people: room.clone(),
+                category: category.repr(),
+                class: class(category),
+                weight: room_weight(room, config),
+                pairs,
+            }
+        })
+        .collect();
+    Section {
+        heading,
+        summary: summary(solution, config)
+            .into_iter()
+            .map(|(label, value)| line(label, value))
+            .collect(),
+        weighted: config.weighted(),
+        rooms,
+        people: vec![],
+    }
+}
+
+/// Writes a standalone HTML page of solutions, which needs nothing else to
+/// be viewed or printed. `summary` is shown before the solutions
+pub fn write_html<W: Write>(
+    title: &str,
+    summary: &[(&str, String)],
+    sections: Vec<Section>,
+    writer: W,
+) -> Result<()> {
+    let report = Report {
+        title: title.to_string(),
+        summary: summary
+            .iter()
+            .map(|(label, value)| line(label, value.clone()))
+            .collect(),
+        sections,
+        ..Default::default()
+    };
+    render(&report, writer)
+}
+
+/// Writes output without rooms as a standalone HTML page, as it would be
+/// printed
+pub fn write_preformatted<W: Write>(text: String, writer: W) -> Result<()> {
+    let report = Report {
+        title: "picker".to_string(),
+        text: Some(text),
+        ..Default::default()
+    };
+    render(&report, writer)
+}
+
+fn render<W: Write>(report: &Report, mut writer: W) -> Result<()> {
+    // Autoescaping is chosen by the template's extension, so every name in
+    // the report is escaped
+    let mut environment = Environment::new();
+    environment.add_template("report.html", TEMPLATE)?;
+    let html = environment
+        .get_template("report.html")?
+        .render(Serde(report))?;
+    writeln!(writer, "{}", html)?;
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use anyhow::{anyhow, bail, Result};
+use good_lp::{
+    constraint, default_solver, variable, Expression, ProblemVariables, ResolutionError,
+    Solution as _, SolverModel, Variable,
+};
+
+use crate::{
+    config::Config,
+    score::{Solution, UNFAIRNESS_BASE},
+    solver::{check_pair_rooms, pairing_solution},
+};
+
+/// How many digits of `Solution::unfairness` there are, one for each
+/// category a person's least wanted roommate can be
+const FEELINGS: usize = 5;
+
+/// Every pair of people allowed to share a room, as indexes into `people`
+type Edge = (usize, usize);
+
+/// Each pair's part of every criterion solutions are compared on, from the
+/// most important to the least, as `criteria[c][edge]`. These are only
+/// `Solution::rank_key` split up, except that how unfair the rooms are is
+/// split into one criterion per digit, unhappiest first. Its key can be far
+/// larger than a float holds exactly, while each digit is tiny
+fn criteria(people: &[String], config: &Config) -> (Vec<Edge>, Vec<Vec<f64>>) {
+    let unfairness = config.settings.objective.worst_off_slot();
+
+    let mut edges = vec![];
+    let mut keys = vec![];
+    for (i, a) in people.iter().enumerate() {
+        for (j, b) in people.iter().enumerate().skip(i + 1) {
+            if config.forbids(a, b) {
+                continue;
+            }
+            let key =
+                Solution::new(vec![vec![a.clone(), b.clone()]], vec![], config).rank_key(config);
+            let mut parts = vec![];
+            for (c, part) in key.into_iter().enumerate() {
+                if Some(c) != unfairness {
+                    parts.push(part as f64);
+                    continue;
+                }
+                let mut digits = vec![];
+                let mut rest = part.unsigned_abs();
+                for _ in 0..FEELINGS {
+                    digits.push(-((rest % UNFAIRNESS_BASE) as f64));
+                    rest /= UNFAIRNESS_BASE;
+                }
+                parts.extend(digits.into_iter().rev());
+            }
+            edges.push((i, j));
+            keys.push(parts);
+        }
+    }
+    let count = keys.first().map_or(0, Vec::len);
+    let criteria = (0..count)
+        .map(|c| keys.iter().map(|x| x[c]).collect::<Vec<_>>())
+        // Criteria every pair scores the same on can't tell pairings apart
+        .filter(|x| x.iter().any(|&y| y != x[0]))
+        .collect();
+    (edges, criteria)
+}
+
+/// Solves the pairing as a sequence of integer linear programs, with a
+/// binary variable for each pair of people allowed to share a room. Everyone
+/// is in at most one pair, there are as many pairs as possible, and pairs
+/// from [must_pair] are constraints of their own. Each criterion from
+/// `settings.objective` is maximised in turn, then kept at its best while
+/// the next is maximised, so that every criterion counts however small it is
+/// next to the others. Returns `None` if the constraints can't all be met
+pub fn solve_ilp(config: &Config) -> Result<Option<Solution>> {
+    check_pair_rooms(config, "ilp")?;
+    let people = config.people();
+    let (edges, criteria) = criteria(&people, config);
+    let index = |name: &str| people.iter().position(|x| x == name);
+    let mut required = vec![];
+    for (a, b) in config.must_pairs() {
+        let (Some(i), Some(j)) = (index(&a), index(&b)) else {
+            bail!(
+                "'{}' is in [must_pair] more than once, or isn't in the people list",
+                a
+            );
+        };
+        let pair = (i.min(j), i.max(j));
+        let Some(pair) = edges.iter().position(|&x| x == pair) else {
+            return Ok(None);
+        };
+        required.push(pair);
+    }
+
+    let mut chosen = None;
+    let mut reached = vec![];
+    // With no criteria left to tell pairings apart, any pairing will do
+    let stages = criteria.len().max(1);
+    for stage in 0..stages {
+        let Some(solution) = solve_stage(&people, &edges, &required, &criteria, &reached, stage)?
+        else {
+            return Ok(None);
+        };
+        if let Some(criterion) = criteria.get(stage) {
+            let total = criterion
+                .iter()
+                .zip(&solution)
+                .filter(|x| *x.1)
+                .map(|x| *x.0)
+                .sum::<f64>();
+            reached.push(total);
+        }
+        chosen = Some(solution);
+    }
+    let Some(chosen) = chosen else {
+        return Ok(None);
+    };
+
+    let mut rooms = vec![];
+    let mut paired = vec![false; people.len()];
+    for (&(i, j), chosen) in edges.iter().zip(chosen) {
+        if chosen {
+            rooms.push(vec![people[i].clone(), people[j].clone()]);
+            paired[i] = true;
+            paired[j] = true;
+        }
+    }
+    for (person, paired) in people.iter().zip(paired) {
+        if !paired {
+            rooms.push(vec![person.clone()]);
+        }
+    }
+    pairing_solution(rooms, config).map(Some)
+}
+
+/// Maximises one criterion while keeping each earlier one at the total it
+/// `reached`, returning which pairs were chosen
+fn solve_stage(
+    people: &[String],
+    edges: &[Edge],
+    required: &[usize],
+    criteria: &[Vec<f64>],
+    reached: &[f64],
+    stage: usize,
+) -> Result<Option<Vec<bool>>> {
+    let mut variables = ProblemVariables::new();
+    let chosen = edges
+        .iter()
+        .map(|_| variables.add(variable().binary()))
+        .collect::<Vec<Variable>>();
+    let total = |criterion: &[f64]| {
+        criterion
+            .iter()
+            .zip(&chosen)
+            .map(|(&weight, &x)| weight * x)
+            .sum::<Expression>()
+    };
+    let objective = criteria
+        .get(stage)
+        .map_or_else(Expression::default, |x| total(x));
+    let mut model = variables.maximise(objective).using(default_solver);
+
+    for person in 0..people.len() {
+        let pairs = edges
+            .iter()
+            .zip(&chosen)
+            .filter(|(edge, _)| edge.0 == person || edge.1 == person)
+            .map(|x| *x.1)
+            .sum::<Expression>();
+        model = model.with(constraint!(pairs <= 1));
+    }
+    let count = chosen.iter().copied().sum::<Expression>();
+    model = model.with(constraint!(count == (people.len() / 2) as f64));
+    for &pair in required {
+        model = model.with(constraint!(chosen[pair] == 1));
+    }
+    // Every criterion is a whole number, so half a point of slack keeps the
+    // earlier totals exact without tripping over rounding in the solver
+    for (criterion, &reached) in criteria.iter().zip(reached) {
+        model = model.with(constraint!(total(criterion) >= reached - 0.5));
+    }
+
+    let solution = match model.solve() {
+        Ok(solution) => solution,
+        Err(ResolutionError::Infeasible) => return Ok(None),
+        Err(error) => return Err(anyhow!("The ilp solver failed: {}", error)),
+    };
+    Ok(Some(
+        chosen.iter().map(|&x| solution.value(x) > 0.5).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::random_config, solver::solve_exact};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn matches_the_exact_solver() {
+        let mut rng = StdRng::seed_from_u64(53);
+        for settings in [
+            "",
+            "objective = { kind = \"lexicographic\", order = [\"worst_off\", \"preferred\"] }",
+        ] {
+            for people in [4, 7, 10] {
+                let config = random_config(&mut rng, people, settings);
+                let ilp = solve_ilp(&config).unwrap().unwrap();
+                let exact = solve_exact(&config).unwrap().unwrap();
+                assert_eq!(ilp.rank_key(&config), exact.rank_key(&config));
+            }
+        }
+    }
+}
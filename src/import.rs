@@ -0,0 +1,139 @@
+use std::{collections::HashMap, fs, io::Read, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+/// Preferences read from a survey export, ready to be written out as a config
+pub struct Survey {
+    pub people: Vec<String>,
+    pub preferred: HashMap<String, Vec<String>>,
+    pub unpreferred: HashMap<String, Vec<String>>,
+}
+
+/// Which table the answers in a column belong in
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Preferred,
+    Unpreferred,
+}
+
+/// Which questions of a Google Forms export hold which answers
+#[derive(Deserialize, Debug)]
+pub struct FormMapping {
+    /// The question each person answered with their name
+    pub name: String,
+    #[serde(default)]
+    pub preferred: Vec<String>,
+    #[serde(default)]
+    pub unpreferred: Vec<String>,
+}
+
+impl FormMapping {
+    fn column(&self, header: &str) -> Option<Column> {
+        let matches = |questions: &[String]| questions.iter().any(|x| normalize(x) == header);
+        if matches(&self.preferred) {
+            Some(Column::Preferred)
+        } else if matches(&self.unpreferred) {
+            Some(Column::Unpreferred)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads a mapping file for `read_google_forms`
+pub fn read_mapping(path: &Path) -> Result<FormMapping> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read mapping file {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Couldn't parse mapping file {}", path.display()))
+}
+
+fn normalize(header: &str) -> String {
+    header.trim().to_lowercase()
+}
+
+/// Splits a cell holding several names, which spreadsheets tend to separate
+/// with either commas or semicolons
+fn split_names(cell: &str) -> impl Iterator<Item = String> + '_ {
+    cell.split([',', ';'])
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+}
+
+/// Reads a CSV file with one row per person, where `column` says which table
+/// the names in each column belong in. If `resubmissions` is set, a later row
+/// for the same person replaces their earlier one
+fn read_survey<R: Read>(
+    reader: R,
+    name_column: &str,
+    column: impl Fn(&str) -> Option<Column>,
+    resubmissions: bool,
+) -> Result<Survey> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.iter().map(normalize).collect::<Vec<_>>();
+    let name_index = headers
+        .iter()
+        .position(|x| x == &normalize(name_column))
+        .ok_or_else(|| anyhow!("Survey has no '{}' column", name_column))?;
+    let columns = headers.iter().map(|x| column(x)).collect::<Vec<_>>();
+
+    let mut survey = Survey {
+        people: vec![],
+        preferred: HashMap::new(),
+        unpreferred: HashMap::new(),
+    };
+    for record in reader.records() {
+        let record = record?;
+        let name = record.get(name_index).unwrap_or_default().trim();
+        if name.is_empty() {
+            continue;
+        }
+        if survey.people.iter().any(|x| x == name) {
+            if !resubmissions {
+                return Err(anyhow!("'{}' has more than one row in the survey", name));
+            }
+        } else {
+            survey.people.push(name.to_string());
+        }
+
+        let mut preferred = vec![];
+        let mut unpreferred = vec![];
+        for (column, cell) in columns.iter().zip(record.iter()) {
+            match column {
+                Some(Column::Preferred) => preferred.extend(split_names(cell)),
+                Some(Column::Unpreferred) => unpreferred.extend(split_names(cell)),
+                None => {}
+            }
+        }
+        survey.preferred.insert(name.to_string(), preferred);
+        survey.unpreferred.insert(name.to_string(), unpreferred);
+    }
+    Ok(survey)
+}
+
+/// Reads a CSV file with one row per person. The `name_column` holds the
+/// person's name, and every column whose header starts with `preferred` or
+/// `unpreferred` holds names for that table, so answers can be spread across
+/// columns like `preferred 1`, `preferred 2`
+pub fn read_csv<R: Read>(reader: R, name_column: &str) -> Result<Survey> {
+    let column = |header: &str| {
+        if header.starts_with("unpreferred") {
+            Some(Column::Unpreferred)
+        } else if header.starts_with("preferred") {
+            Some(Column::Preferred)
+        } else {
+            None
+        }
+    };
+    read_survey(reader, name_column, column, false)
+}
+
+/// Reads a Google Forms response export, which has a column per question
+/// after the timestamp, with multi-select answers joined by commas. The
+/// mapping says which questions hold the names. Forms lists responses oldest
+/// first, so someone who submitted the form again is taken at their word the
+/// last time
+pub fn read_google_forms<R: Read>(reader: R, mapping: &FormMapping) -> Result<Survey> {
+    read_survey(reader, &mapping.name, |x| mapping.column(x), true)
+}
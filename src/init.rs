@@ -0,0 +1,179 @@
+use std::{collections::HashMap, fmt::Write};
+
+pub const DEFAULT_PEOPLE: [&str; 4] = ["alice", "bob", "carol", "dave"];
+
+fn quote(name: &str) -> String {
+    toml::Value::String(name.to_string()).to_string()
+}
+
+fn quote_list(names: &[String]) -> String {
+    let names = names.iter().map(|x| quote(x)).collect::<Vec<_>>();
+    format!("[{}]", names.join(", "))
+}
+
+fn write_table(text: &mut String, people: &[String], table: &HashMap<String, Vec<String>>) {
+    for person in people {
+        let list = table.get(person).map(Vec::as_slice).unwrap_or_default();
+        writeln!(text, "{} = {}", quote(person), quote_list(list)).unwrap();
+    }
+}
+
+/// Builds the text of a commented config file for the given people and
+/// preference tables
+pub fn config_text(
+    people: &[String],
+    preferred: &HashMap<String, Vec<String>>,
+    unpreferred: &HashMap<String, Vec<String>>,
+) -> String {
+    let mut text = String::new();
+    writeln!(
+        text,
+        "# Everyone that needs a room
+people = {}
+",
+        quote_list(people)
+    )
+    .unwrap();
+    text.push_str(
+        "[settings]
+# How many random candidate solutions to generate before picking the best one
+solutions = 500000
+# How many people share each room
+room_size = 2
+# What to do when the roster can't be split evenly into rooms: \"triple\" puts
+# whoever is left over into another room, \"single\" gives them a room of
+# their own, and \"error\" refuses to solve
+# odd_policy = \"triple\"
+# Uncomment to have rooms of different sizes instead, adding up to everyone
+# group_sizes = [2, 2, 3, 4]
+# Uncomment to stop names that only differ in case or surrounding spaces from
+# being treated as the same person
+# strict_names = true
+# What to do with people missing from [preferred] or [unpreferred]: \"error\",
+# \"empty\" to treat them as listing nobody, or \"accept_all\" to treat them as
+# happy with anyone
+# missing_policy = \"empty\"
+# Whether a preferred matchup needs both people to list each other
+# (\"mutual\"), either of them (\"either\"), or scores each person's wishes on
+# their own (\"directed\")
+preference_mode = \"mutual\"
+# How to find solutions: \"random\" generates many random candidates and keeps
+# the best, \"exhaustive\" checks every pairing of a small roster, which is
+# the default for up to 12 people in rooms of two, \"exact\" finds an optimal pairing directly when rooms are for two,
+# and \"stable\" finds a pairing where no two people would both rather swap.
+# \"anneal\" improves one random solution by swapping people between rooms,
+# starting at `temperature` and trying `iterations` swaps. \"genetic\" breeds
+# `population` solutions at a time for `generations` generations, and
+# \"branch_and_bound\" searches for a provably optimal solution, giving up at
+# `timeout` if one is set. \"ilp\" solves rooms of two as an integer linear
+# program with CBC, if picker was built with the `ilp` feature
+# \"hillclimb\" improves `restarts` random solutions with swaps until no swap
+# helps, or as many as fit in `timeout`. \"tabu\" makes `tabu_iterations` of
+# the best swaps it can, with swapped people sitting out `tabu_tenure` moves.
+# \"gale_shapley\" finds a stable pairing across the two [sides], which is
+# the best one for `proposing_side`
+# solver = \"random\"
+# temperature = 2.0
+# iterations = 200000
+# restarts = 100
+# tabu_iterations = 1000
+# tabu_tenure = 7
+# How solutions are compared. By default that is fewest unpreferred matchups
+# first, then the most strongly preferred, the highest preferred weight, the
+# most preferred and the most acceptable, each only breaking ties in the one
+# before. Either pick your own order, which can include \"accepted\", or
+# \"worst_off\" to make the unhappiest person as happy as possible:
+# objective = { kind = \"lexicographic\", order = [\"worst_off\", \"unpreferred\", \"preferred\"] }
+# or add up a score for every matchup by its kind instead:
+# objective = { kind = \"weighted\", preferred_weight = 2, unpreferred_weight = -10 }
+# Uncomment to keep generating solutions until the best one hasn't improved
+# in `window` attempts, instead of generating a fixed number of them
+# convergence = { window = 1000 }
+# Uncomment to keep generating random solutions after finding a perfect one
+# early_stop = false
+# population = 100
+# generations = 500
+# Uncomment to make every run produce the same pairings
+# seed = 1
+# Uncomment so that with `picker solve --history`, pairs from earlier runs
+# count half as much against sharing a room again after each half-life,
+# instead of always counting in full
+# repeat_half_life = \"4 weeks\"
+# Uncomment so that pairs from the latest 4 runs with `picker solve --history`,
+# or rounds of `picker schedule`, can't share a room again
+# repeat_window = 4
+
+# Who each person would like to share a room with. In the default mutual
+# mode, a pair only counts as a preferred matchup if both people list each
+# other. Stronger wishes can be
+# given more weight with a table instead, e.g. alice = { bob = 3, carol = 1 }.
+# Optional [strongly_preferred] and [acceptable] tables work the same way, and
+# are ranked above and below this one. \"*\" stands for everyone else, and
+# \"@name\" for everyone in a group from [groups]
+[preferred]
+",
+    );
+    write_table(&mut text, people, preferred);
+    text.push_str(
+        "
+# Who each person would rather not share a room with. Pairs where either
+# person lists the other are avoided where possible
+[unpreferred]
+",
+    );
+    write_table(&mut text, people, unpreferred);
+    text.push_str(
+        "
+# Pairs that always share a room, whatever their preferences, e.g.
+# alice = \"bob\"
+[must_pair]
+
+# Pairs that may never share a room. Unlike [unpreferred], picker fails
+# instead of settling for one of these, e.g. alice = [\"bob\"]
+[never_pair]
+
+# Rooms decided before solving, kept exactly as they are while everyone else
+# is arranged, e.g. front = [\"alice\", \"bob\"]. A key naming a room from
+# [rooms] puts them in that room
+[locked]
+
+# Named groups of people, e.g. band = [\"alice\", \"bob\"], which preference
+# lists can refer to as \"@band\"
+[groups]
+
+# Two sides where every room takes one person from each, e.g.
+# mentors = [\"alice\"] and mentees = [\"bob\"]
+[sides]
+
+# Values describing each person, e.g. alice = { grade = 10, gender = \"f\" }
+[attributes]
+
+# Actual rooms to fill instead of rooms of room_size, e.g.
+# room_101 = { capacity = 2 }
+[rooms]
+
+# Other names each person goes by, e.g. bob = [\"Bob\", \"Robert\"]
+[aliases]
+
+# Uncomment to seat everyone in a grid with `picker seat`, scoring who sits
+# next to who instead of who shares a room
+# [layout]
+# rows = 5
+# cols = 6
+
+# Uncomment to split everyone into teams with `picker teams`, keeping each
+# team's average of an attribute from [attributes] within `tolerance`
+# [teams]
+# count = 4
+# balance = \"skill\"
+# tolerance = 0.5
+",
+    );
+    text
+}
+
+/// Builds the text of a commented config file with an empty entry for each
+/// person in both preference tables
+pub fn starter_config(people: &[String]) -> String {
+    config_text(people, &HashMap::new(), &HashMap::new())
+}
@@ -0,0 +1,42 @@
+//! Lets Ctrl+C stop a long search early, keeping the solutions found so far
+//! instead of throwing them away
+
+use anyhow::Result;
+use std::{
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+use crate::error::EXIT_INTERRUPTED;
+
+static INSTALLED: Once = Once::new();
+static SOLVING: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Runs a search that Ctrl+C can stop early. Ctrl+C outside of a search, or a
+/// second Ctrl+C during one, still quits straight away
+pub fn solving<T>(search: impl FnOnce() -> T) -> Result<T> {
+    let mut installed = Ok(());
+    INSTALLED.call_once(|| {
+        installed = ctrlc::set_handler(|| {
+            if !SOLVING.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                process::exit(EXIT_INTERRUPTED.into());
+            }
+        });
+    });
+    installed?;
+
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    SOLVING.store(true, Ordering::SeqCst);
+    let result = search();
+    SOLVING.store(false, Ordering::SeqCst);
+    Ok(result)
+}
+
+/// Whether Ctrl+C was pressed during the current search
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::{
+    de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use toml::Spanned;
+
+/// The keys of a table, or nothing for any other kind of value
+struct Keys(Vec<Spanned<String>>);
+
+impl<'de> Deserialize<'de> for Keys {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeysVisitor;
+
+        impl<'de> Visitor<'de> for KeysVisitor {
+            type Value = Keys;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "any value")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Keys, A::Error> {
+                let mut keys = vec![];
+                while let Some(key) = map.next_key::<Spanned<String>>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    keys.push(key);
+                }
+                Ok(Keys(keys))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Keys, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(Keys(vec![]))
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> Result<Keys, E> {
+                Ok(Keys(vec![]))
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> Result<Keys, E> {
+                Ok(Keys(vec![]))
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> Result<Keys, E> {
+                Ok(Keys(vec![]))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> Result<Keys, E> {
+                Ok(Keys(vec![]))
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Keys, E> {
+                Ok(Keys(vec![]))
+            }
+        }
+
+        deserializer.deserialize_any(KeysVisitor)
+    }
+}
+
+/// Where each key was written in a TOML config, so problems can point at the
+/// line they come from
+#[derive(Debug, Default, Clone)]
+pub struct Locations {
+    path: String,
+    /// Where each line of the config starts
+    lines: Vec<usize>,
+    tables: HashMap<String, usize>,
+    keys: HashMap<(String, String), usize>,
+}
+
+impl Locations {
+    /// Finds the keys in a TOML config. Returns no locations at all if the
+    /// text doesn't parse
+    pub fn parse(path: &Path, text: &str) -> Self {
+        let mut locations = Self {
+            path: path.display().to_string(),
+            lines: std::iter::once(0)
+                .chain(text.match_indices('\n').map(|x| x.0 + 1))
+                .collect(),
+            ..Default::default()
+        };
+        let Ok(document) = toml::from_str::<HashMap<Spanned<String>, Keys>>(text) else {
+            return locations;
+        };
+        for (table, keys) in document {
+            for key in keys.0 {
+                let offset = key.span().start;
+                let key = key.into_inner().trim().to_string();
+                locations
+                    .keys
+                    .insert((table.get_ref().clone(), key), offset);
+            }
+            locations
+                .tables
+                .insert(table.get_ref().clone(), table.span().start);
+        }
+        locations
+    }
+
+    fn describe(&self, offset: usize) -> String {
+        let line = self.lines.partition_point(|&x| x <= offset);
+        let column = offset - self.lines[line - 1] + 1;
+        format!("{}:{}:{}", self.path, line, column)
+    }
+
+    /// Where a top level key or table was written, e.g. `config.toml:3:1`
+    pub fn table(&self, table: &str) -> Option<String> {
+        self.tables.get(table).map(|&x| self.describe(x))
+    }
+
+    /// Where a key in a table was written, e.g. `config.toml:12:1`
+    pub fn key(&self, table: &str, key: &str) -> Option<String> {
+        self.keys
+            .get(&(table.to_string(), key.to_string()))
+            .map(|&x| self.describe(x))
+    }
+}
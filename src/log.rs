@@ -1,10 +1,10 @@
 use std::{
-    fmt,
-    io::{self, Write},
+    env, fmt,
+    io::{self, IsTerminal, Write},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
     time::{Duration, Instant},
 };
 
-use crate::Solution;
 use anyhow::Result;
 use colored::Colorize;
 
@@ -52,42 +52,83 @@ fn display_duration(duration: Duration) -> (u128, TimeUnit) {
     (time_since_start, unit)
 }
 
-pub struct Logger {
-    start: Instant,
+/// How much is logged. 0 is quiet, 1 is the default step-by-step output, and
+/// each level above that adds more detail
+static VERBOSITY: AtomicU8 = AtomicU8::new(1);
+
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
 }
 
-pub fn display_result(solution: &Solution) {
-    println!("{}", "RESULT".green().bold());
-    println!(
-        " preferred matchups:    {}",
-        solution.preferred.to_string().blue()
-    );
-    println!(
-        " accepted matchups:     {}",
-        solution.accepted.to_string().blue()
-    );
-    println!(
-        " unpreferred matchups:  {}",
-        solution.unpreferred.to_string().blue()
-    );
-    for room in &solution.rooms {
-        println!(" {} & {}", room.0.blue(), room.1.blue());
-    }
+pub fn enabled(level: u8) -> bool {
+    VERBOSITY.load(Ordering::Relaxed) >= level
+}
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// Decides whether output should be colored. Colors are turned off by
+/// `--no-color`, by a non-empty `NO_COLOR` variable, or when the output isn't
+/// going to a terminal
+pub fn init_color(no_color: bool) {
+    let no_color_env = env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty());
+    let terminal = io::stdout().is_terminal() && io::stderr().is_terminal();
+    let enabled = !no_color && !no_color_env && terminal;
+    COLOR.store(enabled, Ordering::Relaxed);
+    colored::control::set_override(enabled);
+}
+
+/// Runs `f` with colors turned off, e.g. while writing to a file
+pub fn without_color<T>(f: impl FnOnce() -> T) -> T {
+    colored::control::set_override(false);
+    let result = f();
+    colored::control::set_override(COLOR.load(Ordering::Relaxed));
+    result
+}
+
+pub struct Logger {
+    start: Instant,
+    enabled: bool,
 }
 
 pub fn info<T: fmt::Display>(description: T) -> Result<Logger> {
-    print!("{} {}", "INFO".yellow().bold(), description);
-    io::stdout().flush()?;
+    let enabled = enabled(1);
+    if enabled {
+        eprint!("{} {}", "INFO".yellow().bold(), description);
+        io::stderr().flush()?;
+    }
     Ok(Logger {
         start: Instant::now(),
+        enabled,
     })
 }
 
+/// Extra detail shown with `-v`
+pub fn debug<T: fmt::Display>(description: T) {
+    if enabled(2) {
+        eprintln!("{} {}", "DEBUG".cyan().bold(), description);
+    }
+}
+
+/// Very noisy detail shown with `-vv`
+pub fn trace<T: fmt::Display>(description: T) {
+    if enabled(3) {
+        eprintln!(
+            "{} {}",
+            "TRACE".truecolor(150, 150, 150).bold(),
+            description
+        );
+    }
+}
+
 impl Logger {
-    pub fn end(self) {
+    pub fn end(mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.enabled = false;
         let time_passed = self.start.elapsed();
         let (elapsed, unit) = display_duration(time_passed);
-        println!(
+        eprintln!(
             " {} {}{}",
             "took".truecolor(150, 150, 150),
             elapsed.to_string().truecolor(150, 150, 150),
@@ -95,3 +136,13 @@ impl Logger {
         );
     }
 }
+
+impl Drop for Logger {
+    // A logger that is dropped without being ended means the step failed, so
+    // the line is finished off to keep the error message on a line of its own
+    fn drop(&mut self) {
+        if self.enabled {
+            eprintln!();
+        }
+    }
+}
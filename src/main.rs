@@ -1,185 +1,1211 @@
 #![forbid(unsafe_code)]
 
-use anyhow::{anyhow, Result};
+use anonymize::Pseudonyms;
+use anyhow::{anyhow, Context, Result};
+use clap::{CommandFactory, Parser, ValueEnum};
+use cli::{Cli, Command};
 use colored::Colorize;
-use log::display_result;
-use rand::{rngs::ThreadRng, seq::SliceRandom};
-use serde::Deserialize;
+use config::{parse_config, Algorithm, Config};
+use error::Failure;
+use history::History;
+use notify::{RecursiveMode, Watcher};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use score::{RunResult, Solution};
 use std::{
-    cmp,
-    collections::HashMap,
-    env, fs,
+    fs::{self, File},
+    io::{self, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime},
 };
 
+mod amend;
+mod anonymize;
+mod calendar;
+mod cli;
+mod config;
+mod date;
+mod diff;
+mod edit;
+mod error;
+mod explain;
+mod fairness;
+mod graph;
+mod history;
+mod html;
+#[cfg(feature = "ilp")]
+mod ilp;
+mod import;
+mod init;
+mod interrupt;
+mod location;
 mod log;
+mod marriage;
+mod matching;
+mod output;
+mod roommates;
+mod santa;
+mod schedule;
+mod score;
+mod seating;
+mod solver;
+mod stats;
+mod store;
+mod suggest;
+mod teams;
+mod template;
+mod validate;
 
-#[derive(Deserialize, Debug, Clone, Copy)]
-struct Settings {
-    solutions: i64,
+fn read_config(
+    path: &Path,
+    format: Option<cli::ConfigFormat>,
+    event: Option<&str>,
+) -> Result<Config> {
+    if path == Path::new("-") {
+        let logger = log::info("Parsing config from stdin")?;
+        let config = parse_config(path, format, event)?;
+        logger.end();
+        return Ok(config);
+    }
+
+    // Uses the path given on the command line, defaulting to the config.toml
+    // file in the current working directory
+    let logger = log::info("Finding config file path")?;
+    let full_path = path.canonicalize()?;
+    let display_path = full_path.display().to_string();
+    logger.end();
+
+    // Parses the provided config file into a Config struct
+    let logger = log::info(format!("Parsing config file at {}", display_path.blue()))?;
+    let config = parse_config(&full_path, format, event)?;
+    logger.end();
+    Ok(config)
+}
+
+fn load_config(args: &cli::GenerateArgs) -> Result<Config> {
+    let mut config = configure(args)?;
+    anonymize(args, &mut config)?;
+    Ok(config)
+}
+
+/// Reads the config and applies the command line options to it
+fn configure(args: &cli::GenerateArgs) -> Result<Config> {
+    let mut config = read_config(&args.config, args.config_format, args.event.as_deref())?;
+    log::debug(format!("{} people in config", config.people().len()));
+
+    // Command line options take precedence over environment variables, which
+    // take precedence over the config file
+    config.settings.apply_env()?;
+    config.settings.limit(args.solutions, args.timeout);
+    if let Some(solver) = args.solver {
+        config.settings.solver = Some(solver);
+    }
+    if let Some(restarts) = args.restarts {
+        config.settings.restarts = restarts;
+    }
+    if let Some(side) = &args.proposing_side {
+        config.settings.proposing_side = Some(side.clone());
+    }
+    for lock in &args.lock {
+        config.lock(&lock.split(':').map(str::trim).collect::<Vec<_>>());
+    }
+    if args.no_early_stop {
+        config.settings.early_stop = false;
+    }
+    Ok(config)
+}
+
+/// Renames everyone in the config if `--anonymize` was given, returning the
+/// pseudonyms so that names from elsewhere can be renamed to match
+fn anonymize(args: &cli::GenerateArgs, config: &mut Config) -> Result<Pseudonyms> {
+    let Some(key) = &args.anonymize else {
+        return Ok(Pseudonyms::default());
+    };
+    let logger = log::info(format!(
+        "Anonymizing everyone, with the key in {}",
+        key.display().to_string().blue()
+    ))?;
+    // The seeded rng isn't used, so that the order pseudonyms were handed
+    // out in can't be worked out from a logged seed
+    let pseudonyms = Pseudonyms::load(key, config, &mut rand::thread_rng())?;
+    pseudonyms.config(config);
+    logger.end();
+    Ok(pseudonyms)
+}
+
+fn make_rng(args: &cli::GenerateArgs, config: &Config) -> Result<StdRng> {
+    make_seeded_rng(choose_seed(args, config))
+}
+
+/// Seeds the rng from the command line or config file if possible, so that
+/// runs can be reproduced. Otherwise a random seed is picked and logged
+fn choose_seed(args: &cli::GenerateArgs, config: &Config) -> u64 {
+    args.seed
+        .or(config.settings.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen())
 }
 
-#[derive(Deserialize, Debug)]
-struct Config {
-    settings: Settings,
-    preferred: HashMap<String, Vec<String>>,
-    unpreferred: HashMap<String, Vec<String>>,
+fn make_seeded_rng(seed: u64) -> Result<StdRng> {
+    let logger = log::info(format!(
+        "Generating rng with seed {}",
+        seed.to_string().blue()
+    ))?;
+    let rng = StdRng::seed_from_u64(seed);
+    logger.end();
+    Ok(rng)
 }
 
-#[derive(Debug)]
-pub struct Solution {
-    pub rooms: Vec<(String, String)>,
-    pub preferred: u64,
-    pub accepted: u64,
-    pub unpreferred: u64,
+fn generate(config: &Config, rng: &mut StdRng) -> Result<Vec<Solution>> {
+    // Generates n amount of solutions, randomly changing the order of the list
+    // of people randomly each time, to ensure a range of solutions are generated
+    let logger = match (solver::chosen_solver(config), config.settings.timeout) {
+        (Algorithm::Exhaustive, _) => log::info("Checking every possible pairing")?,
+        (Algorithm::BranchAndBound, _) => log::info("Searching for an optimal solution")?,
+        (Algorithm::Ilp, _) => log::info("Solving the pairing as an integer linear program")?,
+        (Algorithm::Exact, _) => log::info("Finding an optimal solution with the exact solver")?,
+        (Algorithm::Stable, _) => log::info("Finding a stable solution")?,
+        (Algorithm::GaleShapley, _) => log::info("Finding a stable pairing across the sides")?,
+        (Algorithm::Hillclimb | Algorithm::Random, _) if config.settings.convergence.is_some() => {
+            log::info(format!(
+                "Generating solutions until the best hasn't improved in {}",
+                config
+                    .settings
+                    .convergence
+                    .unwrap()
+                    .window
+                    .to_string()
+                    .blue()
+            ))?
+        }
+        (Algorithm::Hillclimb, Some(timeout)) => log::info(format!(
+            "Hill climbing from random solutions for {}",
+            humantime::format_duration(timeout).to_string().blue()
+        ))?,
+        (Algorithm::Hillclimb, None) => log::info(format!(
+            "Hill climbing from {} random solutions",
+            config.settings.restarts.to_string().blue()
+        ))?,
+        (Algorithm::Tabu, _) => log::info(format!(
+            "Tabu searching over {} moves",
+            config.settings.tabu_iterations.to_string().blue()
+        ))?,
+        (Algorithm::Anneal, _) => log::info(format!(
+            "Annealing a solution over {} iterations",
+            config.settings.iterations.to_string().blue()
+        ))?,
+        (Algorithm::Genetic, _) => log::info(format!(
+            "Breeding solutions over {} generations",
+            config.settings.generations.to_string().blue()
+        ))?,
+        (Algorithm::Random, Some(timeout)) => log::info(format!(
+            "Generating solutions for {}",
+            humantime::format_duration(timeout).to_string().blue()
+        ))?,
+        (Algorithm::Random, None) => log::info(format!(
+            "Generating {} solutions",
+            config.settings.solutions.to_string().blue()
+        ))?,
+    };
+    let started = Instant::now();
+    let solutions = interrupt::solving(|| solver::generate_solutions(config, rng))??;
+    logger.end();
+    let searched = solver::chosen_solver(config) == Algorithm::BranchAndBound;
+    if interrupt::interrupted() {
+        log::info("Interrupted, so the solution is the best found so far")?.end();
+    } else if searched
+        && config
+            .settings
+            .timeout
+            .is_some_and(|x| started.elapsed() >= x)
+    {
+        log::info("The search was stopped at the timeout, so the solution might not be optimal")?
+            .end();
+    }
+    log::debug(format!("{} solutions generated", solutions.len()));
+    if log::enabled(3) {
+        for (i, solution) in solutions.iter().enumerate() {
+            log::trace(format!(
+                "solution {}: {} preferred, {} accepted, {} unpreferred",
+                i, solution.preferred, solution.accepted, solution.unpreferred
+            ));
+        }
+    }
+    if solutions.is_empty() {
+        if config.has_forbidden() {
+            return Err(Failure::Forbidden.into());
+        }
+        if interrupt::interrupted() {
+            return Err(anyhow!("Interrupted before any solutions were generated"));
+        }
+        return Err(anyhow!("No solutions were generated"));
+    }
+    Ok(solutions)
 }
 
-fn parse_config<T: AsRef<Path>>(path: T) -> Result<Config> {
-    let text = fs::read_to_string(path)?;
-    let result = toml::from_str(&text)?;
-    Ok(result)
+fn rank<'a>(solutions: &'a [Solution], config: &Config) -> Result<Vec<&'a Solution>> {
+    // Keeps only the solutions with the best score, see `Solution::rank_key`
+    // for the order the counts are compared in
+    let logger = log::info("Ranking solutions")?;
+    let solutions = solver::best(solutions, config);
+    logger.end();
+    let best = solutions[0];
+    log::debug(format!(
+        "best solutions have {} unpreferred and {} preferred matchups, with a weight of {}",
+        best.unpreferred, best.preferred, best.weight
+    ));
+
+    log::info(format!(
+        "{} distinct optimal solutions found",
+        solutions.len().to_string().blue()
+    ))?
+    .end();
+    Ok(solutions)
 }
 
-fn get_preferred_people(a: &str, people: &[String], config: &Config) -> Option<Vec<String>> {
-    let mut result = vec![];
-    for b in people {
-        let a_prefers_b = config.preferred.get(a)?.contains(b);
-        let b_prefers_a = config.preferred.get(b)?.contains(&a.to_string());
-        if a_prefers_b && b_prefers_a {
-            result.push(b.clone());
+/// Writes a result to the given file, or to stdout if there isn't one
+fn write_output(
+    path: Option<&Path>,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    match path {
+        Some(path) => {
+            // Colors are only meant for the terminal, so they are turned off
+            // while writing to the file
+            let logger = log::info(format!(
+                "Writing result to {}",
+                path.display().to_string().blue()
+            ))?;
+            let mut file = File::create(path)?;
+            log::without_color(|| write(&mut file))?;
+            logger.end();
+            Ok(())
         }
+        None => write(&mut io::stdout()),
     }
-    Some(result)
 }
 
-fn get_accepted_people(a: &str, people: &[String], config: &Config) -> Option<Vec<String>> {
-    let mut result = vec![];
-    for b in people {
-        let a_unprefers_b = config.unpreferred.get(a)?.contains(b);
-        let b_unprefers_a = config.unpreferred.get(b)?.contains(&a.to_string());
-        if !a_unprefers_b && !b_unprefers_a {
-            result.push(b.clone());
+/// Shows optimal solutions one at a time with `show` until one is accepted,
+/// re-rolling from the already generated pool. Returns `None` if the user
+/// quits
+fn choose_interactively<'a>(
+    solutions: &[&'a Solution],
+    show: impl Fn(&Solution) -> Result<()>,
+    rng: &mut StdRng,
+) -> Result<Option<&'a Solution>> {
+    let mut index = rng.gen_range(0..solutions.len());
+    let mut line = String::new();
+    loop {
+        show(solutions[index])?;
+        loop {
+            eprint!("{} ", "accept, re-roll or quit? [a/r/q]".yellow().bold());
+            io::stderr().flush()?;
+            line.clear();
+            if io::stdin().read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            match line.trim().to_lowercase().as_str() {
+                "a" | "accept" => return Ok(Some(solutions[index])),
+                "q" | "quit" => return Ok(None),
+                "r" | "re-roll" | "reroll" => break,
+                other => eprintln!("Unknown choice '{}'", other),
+            }
+        }
+        if solutions.len() == 1 {
+            eprintln!("There is only one optimal solution");
+            continue;
         }
+        // Picks from every solution except the current one
+        let next = rng.gen_range(0..solutions.len() - 1);
+        index = if next >= index { next + 1 } else { next };
     }
-    Some(result)
 }
 
-fn find_index<T: cmp::PartialEq>(item: &T, array: &[T]) -> Result<usize> {
-    array
-        .iter()
-        .position(|x| x == item)
-        .ok_or_else(|| anyhow!("Error choosing random item from list. Array empty."))
-}
-
-fn choose_person(
-    list: &Vec<String>,
-    index_list: &mut Vec<String>,
-    rng: &mut ThreadRng,
-) -> Result<String> {
-    let person = list
-        .choose(rng)
-        .ok_or_else(|| anyhow!("Error choosing random person"))?;
-    let index = find_index(person, index_list)?;
-    Ok(index_list.remove(index))
-}
-
-fn solve(config: &Config, rng: &mut ThreadRng) -> Result<Solution> {
-    let mut rooms = vec![];
-    let mut preferred = 0;
-    let mut accepted = 0;
-    let mut unpreferred = 0;
-
-    let mut people = config.unpreferred.keys().cloned().collect::<Vec<_>>();
-    people.shuffle(rng);
-
-    while let Some(person) = people.pop() {
-        let preferred_people = get_preferred_people(&person, &people, config)
-            .ok_or_else(|| anyhow!("Error generating preferred people"))?;
-        let accepted_people = get_accepted_people(&person, &people, config)
-            .ok_or_else(|| anyhow!("Error generating accepted people"))?;
-
-        if !preferred_people.is_empty() {
-            let second_person = choose_person(&preferred_people, &mut people, rng)?;
-            rooms.push((person, second_person));
-            preferred += 1;
-        } else if !accepted_people.is_empty() {
-            let second_person = choose_person(&accepted_people, &mut people, rng)?;
-            rooms.push((person, second_person));
-            accepted += 1;
-        } else {
-            let second_person = choose_person(&people.clone(), &mut people, rng)?;
-            rooms.push((person, second_person));
-            unpreferred += 1;
-        }
-    }
-
-    Ok(Solution {
-        rooms,
-        preferred,
-        accepted,
-        unpreferred,
+/// Adds the chosen solution to the `--history` file, if `--commit` asked for it
+fn commit_to_history(
+    history: &mut History,
+    solution: &Solution,
+    seed: u64,
+    args: &cli::SolveArgs,
+) -> Result<()> {
+    if !args.commit {
+        return Ok(());
+    }
+    let path = &args.history[0];
+    let logger = log::info(format!(
+        "Adding the solution to {}",
+        path.display().to_string().blue()
+    ))?;
+    let (_, hash) = config_identity(&args.generate.config)?;
+    history.append(solution, seed, hash.as_deref())?;
+    logger.end();
+    Ok(())
+}
+
+/// The chosen solution, along with what it takes to find it again
+fn run_result(
+    args: &cli::SolveArgs,
+    config: &Config,
+    started: SystemTime,
+    seed: u64,
+    solution: &Solution,
+) -> Result<RunResult> {
+    let (_, config_hash) = config_identity(&args.generate.config)?;
+    Ok(RunResult {
+        seed,
+        config_hash,
+        started: humantime::format_rfc3339_seconds(started).to_string(),
+        finished: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        people: args.people.then(|| score::outcomes(solution, config)),
+        solution: solution.clone(),
     })
 }
 
-fn generate_solutions(config: &Config, rng: &mut ThreadRng) -> Result<Vec<Solution>> {
-    let mut result = vec![];
-    for _ in 0..config.settings.solutions {
-        result.push(solve(config, rng)?);
+/// Prints the chosen solution through `--template` if there is one, or else
+/// in the chosen format
+fn write_chosen(
+    args: &cli::SolveArgs,
+    result: &RunResult,
+    config: &Config,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    match &args.template {
+        Some(path) => template::write_template(path, result, config, writer),
+        None => output::write_run_result(result, config, args.format, writer),
     }
-    Ok(result)
 }
 
-fn main() -> Result<()> {
-    // Uses first env arg as path to config file. If not provided, uses the
-    // config.toml file in the current working directory
-    let logger = log::info("Finding config file path")?;
-    let default_path = String::from("config.toml");
-    let path = env::args().nth(1).unwrap_or(default_path);
-    let full_path = PathBuf::from(path.clone()).canonicalize().unwrap();
-    let display_path = full_path.to_str().unwrap();
+/// The full path of the config file and a hash of its contents, or `-` and
+/// no hash for stdin
+fn config_identity(file: &Path) -> Result<(String, Option<String>)> {
+    if file == Path::new("-") {
+        return Ok(("-".to_string(), None));
+    }
+    let contents =
+        fs::read(file).with_context(|| format!("Couldn't read {} to hash it", file.display()))?;
+    Ok((
+        file.canonicalize()?.display().to_string(),
+        Some(store::hash_config(&contents)),
+    ))
+}
+
+/// Records the run in the history database, if `--record` or `--db` asked for it
+fn record_run(
+    args: &cli::SolveArgs,
+    config: &Config,
+    seed: u64,
+    solution: &Solution,
+) -> Result<()> {
+    if !args.record && args.db.is_none() {
+        return Ok(());
+    }
+    let path = match &args.db {
+        Some(path) => path.clone(),
+        None => store::default_path()?,
+    };
+    let logger = log::info(format!(
+        "Recording the run in {}",
+        path.display().to_string().blue()
+    ))?;
+    let (name, hash) = config_identity(&args.generate.config)?;
+    let solver = solver::chosen_solver(config)
+        .to_possible_value()
+        .map_or("random".to_string(), |x| x.get_name().to_string());
+    let id =
+        store::Store::create(&path)?.record(&name, hash.as_deref(), seed, &solver, solution)?;
     logger.end();
+    log::debug(format!("Recorded as run {}", id));
+    Ok(())
+}
 
-    // Parses the provided config file into a Config struct
-    let logger = log::info(format!("Parsing config file at {}", display_path.blue()))?;
-    let config = parse_config(path)?;
+fn run_history(args: &cli::HistoryArgs) -> Result<()> {
+    match &args.command {
+        cli::HistoryCommand::Report { config, file } => {
+            return run_report(args, config, file.as_deref())
+        }
+        cli::HistoryCommand::Merge { files, output } => {
+            return merge_histories(files, output.as_deref())
+        }
+        _ => {}
+    }
+    let file = match &args.command {
+        cli::HistoryCommand::Undo { file } | cli::HistoryCommand::Drop { file, .. } => {
+            file.as_deref()
+        }
+        _ => None,
+    };
+    if let Some(file) = file {
+        return drop_from_history_file(args, file);
+    }
+
+    let path = match &args.db {
+        Some(path) => path.clone(),
+        None => store::default_path()?,
+    };
+    let store = match args.command {
+        cli::HistoryCommand::List | cli::HistoryCommand::Show { .. } => store::Store::open(&path)?,
+        _ => store::Store::open_writable(&path)?,
+    };
+    let id = match args.command {
+        cli::HistoryCommand::List => {
+            return store::write_runs(&store.runs()?, args.format, io::stdout())
+        }
+        cli::HistoryCommand::Show { id } => {
+            return store::write_run(&store.run(id)?, args.format, io::stdout())
+        }
+        cli::HistoryCommand::Undo { .. } => store
+            .latest()?
+            .ok_or_else(|| anyhow!("There are no runs in {} to undo", path.display()))?,
+        cli::HistoryCommand::Drop { id, .. } => id,
+        cli::HistoryCommand::Report { .. } | cli::HistoryCommand::Merge { .. } => unreachable!(),
+    };
+    let logger = log::info(format!(
+        "Removing run {} from {}",
+        id.to_string().blue(),
+        path.display().to_string().blue()
+    ))?;
+    store.remove(id)?;
     logger.end();
+    Ok(())
+}
+
+fn run_report(args: &cli::HistoryArgs, config: &Path, file: Option<&Path>) -> Result<()> {
+    let config = read_config(config, None, None)?;
+    let runs = match file {
+        Some(file) => History::read(file)?.rooms()?,
+        None => {
+            let path = match &args.db {
+                Some(path) => path.clone(),
+                None => store::default_path()?,
+            };
+            store::Store::open(&path)?
+                .runs()?
+                .into_iter()
+                .map(|x| x.solution.rooms)
+                .collect()
+        }
+    };
+    let report = fairness::report(&runs, &config);
+    fairness::write_report(&report, &config, args.format, io::stdout())
+}
 
-    let logger = log::info("Generating rng")?;
-    let mut rng = rand::thread_rng();
+fn merge_histories(files: &[PathBuf], output: Option<&Path>) -> Result<()> {
+    let logger = log::info(format!(
+        "Merging {} histories",
+        files.len().to_string().blue()
+    ))?;
+    let mut merged = History::read(&files[0])?;
+    for file in &files[1..] {
+        merged.merge(&History::read(file)?)?;
+    }
     logger.end();
+    log::debug(format!("{} solutions in the merged history", merged.len()));
+    write_output(output, |writer| merged.write_to(writer))
+}
 
-    // Generates n amount of solutions, randomly changing the order of the list
-    // of people randomly each time, to ensure a range of solutions are generated
+fn drop_from_history_file(args: &cli::HistoryArgs, path: &Path) -> Result<()> {
+    let mut history = History::read(path)?;
+    let position = match args.command {
+        cli::HistoryCommand::Drop { id, .. } => id,
+        _ => history.len() as i64,
+    };
+    if position < 1 || position as usize > history.len() {
+        return Err(anyhow!(
+            "{} has {} solutions, so there is no solution {} to remove",
+            path.display(),
+            history.len(),
+            position
+        ));
+    }
     let logger = log::info(format!(
-        "Generating {} solutions",
-        config.settings.solutions.to_string().blue()
+        "Removing solution {} from {}",
+        position.to_string().blue(),
+        path.display().to_string().blue()
     ))?;
-    let solutions = generate_solutions(&config, &mut rng)?;
+    history.remove(position as usize - 1)?;
     logger.end();
+    Ok(())
+}
 
-    // Filters out all solutions that do not have the minimum number of unpreferred matchups
-    // Then filters out all solutions that do not have the maximum number of preferred matchups
-    let logger = log::info("Ranking solutions")?;
-    let min_unpreferred = solutions.iter().map(|x| x.unpreferred).min().unwrap();
-    let solutions = solutions
+/// Prints every solution on the Pareto front, see `solver::pareto_front`
+fn print_pareto_front(
+    solutions: &[Solution],
+    config: &Config,
+    args: &cli::SolveArgs,
+) -> Result<()> {
+    let logger = log::info("Finding the Pareto front")?;
+    let front = solver::pareto_front(solutions);
+    logger.end();
+    log::info(format!(
+        "{} solutions on the Pareto front",
+        front.len().to_string().blue()
+    ))?
+    .end();
+    write_output(args.output.as_deref(), |writer| {
+        output::write_results(&front, config, args.format, writer)
+    })?;
+
+    // The front starts with the fewest unpreferred matchups of any solution
+    if front[0].unpreferred > 0 {
+        return Err(Failure::Unpreferred(front[0].unpreferred).into());
+    }
+    Ok(())
+}
+
+fn run_solve(args: &cli::SolveArgs) -> Result<()> {
+    let started = SystemTime::now();
+    if args.interactive && args.generate.config == Path::new("-") {
+        return Err(anyhow!(
+            "Can't prompt interactively while reading the config from stdin"
+        ));
+    }
+    if args.people && matches!(args.format, cli::Format::Csv) {
+        return Err(anyhow!(
+            "--people can't be printed with --format csv, which has one row per room"
+        ));
+    }
+    // The history has everyone's real names, so it is applied before anyone
+    // is renamed
+    let mut config = configure(&args.generate)?;
+    let mut history = match args.history.split_first() {
+        Some((path, others)) => {
+            let paths = args
+                .history
+                .iter()
+                .map(|x| x.display().to_string().blue().to_string())
+                .collect::<Vec<_>>();
+            let logger = log::info(format!("Reading history from {}", paths.join(", ")))?;
+            // Only the first history is ever written to, so the others are
+            // merged into a copy of it
+            let history = History::read(path)?;
+            let mut combined = history.clone();
+            for other in others {
+                combined.merge(&History::read(other)?)?;
+            }
+            // Who was unlucky is worked out before the history changes anyone's
+            // preferences, as the latest run's pairs are all repeats
+            let unlucky = combined.unlucky(&config)?;
+            if let Some(half_life) = args.half_life {
+                config.settings.repeat_half_life = Some(half_life);
+            }
+            let half_life = config.settings.repeat_half_life;
+            let window = config.settings.repeat_window;
+            combined.apply(&mut config, args.forbid_repeats, half_life, window)?;
+            logger.end();
+            log::debug(format!("{} solutions in history", combined.len()));
+            let logger = log::info("Sparing anyone who had an unpreferred matchup last time")?;
+            let spared = history::spare(&mut config, &unlucky);
+            logger.end();
+            log::debug(format!("{} unpreferred matchups ruled out", spared));
+            Some(history)
+        }
+        None => None,
+    };
+    anonymize(&args.generate, &mut config)?;
+    // Every trade-off is wanted, so the search can't stop at the first
+    // perfect solution or drop everything but the best ones
+    if args.pareto {
+        config.settings.pareto = true;
+        config.settings.early_stop = false;
+    }
+    if args.dry_run {
+        return dry_run(&config);
+    }
+    let seed = choose_seed(&args.generate, &config);
+    let mut rng = make_seeded_rng(seed)?;
+    let solutions = generate(&config, &mut rng)?;
+    if args.stats {
+        let optimal = rank(&solutions, &config)?;
+        stats::write_stats(&solutions, &optimal, &config, 5, 10, io::stderr())?;
+    }
+    if args.pareto {
+        return print_pareto_front(&solutions, &config, args);
+    }
+    let solutions = rank(&solutions, &config)?;
+
+    if args.interactive {
+        // Each solution is shown the way it would be written out, so that
+        // `--template` and `--people` apply while choosing too
+        let show = |solution: &Solution| {
+            let result = run_result(args, &config, started, seed, solution)?;
+            write_chosen(args, &result, &config, &mut io::stdout())
+        };
+        let Some(solution) = choose_interactively(&solutions, show, &mut rng)? else {
+            return Ok(());
+        };
+        // The accepted solution has already been printed, so it only needs
+        // writing out if a file was asked for
+        if args.output.is_some() {
+            let result = run_result(args, &config, started, seed, solution)?;
+            write_output(args.output.as_deref(), |writer| {
+                write_chosen(args, &result, &config, writer)
+            })?;
+        }
+        if let Some(history) = &mut history {
+            commit_to_history(history, solution, seed, args)?;
+        }
+        record_run(args, &config, seed, solution)?;
+        if solution.unpreferred > 0 {
+            return Err(Failure::Unpreferred(solution.unpreferred).into());
+        }
+        return Ok(());
+    }
+
+    // Either a single solution is printed, or several numbered ones
+    let chosen = if args.all {
+        Some(solutions.clone())
+    } else {
+        args.top_k.map(|k| {
+            solutions
+                .choose_multiple(&mut rng, k)
+                .copied()
+                .collect::<Vec<_>>()
+        })
+    };
+    let solution = solutions.choose(&mut rng).unwrap();
+    let result = run_result(args, &config, started, seed, solution)?;
+    write_output(args.output.as_deref(), |writer| match &chosen {
+        Some(chosen) => output::write_results(chosen, &config, args.format, writer),
+        None => write_chosen(args, &result, &config, writer),
+    })?;
+    if chosen.is_none() {
+        if let Some(history) = &mut history {
+            commit_to_history(history, solution, seed, args)?;
+        }
+        record_run(args, &config, seed, solution)?;
+    }
+
+    if solution.unpreferred > 0 {
+        return Err(Failure::Unpreferred(solution.unpreferred).into());
+    }
+    Ok(())
+}
+
+fn watch_once(args: &cli::WatchArgs) -> Result<()> {
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let solutions = generate(&config, &mut rng)?;
+    let solutions = rank(&solutions, &config)?;
+    let solution = solutions.choose(&mut rng).unwrap();
+    output::write_result(solution, &config, args.format, io::stdout())
+}
+
+fn run_watch(args: &cli::WatchArgs) -> Result<()> {
+    if args.generate.config == Path::new("-") {
+        return Err(anyhow!("Can't watch stdin for changes"));
+    }
+
+    // The directory is watched rather than the file itself, as many editors
+    // save by replacing the file, which would end a watch on the file
+    let path = args.generate.config.canonicalize()?;
+    let directory = path
+        .parent()
+        .ok_or_else(|| anyhow!("Error finding directory of config file"))?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+    loop {
+        if let Err(error) = watch_once(args) {
+            eprintln!("{} {:#}", "ERROR".red().bold(), error);
+        }
+
+        let logger = log::info(format!(
+            "Waiting for changes to {}",
+            path.display().to_string().blue()
+        ))?;
+        loop {
+            let event = receiver.recv()??;
+            let relevant = (event.kind.is_modify() || event.kind.is_create())
+                && event
+                    .paths
+                    .iter()
+                    .any(|x| x.file_name() == path.file_name());
+            if relevant {
+                break;
+            }
+        }
+        // Saving often produces a burst of events, which only need one re-solve
+        while receiver.recv_timeout(Duration::from_millis(100)).is_ok() {}
+        logger.end();
+    }
+}
+
+fn run_stats(args: &cli::StatsArgs) -> Result<()> {
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let solutions = generate(&config, &mut rng)?;
+    let optimal = rank(&solutions, &config)?;
+    stats::write_stats(
+        &solutions,
+        &optimal,
+        &config,
+        args.top,
+        args.top_pairs,
+        io::stdout(),
+    )
+}
+
+fn run_explain(args: &cli::ExplainArgs) -> Result<()> {
+    let mut config = configure(&args.generate)?;
+    let pseudonyms = anonymize(&args.generate, &mut config)?;
+    let solution = match &args.solution {
+        Some(path) => {
+            let logger = log::info(format!(
+                "Reading solution from {}",
+                path.display().to_string().blue()
+            ))?;
+            let mut solution = serde_json::from_str(&fs::read_to_string(path)?)?;
+            pseudonyms.solution(&mut solution);
+            logger.end();
+            solution
+        }
+        None => {
+            let mut rng = make_rng(&args.generate, &config)?;
+            let solutions = generate(&config, &mut rng)?;
+            let optimal = rank(&solutions, &config)?;
+            optimal.choose(&mut rng).copied().unwrap().clone()
+        }
+    };
+    explain::write_explanation(&solution, &config, io::stdout())
+}
+
+fn run_graph(args: &cli::GraphArgs) -> Result<()> {
+    let mut config = configure(&args.generate)?;
+    let pseudonyms = anonymize(&args.generate, &mut config)?;
+    let solution = match &args.solution {
+        Some(path) => {
+            let mut solution = read_solution(path)?;
+            pseudonyms.solution(&mut solution);
+            Some(solution)
+        }
+        None if args.no_solution => None,
+        None => {
+            let mut rng = make_rng(&args.generate, &config)?;
+            let solutions = generate(&config, &mut rng)?;
+            let optimal = rank(&solutions, &config)?;
+            optimal.choose(&mut rng).copied().cloned()
+        }
+    };
+    write_output(args.output.as_deref(), |writer| {
+        graph::write_dot(&config, solution.as_ref(), writer)
+    })
+}
+
+fn run_santa(args: &cli::SantaArgs) -> Result<()> {
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let logger = log::info("Assigning everyone someone to give to")?;
+    let gifts = santa::assign(&config, &mut rng)?;
+    logger.end();
+    let Some(gifts) = gifts else {
+        return Err(Failure::Forbidden.into());
+    };
+    write_output(args.output.as_deref(), |writer| {
+        santa::write_assignment(&gifts, args.format, writer)
+    })?;
+
+    let unpreferred = gifts
         .iter()
-        .filter(|x| x.unpreferred == min_unpreferred)
-        .collect::<Vec<_>>();
+        .filter(|x| x.category == score::Category::Unpreferred)
+        .count();
+    if unpreferred > 0 {
+        return Err(Failure::Unpreferred(unpreferred as u64).into());
+    }
+    Ok(())
+}
+
+fn run_seat(args: &cli::SeatArgs) -> Result<()> {
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let logger = log::info(format!(
+        "Improving {} random seatings with swaps",
+        config.settings.restarts.to_string().blue()
+    ))?;
+    let seating = interrupt::solving(|| seating::seat(&config, &mut rng))??;
+    logger.end();
+    let Some(seating) = seating else {
+        return Err(Failure::Forbidden.into());
+    };
+    write_output(args.output.as_deref(), |writer| {
+        seating::write_seating(&seating, args.format, writer)
+    })?;
+
+    if seating.neighbours.unpreferred > 0 {
+        return Err(Failure::Unpreferred(seating.neighbours.unpreferred).into());
+    }
+    Ok(())
+}
+
+fn run_teams(args: &cli::TeamsArgs) -> Result<()> {
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let logger = log::info(format!(
+        "Improving {} random teams with swaps",
+        config.settings.restarts.to_string().blue()
+    ))?;
+    let split = interrupt::solving(|| teams::split(&config, &mut rng))??;
+    logger.end();
+    let Some(split) = split else {
+        return Err(Failure::Forbidden.into());
+    };
+    write_output(args.output.as_deref(), |writer| {
+        teams::write_split(&split, &config, args.format, writer)
+    })?;
+
+    if let (Some(spread), Some(teams)) = (split.spread(), &config.teams) {
+        if spread > teams.tolerance {
+            return Err(anyhow!(
+                "The teams' averages are {:.2} apart, more than the tolerance of {:.2}",
+                spread,
+                teams.tolerance
+            ));
+        }
+    }
+    if split.solution.unpreferred > 0 {
+        return Err(Failure::Unpreferred(split.solution.unpreferred).into());
+    }
+    Ok(())
+}
+
+/// The date of each round, if `--start` was given
+fn schedule_dates(args: &cli::ScheduleArgs, rounds: usize) -> Option<Vec<String>> {
+    let start = args.start?;
+    Some(
+        (0..rounds as u32)
+            .map(|i| start.after(args.every, i).to_string())
+            .collect(),
+    )
+}
+
+/// Prints the rounds in the chosen format, or as a calendar titled with
+/// `--title`
+fn write_schedule(
+    args: &cli::ScheduleArgs,
+    rounds: &[Solution],
+    dates: Option<&[String]>,
+    repeats: usize,
+    config: &Config,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    match (args.format, dates) {
+        (cli::Format::Ics, Some(dates)) => {
+            calendar::write_calendar(rounds, dates, &args.title, writer)
+        }
+        _ => output::write_rounds(rounds, dates, repeats, config, args.format, writer),
+    }
+}
+
+fn run_schedule(args: &cli::ScheduleArgs) -> Result<()> {
+    if matches!(args.format, cli::Format::Ics) && args.start.is_none() {
+        return Err(anyhow!(
+            "--format ics needs --start, so that each round has a date"
+        ));
+    }
+    let config = load_config(&args.generate)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let Some(count) = args.rounds else {
+        let logger = log::info("Scheduling a round robin")?;
+        let rounds = interrupt::solving(|| schedule::round_robin(&config, &mut rng))??;
+        logger.end();
+        if rounds.is_empty() {
+            return Err(anyhow!("Interrupted before the round robin was scheduled"));
+        }
+        // Every unpreferred pair shares a room in some round of a round
+        // robin, so they aren't a failure here
+        let dates = schedule_dates(args, rounds.len());
+        return write_output(args.output.as_deref(), |writer| {
+            write_schedule(args, &rounds, dates.as_deref(), 0, &config, writer)
+        });
+    };
+    let logger = log::info(format!("Scheduling {} rounds", count.to_string().blue()))?;
+    let rounds = interrupt::solving(|| schedule::schedule(&config, count, &mut rng))??;
+    logger.end();
+    if rounds.len() < count {
+        if rounds.is_empty() {
+            return Err(anyhow!("Interrupted before any rounds were scheduled"));
+        }
+        log::info(format!(
+            "Interrupted, so only the first {} rounds were scheduled",
+            rounds.len()
+        ))?
+        .end();
+    }
+    let repeats = schedule::total_repeats(&rounds, &config);
+    let dates = schedule_dates(args, rounds.len());
+    write_output(args.output.as_deref(), |writer| {
+        write_schedule(args, &rounds, dates.as_deref(), repeats, &config, writer)
+    })?;
+
+    let unpreferred = rounds.iter().map(|x| x.unpreferred).sum::<u64>();
+    if unpreferred > 0 {
+        return Err(Failure::Unpreferred(unpreferred).into());
+    }
+    Ok(())
+}
+
+/// Reads a solution written by `picker solve --format json`, leaving out the
+/// details of the run it came from
+fn read_solution(path: &Path) -> Result<Solution> {
+    let logger = log::info(format!(
+        "Reading the solution at {}",
+        path.display().to_string().blue()
+    ))?;
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    let solution = serde_json::from_str(&text).with_context(|| {
+        format!(
+            "{} isn't a solution written by `picker solve --format json`",
+            path.display()
+        )
+    })?;
+    logger.end();
+    Ok(solution)
+}
 
-    let max_preferred = solutions.iter().map(|x| x.preferred).max().unwrap();
-    let solutions = solutions
+fn run_diff(args: &cli::DiffArgs) -> Result<()> {
+    let old = read_solution(&args.old)?;
+    let new = read_solution(&args.new)?;
+    let diff = diff::diff(&old, &new);
+    write_output(args.output.as_deref(), |writer| {
+        diff::write_diff(&diff, args.format, writer)
+    })
+}
+
+fn run_amend(args: &cli::AmendArgs) -> Result<()> {
+    let mut config = configure(&args.generate)?;
+    let pseudonyms = anonymize(&args.generate, &mut config)?;
+    let mut rng = make_rng(&args.generate, &config)?;
+    let mut saved = read_solution(&args.solution)?;
+    pseudonyms.solution(&mut saved);
+    let remove = args
+        .remove
+        .iter()
+        .map(|x| pseudonyms.get(x))
+        .collect::<Vec<_>>();
+    let add = args
+        .add
         .iter()
-        .filter(|x| x.preferred == max_preferred)
+        .map(|x| pseudonyms.get(x))
         .collect::<Vec<_>>();
+
+    let logger = log::info("Re-solving the rooms that change")?;
+    let amended = interrupt::solving(|| amend::amend(&config, &saved, &remove, &add, &mut rng))??;
     logger.end();
+    let Some(solution) = amended else {
+        return Err(Failure::Forbidden.into());
+    };
+    write_output(args.output.as_deref(), |writer| {
+        output::write_result(&solution, &config, args.format, writer)
+    })?;
+    if solution.unpreferred > 0 {
+        return Err(Failure::Unpreferred(solution.unpreferred).into());
+    }
+    Ok(())
+}
 
-    log::info(format!(
-        "{} optimal solutions found",
-        solutions.len().to_string().blue()
-    ))?
-    .end();
+/// Prints the problems found in a config, failing if any of them are errors
+fn report_problems(config: &Config) -> Result<usize> {
+    let problems = validate::validate(config);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+
+    let errors = problems
+        .iter()
+        .filter(|x| x.severity == validate::Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(Failure::Config(anyhow!("Config has {} error(s)", errors)).into());
+    }
+    Ok(problems.len())
+}
+
+fn run_validate(args: &cli::ValidateArgs) -> Result<()> {
+    let config = read_config(&args.config, args.config_format, args.event.as_deref())?;
+    let warnings = report_problems(&config)?;
+    println!(
+        "{} config is valid ({} warning(s))",
+        "OK".green().bold(),
+        warnings
+    );
+    Ok(())
+}
+
+fn dry_run(config: &Config) -> Result<()> {
+    let warnings = report_problems(config)?;
+
+    let logger = log::info("Checking whether a solution without unpreferred matchups exists")?;
+    let possible = validate::zero_unpreferred_possible(config);
+    let separable = validate::never_pairs_separable(config);
+    logger.end();
+
+    let people = config.people().len();
+    println!("{}", "DRY RUN".green().bold());
+    println!(" warnings:              {}", warnings.to_string().blue());
+    println!(" people:                {}", people.to_string().blue());
+    println!(
+        " rooms:                 {}",
+        config.capacities().len().to_string().blue()
+    );
+    let text = match possible {
+        Some(true) => "possible".green(),
+        Some(false) => "impossible".red(),
+        None => "unknown, gave up searching".yellow(),
+    };
+    println!(" no unpreferred rooms:  {}", text);
+    if config.has_forbidden() {
+        let text = match separable {
+            Some(true) => "possible".green(),
+            Some(false) => "impossible".red(),
+            None => "unknown, gave up searching".yellow(),
+        };
+        println!(" no forbidden rooms:    {}", text);
+    }
+
+    if separable == Some(false) {
+        return Err(Failure::Forbidden.into());
+    }
+    if possible == Some(false) {
+        return Err(Failure::Infeasible.into());
+    }
+    Ok(())
+}
+
+fn run_init(args: &cli::InitArgs) -> Result<()> {
+    if args.config.exists() && !args.force {
+        return Err(anyhow!(
+            "{} already exists, use --force to overwrite it",
+            args.config.display()
+        ));
+    }
+
+    let people = if args.people.is_empty() {
+        init::DEFAULT_PEOPLE.map(String::from).to_vec()
+    } else {
+        args.people.iter().map(|x| x.trim().to_string()).collect()
+    };
+
+    let logger = log::info(format!(
+        "Writing starter config to {}",
+        args.config.display().to_string().blue()
+    ))?;
+    fs::write(&args.config, init::starter_config(&people))?;
+    logger.end();
+    Ok(())
+}
+
+fn run_import(args: &cli::ImportArgs) -> Result<()> {
+    let logger = log::info(format!(
+        "Reading survey from {}",
+        args.survey.display().to_string().blue()
+    ))?;
+    let file = File::open(&args.survey)?;
+    let format = if args.google_forms {
+        cli::ImportFormat::GoogleForms
+    } else {
+        args.format
+    };
+    let survey = match format {
+        cli::ImportFormat::Csv => import::read_csv(file, &args.name_column)?,
+        cli::ImportFormat::GoogleForms => {
+            let path = args
+                .mapping
+                .as_deref()
+                .ok_or_else(|| anyhow!("Importing from Google Forms needs a --mapping file"))?;
+            import::read_google_forms(file, &import::read_mapping(path)?)?
+        }
+    };
+    logger.end();
+    log::debug(format!("{} people in survey", survey.people.len()));
+
+    let text = init::config_text(&survey.people, &survey.preferred, &survey.unpreferred);
+    write_output(args.output.as_deref(), |writer| {
+        writer.write_all(text.as_bytes())?;
+        Ok(())
+    })
+}
+
+fn run_add_person(args: &cli::AddPersonArgs) -> Result<()> {
+    let mut editor = edit::Editor::read(&args.config)?;
+    let trim = |names: &[String]| {
+        names
+            .iter()
+            .map(|x| x.trim().to_string())
+            .collect::<Vec<_>>()
+    };
+    editor.add_person(
+        args.name.trim(),
+        &trim(&args.preferred),
+        &trim(&args.unpreferred),
+    )?;
+
+    let logger = log::info(format!(
+        "Adding {} to {}",
+        args.name.trim().blue(),
+        args.config.display().to_string().blue()
+    ))?;
+    editor.write(&args.config)?;
+    logger.end();
+    Ok(())
+}
 
-    display_result(solutions.choose(&mut rng).unwrap());
+fn run_remove_person(args: &cli::RemovePersonArgs) -> Result<()> {
+    let mut editor = edit::Editor::read(&args.config)?;
+    let removed = editor.remove_person(&args.name)?;
 
+    let logger = log::info(format!(
+        "Removing {} from {} places in {}",
+        args.name.blue(),
+        removed.to_string().blue(),
+        args.config.display().to_string().blue()
+    ))?;
+    editor.write(&args.config)?;
+    logger.end();
     Ok(())
 }
+
+fn run_completions(args: &cli::CompletionsArgs) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+}
+
+fn run(cli: &Cli) -> Result<()> {
+    match &cli.command {
+        Command::Solve(args) => run_solve(args),
+        Command::Validate(args) => run_validate(args),
+        Command::Init(args) => run_init(args),
+        Command::Stats(args) => run_stats(args),
+        Command::Explain(args) => run_explain(args),
+        Command::Santa(args) => run_santa(args),
+        Command::Seat(args) => run_seat(args),
+        Command::Teams(args) => run_teams(args),
+        Command::Schedule(args) => run_schedule(args),
+        Command::History(args) => run_history(args),
+        Command::Amend(args) => run_amend(args),
+        Command::Diff(args) => run_diff(args),
+        Command::Graph(args) => run_graph(args),
+        Command::Watch(args) => run_watch(args),
+        Command::Import(args) => run_import(args),
+        Command::AddPerson(args) => run_add_person(args),
+        Command::RemovePerson(args) => run_remove_person(args),
+        Command::Completions(args) => {
+            run_completions(args);
+            Ok(())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    log::set_verbosity(cli.verbosity());
+    log::init_color(cli.no_color);
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{} {:#}", "ERROR".red().bold(), error);
+            ExitCode::from(error::exit_code(&error))
+        }
+    }
+}
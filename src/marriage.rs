@@ -0,0 +1,95 @@
+//! The Gale–Shapley algorithm for two-sided stable matching. A matching is
+//! stable if no proposer and receiver would both rather be matched with each
+//! other than with who they got. It is the best stable matching for every
+//! proposer, and the worst for every receiver
+
+/// Finds a stable matching given each proposer's preference list over the
+/// receivers and each receiver's list over the proposers, best first. Anyone
+/// left out of a list is never matched with them. Returns each proposer's
+/// receiver, or `None` for proposers left on their own
+pub fn stable_marriage(proposers: &[Vec<usize>], receivers: &[Vec<usize>]) -> Vec<Option<usize>> {
+    let mut rank = vec![vec![usize::MAX; proposers.len()]; receivers.len()];
+    for (b, list) in receivers.iter().enumerate() {
+        for (i, &a) in list.iter().enumerate() {
+            rank[b][a] = i;
+        }
+    }
+
+    // Everyone free proposes to the next receiver on their list, who holds on
+    // to the best proposal they have had and turns the rest away
+    let mut next = vec![0; proposers.len()];
+    let mut holding = vec![None::<usize>; receivers.len()];
+    let mut free = (0..proposers.len()).rev().collect::<Vec<_>>();
+    while let Some(a) = free.pop() {
+        while let Some(&b) = proposers[a].get(next[a]) {
+            next[a] += 1;
+            if rank[b][a] == usize::MAX {
+                continue;
+            }
+            if holding[b].is_none_or(|x| rank[b][a] < rank[b][x]) {
+                if let Some(previous) = holding[b].replace(a) {
+                    free.push(previous);
+                }
+                break;
+            }
+        }
+    }
+
+    let mut mates = vec![None; proposers.len()];
+    for (b, a) in holding.iter().enumerate() {
+        if let Some(a) = a {
+            mates[*a] = Some(b);
+        }
+    }
+    mates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    #[test]
+    fn leaves_no_blocking_pairs() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for (proposing, receiving) in [(3, 3), (4, 6), (6, 4)] {
+            for _ in 0..50 {
+                let mut lists = |people: usize, others: usize| {
+                    (0..people)
+                        .map(|_| {
+                            let mut list = (0..others).collect::<Vec<_>>();
+                            list.shuffle(&mut rng);
+                            list
+                        })
+                        .collect::<Vec<_>>()
+                };
+                let proposers = lists(proposing, receiving);
+                let receivers = lists(receiving, proposing);
+                let mates = stable_marriage(&proposers, &receivers);
+                let mut held = vec![None; receiving];
+                for (a, b) in mates.iter().enumerate() {
+                    if let Some(b) = b {
+                        assert_eq!(held[*b].replace(a), None);
+                    }
+                }
+                assert_eq!(mates.iter().flatten().count(), proposing.min(receiving));
+
+                let rank = |list: &[usize], x| list.iter().position(|&y| y == x);
+                for a in 0..proposing {
+                    for b in 0..receiving {
+                        let proposer = mates[a]
+                            .is_none_or(|x| rank(&proposers[a], b) < rank(&proposers[a], x));
+                        let receiver =
+                            held[b].is_none_or(|x| rank(&receivers[b], a) < rank(&receivers[b], x));
+                        assert!(
+                            !(proposer && receiver),
+                            "{} and {} would rather be matched",
+                            a,
+                            b
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,578 @@
+//! Maximum weight matching in general graphs, using Edmonds' blossom
+//! algorithm with dual variables. This follows Joris van Rantwijk's well known
+//! implementation, with every weight kept as an integer so no precision is
+//! lost. Vertices and edges are numbered from 0, and each edge `k` has two
+//! endpoints, `2k` and `2k + 1`
+
+/// Stands in for a missing vertex, edge, endpoint or blossom
+const NONE: usize = usize::MAX;
+
+struct Matcher<'a> {
+    edges: &'a [(usize, usize, i128)],
+    vertices: usize,
+    /// The vertex at each endpoint
+    endpoint: Vec<usize>,
+    /// The remote endpoints of the edges touching each vertex
+    neighbours: Vec<Vec<usize>>,
+    /// The remote endpoint of each vertex's matched edge
+    mate: Vec<usize>,
+    /// 0 for unlabelled, 1 for S, 2 for T, with 4 and 5 used while scanning
+    label: Vec<u8>,
+    /// The endpoint through which each vertex or blossom got its label
+    label_end: Vec<usize>,
+    /// The top level blossom each vertex belongs to
+    in_blossom: Vec<usize>,
+    blossom_parent: Vec<usize>,
+    blossom_children: Vec<Vec<usize>>,
+    blossom_base: Vec<usize>,
+    /// The endpoints of the edges between each blossom's children
+    blossom_endpoints: Vec<Vec<usize>>,
+    /// The least slack edge from each vertex or blossom to a different S
+    /// blossom
+    best_edge: Vec<usize>,
+    blossom_best_edges: Vec<Option<Vec<usize>>>,
+    unused_blossoms: Vec<usize>,
+    dual: Vec<i128>,
+    allowed: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(vertices: usize, edges: &'a [(usize, usize, i128)]) -> Self {
+        let max_weight = edges.iter().map(|x| x.2).max().unwrap_or(0).max(0);
+        let mut neighbours = vec![vec![]; vertices];
+        for (k, &(i, j, _)) in edges.iter().enumerate() {
+            neighbours[i].push(2 * k + 1);
+            neighbours[j].push(2 * k);
+        }
+        Self {
+            edges,
+            vertices,
+            endpoint: (0..2 * edges.len())
+                .map(|p| {
+                    let (i, j, _) = edges[p / 2];
+                    if p % 2 == 0 {
+                        i
+                    } else {
+                        j
+                    }
+                })
+                .collect(),
+            neighbours,
+            mate: vec![NONE; vertices],
+            label: vec![0; 2 * vertices],
+            label_end: vec![NONE; 2 * vertices],
+            in_blossom: (0..vertices).collect(),
+            blossom_parent: vec![NONE; 2 * vertices],
+            blossom_children: vec![vec![]; 2 * vertices],
+            blossom_base: (0..vertices).chain((0..vertices).map(|_| NONE)).collect(),
+            blossom_endpoints: vec![vec![]; 2 * vertices],
+            best_edge: vec![NONE; 2 * vertices],
+            blossom_best_edges: vec![None; 2 * vertices],
+            unused_blossoms: (vertices..2 * vertices).collect(),
+            dual: (0..2 * vertices)
+                .map(|x| if x < vertices { max_weight } else { 0 })
+                .collect(),
+            allowed: vec![false; edges.len()],
+            queue: vec![],
+        }
+    }
+
+    /// Twice the amount by which an edge's weight falls short of its
+    /// endpoints' duals
+    fn slack(&self, k: usize) -> i128 {
+        let (i, j, weight) = self.edges[k];
+        self.dual[i] + self.dual[j] - 2 * weight
+    }
+
+    /// The vertices inside a blossom, however deeply nested
+    fn leaves(&self, b: usize) -> Vec<usize> {
+        if b < self.vertices {
+            return vec![b];
+        }
+        let mut leaves = vec![];
+        for &child in &self.blossom_children[b] {
+            leaves.extend(self.leaves(child));
+        }
+        leaves
+    }
+
+    /// Labels a vertex's top level blossom as S (1) or T (2), reached through
+    /// endpoint `p`. A T blossom's mate is labelled S in turn
+    fn assign_label(&mut self, w: usize, t: u8, p: usize) {
+        let b = self.in_blossom[w];
+        self.label[w] = t;
+        self.label[b] = t;
+        self.label_end[w] = p;
+        self.label_end[b] = p;
+        self.best_edge[w] = NONE;
+        self.best_edge[b] = NONE;
+        if t == 1 {
+            let leaves = self.leaves(b);
+            self.queue.extend(leaves);
+        } else {
+            let base = self.blossom_base[b];
+            let mate = self.mate[base];
+            self.assign_label(self.endpoint[mate], 1, mate ^ 1);
+        }
+    }
+
+    /// Traces back from two S vertices joined by an edge, returning the base
+    /// of the new blossom they form, or `NONE` if they lead to different
+    /// exposed vertices and so form an augmenting path
+    fn scan_blossom(&mut self, mut v: usize, mut w: usize) -> usize {
+        let mut path = vec![];
+        let mut base = NONE;
+        while v != NONE || w != NONE {
+            let mut b = self.in_blossom[v];
+            if self.label[b] & 4 != 0 {
+                base = self.blossom_base[b];
+                break;
+            }
+            path.push(b);
+            self.label[b] = 5;
+            if self.label_end[b] == NONE {
+                v = NONE;
+            } else {
+                v = self.endpoint[self.label_end[b]];
+                b = self.in_blossom[v];
+                v = self.endpoint[self.label_end[b]];
+            }
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = 1;
+        }
+        base
+    }
+
+    /// Makes a new blossom out of the cycle closed by edge `k`
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.in_blossom[base];
+        let mut bv = self.in_blossom[v];
+        let mut bw = self.in_blossom[w];
+        let b = self.unused_blossoms.pop().unwrap();
+        self.blossom_base[b] = base;
+        self.blossom_parent[b] = NONE;
+        self.blossom_parent[bb] = b;
+
+        let mut path = vec![];
+        let mut endpoints = vec![];
+        while bv != bb {
+            self.blossom_parent[bv] = b;
+            path.push(bv);
+            endpoints.push(self.label_end[bv]);
+            v = self.endpoint[self.label_end[bv]];
+            bv = self.in_blossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endpoints.reverse();
+        endpoints.push(2 * k);
+        while bw != bb {
+            self.blossom_parent[bw] = b;
+            path.push(bw);
+            endpoints.push(self.label_end[bw] ^ 1);
+            w = self.endpoint[self.label_end[bw]];
+            bw = self.in_blossom[w];
+        }
+
+        self.label[b] = 1;
+        self.label_end[b] = self.label_end[bb];
+        self.dual[b] = 0;
+        for v in self.leaves_of(&path) {
+            if self.label[self.in_blossom[v]] == 2 {
+                self.queue.push(v);
+            }
+            self.in_blossom[v] = b;
+        }
+
+        let mut best_edge_to = vec![NONE; 2 * self.vertices];
+        for &bv in &path {
+            let lists = match self.blossom_best_edges[bv].take() {
+                Some(list) => vec![list],
+                None => self
+                    .leaves(bv)
+                    .into_iter()
+                    .map(|v| self.neighbours[v].iter().map(|p| p / 2).collect())
+                    .collect(),
+            };
+            for k in lists.into_iter().flatten() {
+                let (mut i, mut j, _) = self.edges[k];
+                if self.in_blossom[j] == b {
+                    std::mem::swap(&mut i, &mut j);
+                }
+                let bj = self.in_blossom[j];
+                if bj != b
+                    && self.label[bj] == 1
+                    && (best_edge_to[bj] == NONE || self.slack(k) < self.slack(best_edge_to[bj]))
+                {
+                    best_edge_to[bj] = k;
+                }
+            }
+            self.best_edge[bv] = NONE;
+        }
+        let best_edges = best_edge_to
+            .into_iter()
+            .filter(|&k| k != NONE)
+            .collect::<Vec<_>>();
+        self.best_edge[b] = NONE;
+        for &k in &best_edges {
+            if self.best_edge[b] == NONE || self.slack(k) < self.slack(self.best_edge[b]) {
+                self.best_edge[b] = k;
+            }
+        }
+        self.blossom_best_edges[b] = Some(best_edges);
+        self.blossom_children[b] = path;
+        self.blossom_endpoints[b] = endpoints;
+    }
+
+    fn leaves_of(&self, blossoms: &[usize]) -> Vec<usize> {
+        blossoms.iter().flat_map(|&x| self.leaves(x)).collect()
+    }
+
+    /// Where to start walking around a blossom from child `i`, so that the
+    /// walk back to the base goes through an even number of edges. Returns the
+    /// signed starting position, the step and the endpoint trick
+    fn walk(&self, b: usize, i: usize) -> (isize, isize, usize) {
+        if i & 1 == 1 {
+            (i as isize - self.blossom_children[b].len() as isize, 1, 0)
+        } else {
+            (i as isize, -1, 1)
+        }
+    }
+
+    fn child(&self, b: usize, j: isize) -> usize {
+        let children = &self.blossom_children[b];
+        children[j.rem_euclid(children.len() as isize) as usize]
+    }
+
+    fn endpoint_at(&self, b: usize, j: isize) -> usize {
+        let endpoints = &self.blossom_endpoints[b];
+        endpoints[j.rem_euclid(endpoints.len() as isize) as usize]
+    }
+
+    /// Breaks a blossom back up into its children, relabelling them if it
+    /// was a T blossom in the middle of a stage
+    fn expand_blossom(&mut self, b: usize, end_stage: bool) {
+        for s in self.blossom_children[b].clone() {
+            self.blossom_parent[s] = NONE;
+            if s < self.vertices {
+                self.in_blossom[s] = s;
+            } else if end_stage && self.dual[s] == 0 {
+                self.expand_blossom(s, end_stage);
+            } else {
+                for v in self.leaves(s) {
+                    self.in_blossom[v] = s;
+                }
+            }
+        }
+
+        if !end_stage && self.label[b] == 2 {
+            let entry_child = self.in_blossom[self.endpoint[self.label_end[b] ^ 1]];
+            let index = self.blossom_children[b]
+                .iter()
+                .position(|&x| x == entry_child)
+                .unwrap();
+            let (mut j, step, trick) = self.walk(b, index);
+            let mut p = self.label_end[b];
+            while j != 0 {
+                self.label[self.endpoint[p ^ 1]] = 0;
+                let q = self.endpoint_at(b, j - trick as isize);
+                self.label[self.endpoint[q ^ trick ^ 1]] = 0;
+                self.assign_label(self.endpoint[p ^ 1], 2, p);
+                self.allowed[q / 2] = true;
+                j += step;
+                p = self.endpoint_at(b, j - trick as isize) ^ trick;
+                self.allowed[p / 2] = true;
+                j += step;
+            }
+            let bv = self.child(b, j);
+            let v = self.endpoint[p ^ 1];
+            self.label[v] = 2;
+            self.label[bv] = 2;
+            self.label_end[v] = p;
+            self.label_end[bv] = p;
+            self.best_edge[bv] = NONE;
+            j += step;
+            while self.child(b, j) != entry_child {
+                let bv = self.child(b, j);
+                if self.label[bv] == 1 {
+                    j += step;
+                    continue;
+                }
+                let labelled = self.leaves(bv).into_iter().find(|&v| self.label[v] != 0);
+                if let Some(v) = labelled {
+                    self.label[v] = 0;
+                    let mate = self.mate[self.blossom_base[bv]];
+                    self.label[self.endpoint[mate]] = 0;
+                    self.assign_label(v, 2, self.label_end[v]);
+                }
+                j += step;
+            }
+        }
+
+        self.label[b] = 0;
+        self.label_end[b] = NONE;
+        self.blossom_children[b].clear();
+        self.blossom_endpoints[b].clear();
+        self.blossom_base[b] = NONE;
+        self.blossom_best_edges[b] = None;
+        self.best_edge[b] = NONE;
+        self.unused_blossoms.push(b);
+    }
+
+    /// Swaps matched and unmatched edges along the path through blossom `b`
+    /// from vertex `v` to its base, making `v` the new base
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        let mut t = v;
+        while self.blossom_parent[t] != b {
+            t = self.blossom_parent[t];
+        }
+        if t >= self.vertices {
+            self.augment_blossom(t, v);
+        }
+        let i = self.blossom_children[b]
+            .iter()
+            .position(|&x| x == t)
+            .unwrap();
+        let (mut j, step, trick) = self.walk(b, i);
+        while j != 0 {
+            j += step;
+            let t = self.child(b, j);
+            let p = self.endpoint_at(b, j - trick as isize) ^ trick;
+            if t >= self.vertices {
+                self.augment_blossom(t, self.endpoint[p]);
+            }
+            j += step;
+            let t = self.child(b, j);
+            if t >= self.vertices {
+                self.augment_blossom(t, self.endpoint[p ^ 1]);
+            }
+            self.mate[self.endpoint[p]] = p ^ 1;
+            self.mate[self.endpoint[p ^ 1]] = p;
+        }
+        self.blossom_children[b].rotate_left(i);
+        self.blossom_endpoints[b].rotate_left(i);
+        self.blossom_base[b] = self.blossom_base[self.blossom_children[b][0]];
+    }
+
+    /// Swaps matched and unmatched edges along the augmenting path through
+    /// edge `k`
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for (mut s, mut p) in [(v, 2 * k + 1), (w, 2 * k)] {
+            loop {
+                let bs = self.in_blossom[s];
+                if bs >= self.vertices {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p;
+                if self.label_end[bs] == NONE {
+                    break;
+                }
+                let t = self.endpoint[self.label_end[bs]];
+                let bt = self.in_blossom[t];
+                s = self.endpoint[self.label_end[bt]];
+                let j = self.endpoint[self.label_end[bt] ^ 1];
+                if bt >= self.vertices {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.label_end[bt];
+                p = self.label_end[bt] ^ 1;
+            }
+        }
+    }
+
+    /// Grows the alternating trees from the queue of S vertices. Returns
+    /// whether the matching was augmented
+    fn grow(&mut self) -> bool {
+        while let Some(v) = self.queue.pop() {
+            for p in self.neighbours[v].clone() {
+                let k = p / 2;
+                let w = self.endpoint[p];
+                if self.in_blossom[v] == self.in_blossom[w] {
+                    continue;
+                }
+                let mut slack = 0;
+                if !self.allowed[k] {
+                    slack = self.slack(k);
+                    if slack <= 0 {
+                        self.allowed[k] = true;
+                    }
+                }
+                let bw = self.in_blossom[w];
+                if self.allowed[k] {
+                    if self.label[bw] == 0 {
+                        self.assign_label(w, 2, p ^ 1);
+                    } else if self.label[bw] == 1 {
+                        let base = self.scan_blossom(v, w);
+                        if base != NONE {
+                            self.add_blossom(base, k);
+                        } else {
+                            self.augment_matching(k);
+                            return true;
+                        }
+                    } else if self.label[w] == 0 {
+                        self.label[w] = 2;
+                        self.label_end[w] = p ^ 1;
+                    }
+                } else if self.label[bw] == 1 {
+                    let b = self.in_blossom[v];
+                    if self.best_edge[b] == NONE || slack < self.slack(self.best_edge[b]) {
+                        self.best_edge[b] = k;
+                    }
+                } else if self.label[w] == 0
+                    && (self.best_edge[w] == NONE || slack < self.slack(self.best_edge[w]))
+                {
+                    self.best_edge[w] = k;
+                }
+            }
+        }
+        false
+    }
+
+    /// Runs one stage, looking for an augmenting path and adjusting the duals
+    /// until one is found or none can be. Returns whether one was found
+    fn stage(&mut self, max_cardinality: bool) -> bool {
+        self.label.fill(0);
+        self.best_edge.fill(NONE);
+        for b in self.vertices..2 * self.vertices {
+            self.blossom_best_edges[b] = None;
+        }
+        self.allowed.fill(false);
+        self.queue.clear();
+        for v in 0..self.vertices {
+            if self.mate[v] == NONE && self.label[self.in_blossom[v]] == 0 {
+                self.assign_label(v, 1, NONE);
+            }
+        }
+
+        loop {
+            if self.grow() {
+                return true;
+            }
+
+            // 1: stop, as every exposed vertex's dual reached zero
+            // 2: an S vertex can reach a free vertex
+            // 3: two S blossoms can be joined
+            // 4: a T blossom's dual reached zero, so it can be expanded
+            let mut kind = 0;
+            let mut delta = 0;
+            let mut edge = NONE;
+            let mut blossom = NONE;
+            let min_dual = self.dual[..self.vertices]
+                .iter()
+                .copied()
+                .min()
+                .unwrap_or(0);
+            if !max_cardinality {
+                kind = 1;
+                delta = min_dual;
+            }
+            for v in 0..self.vertices {
+                if self.label[self.in_blossom[v]] == 0 && self.best_edge[v] != NONE {
+                    let d = self.slack(self.best_edge[v]);
+                    if kind == 0 || d < delta {
+                        delta = d;
+                        kind = 2;
+                        edge = self.best_edge[v];
+                    }
+                }
+            }
+            for b in 0..2 * self.vertices {
+                if self.blossom_parent[b] == NONE && self.label[b] == 1 && self.best_edge[b] != NONE
+                {
+                    let d = self.slack(self.best_edge[b]) / 2;
+                    if kind == 0 || d < delta {
+                        delta = d;
+                        kind = 3;
+                        edge = self.best_edge[b];
+                    }
+                }
+            }
+            for b in self.vertices..2 * self.vertices {
+                if self.blossom_base[b] != NONE
+                    && self.blossom_parent[b] == NONE
+                    && self.label[b] == 2
+                    && (kind == 0 || self.dual[b] < delta)
+                {
+                    delta = self.dual[b];
+                    kind = 4;
+                    blossom = b;
+                }
+            }
+            if kind == 0 {
+                kind = 1;
+                delta = min_dual.max(0);
+            }
+
+            for v in 0..self.vertices {
+                match self.label[self.in_blossom[v]] {
+                    1 => self.dual[v] -= delta,
+                    2 => self.dual[v] += delta,
+                    _ => {}
+                }
+            }
+            for b in self.vertices..2 * self.vertices {
+                if self.blossom_base[b] != NONE && self.blossom_parent[b] == NONE {
+                    match self.label[b] {
+                        1 => self.dual[b] += delta,
+                        2 => self.dual[b] -= delta,
+                        _ => {}
+                    }
+                }
+            }
+
+            match kind {
+                1 => return false,
+                2 | 3 => {
+                    self.allowed[edge] = true;
+                    let (mut i, j, _) = self.edges[edge];
+                    if self.label[self.in_blossom[i]] == 0 {
+                        i = j;
+                    }
+                    self.queue.push(i);
+                }
+                _ => self.expand_blossom(blossom, false),
+            }
+        }
+    }
+}
+
+/// Finds a matching with the largest total weight, given edges as
+/// `(vertex, vertex, weight)`. If `max_cardinality` is set, only matchings
+/// with as many edges as possible are considered. Returns each vertex's mate
+pub fn max_weight_matching(
+    vertices: usize,
+    edges: &[(usize, usize, i128)],
+    max_cardinality: bool,
+) -> Vec<Option<usize>> {
+    if edges.is_empty() {
+        return vec![None; vertices];
+    }
+    let mut matcher = Matcher::new(vertices, edges);
+    for _ in 0..vertices {
+        if !matcher.stage(max_cardinality) {
+            break;
+        }
+        // Blossoms whose dual reached zero can be broken up between stages
+        for b in vertices..2 * vertices {
+            if matcher.blossom_parent[b] == NONE
+                && matcher.blossom_base[b] != NONE
+                && matcher.label[b] == 1
+                && matcher.dual[b] == 0
+            {
+                matcher.expand_blossom(b, true);
+            }
+        }
+    }
+    matcher
+        .mate
+        .iter()
+        .map(|&p| (p != NONE).then(|| matcher.endpoint[p]))
+        .collect()
+}
@@ -0,0 +1,491 @@
+use std::io::Write;
+
+use crate::{
+    calendar,
+    cli::Format,
+    config::{Config, Count},
+    html, log,
+    score::{
+        classify_room, max_preferred_pairs, preferred_pairs, room_weight, Category, RunResult,
+        Solution,
+    },
+};
+use anyhow::Result;
+use colored::{ColoredString, Colorize};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Table};
+use serde::Serialize;
+
+pub fn write_result<W: Write>(
+    solution: &Solution,
+    config: &Config,
+    format: Format,
+    writer: W,
+) -> Result<()> {
+    match format {
+        Format::Text => write_text(solution, config, "RESULT", writer),
+        Format::Json => write_json(solution, writer),
+        Format::Csv => write_csv(&[solution], config, None, None, writer),
+        Format::Markdown => write_markdown(solution, config, "## Result", writer),
+        Format::Html => {
+            let sections = vec![html::section(solution, config, None)];
+            html::write_html("Result", &[], sections, writer)
+        }
+        Format::Ics => Err(calendar::unsupported()),
+    }
+}
+
+/// Writes a solution along with the run that chose it. Only JSON has room
+/// for the details of the run, so the other formats match `write_result`,
+/// followed by how each person did if that was asked for
+pub fn write_run_result<W: Write>(
+    result: &RunResult,
+    config: &Config,
+    format: Format,
+    mut writer: W,
+) -> Result<()> {
+    let Some(people) = &result.people else {
+        return match format {
+            Format::Json => write_json(result, writer),
+            _ => write_result(&result.solution, config, format, writer),
+        };
+    };
+    match format {
+        Format::Text => {
+            write_text(&result.solution, config, "RESULT", &mut writer)?;
+            writeln!(writer)?;
+            writeln!(writer, "{}", "PEOPLE".green().bold())?;
+            let width = people.iter().map(|x| x.name.len()).max().unwrap_or(0) + 2;
+            for outcome in people {
+                let name = format!("{:<width$}", outcome.name);
+                writeln!(writer, " {}{}", name.blue(), label(outcome.category))?;
+            }
+            Ok(())
+        }
+        Format::Json => write_json(result, writer),
+        Format::Csv => write_result(&result.solution, config, format, writer),
+        Format::Markdown => {
+            write_markdown(&result.solution, config, "## Result", &mut writer)?;
+            writeln!(writer)?;
+            writeln!(writer, "### People")?;
+            writeln!(writer)?;
+            writeln!(writer, "| person | outcome |")?;
+            writeln!(writer, "| --- | --- |")?;
+            for outcome in people {
+                let name = escape_markdown(&outcome.name);
+                writeln!(writer, "| {} | {} |", name, outcome.category.repr())?;
+            }
+            Ok(())
+        }
+        Format::Html => {
+            let section = html::section(&result.solution, config, None).with_people(people);
+            html::write_html("Result", &[], vec![section], writer)
+        }
+        Format::Ics => Err(calendar::unsupported()),
+    }
+}
+
+/// Writes several solutions at once, numbering them so they can be told apart
+pub fn write_results<W: Write>(
+    solutions: &[&Solution],
+    config: &Config,
+    format: Format,
+    mut writer: W,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            for (i, solution) in solutions.iter().enumerate() {
+                if i > 0 {
+                    writeln!(writer)?;
+                }
+                write_text(solution, config, &format!("RESULT {}", i + 1), &mut writer)?;
+            }
+            Ok(())
+        }
+        Format::Json => write_json(solutions, writer),
+        Format::Csv => write_csv(solutions, config, Some("solution"), None, writer),
+        Format::Markdown => {
+            for (i, solution) in solutions.iter().enumerate() {
+                if i > 0 {
+                    writeln!(writer)?;
+                }
+                let heading = format!("## Result {}", i + 1);
+                write_markdown(solution, config, &heading, &mut writer)?;
+            }
+            Ok(())
+        }
+        Format::Html => {
+            let sections = solutions
+                .iter()
+                .enumerate()
+                .map(|(i, x)| html::section(x, config, Some(format!("Result {}", i + 1))))
+                .collect();
+            html::write_html("Results", &[], sections, writer)
+        }
+        Format::Ics => Err(calendar::unsupported()),
+    }
+}
+
+/// A round of a schedule, along with when it happens
+#[derive(Serialize)]
+struct Dated<'a> {
+    date: String,
+    #[serde(flatten)]
+    round: &'a Solution,
+}
+
+/// Writes each round of a schedule in order, after how many pairs shared a
+/// room again after an earlier round. If there are dates, each round is
+/// written with its own
+pub fn write_rounds<W: Write>(
+    rounds: &[Solution],
+    dates: Option<&[String]>,
+    repeats: usize,
+    config: &Config,
+    format: Format,
+    mut writer: W,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            writeln!(writer, "{}", "SCHEDULE".green().bold())?;
+            writeln!(
+                writer,
+                " {:<20}{}",
+                "repeated pairs:",
+                repeats.to_string().blue()
+            )?;
+            for (i, round) in rounds.iter().enumerate() {
+                writeln!(writer)?;
+                let title = match dates {
+                    Some(dates) => format!("ROUND {} ({})", i + 1, dates[i]),
+                    None => format!("ROUND {}", i + 1),
+                };
+                write_text(round, config, &title, &mut writer)?;
+            }
+            Ok(())
+        }
+        Format::Json => match dates {
+            Some(dates) => {
+                let rounds = rounds
+                    .iter()
+                    .zip(dates)
+                    .map(|(round, date)| Dated {
+                        date: date.clone(),
+                        round,
+                    })
+                    .collect::<Vec<_>>();
+                write_json(&rounds, writer)
+            }
+            None => write_json(rounds, writer),
+        },
+        Format::Csv => {
+            let rounds = rounds.iter().collect::<Vec<_>>();
+            write_csv(&rounds, config, Some("round"), dates, writer)
+        }
+        Format::Markdown => {
+            writeln!(writer, "## Schedule")?;
+            writeln!(writer)?;
+            writeln!(writer, "- **Repeated pairs:** {}", repeats)?;
+            for (i, round) in rounds.iter().enumerate() {
+                writeln!(writer)?;
+                let heading = match dates {
+                    Some(dates) => format!("### Round {} ({})", i + 1, dates[i]),
+                    None => format!("### Round {}", i + 1),
+                };
+                write_markdown(round, config, &heading, &mut writer)?;
+            }
+            Ok(())
+        }
+        Format::Html => {
+            let sections = rounds
+                .iter()
+                .enumerate()
+                .map(|(i, round)| {
+                    let heading = match dates {
+                        Some(dates) => format!("Round {} ({})", i + 1, dates[i]),
+                        None => format!("Round {}", i + 1),
+                    };
+                    html::section(round, config, Some(heading))
+                })
+                .collect();
+            let summary = [("repeated pairs:", repeats.to_string())];
+            html::write_html("Schedule", &summary, sections, writer)
+        }
+        // The events' titles come from `picker schedule --title`, which
+        // writes the calendar itself
+        Format::Ics => Err(calendar::unsupported()),
+    }
+}
+
+/// The counts printed above a solution's rooms, with their labels
+pub fn summary(solution: &Solution, config: &Config) -> Vec<(&'static str, String)> {
+    let mut lines = vec![];
+    if config.tiered() {
+        lines.push((
+            "strongly preferred matchups:",
+            solution.strongly_preferred.to_string(),
+        ));
+    }
+    lines.push(("preferred matchups:", solution.preferred.to_string()));
+    if config.tiered() {
+        lines.push(("acceptable matchups:", solution.acceptable.to_string()));
+    }
+    lines.push(("accepted matchups:", solution.accepted.to_string()));
+    lines.push(("unpreferred matchups:", solution.unpreferred.to_string()));
+    if config.weighted() {
+        lines.push(("preferred weight:", solution.weight.to_string()));
+    }
+    if let Some(worst) = solution.worst_off {
+        if config.settings.objective.uses(Count::WorstOff) {
+            lines.push(("worst-off person's roommate:", worst.repr().to_string()));
+        }
+    }
+    // How the solution compares to the best any solution could do, as the
+    // counts alone don't say whether more was possible
+    let (reached, most) = (
+        preferred_pairs(solution, config),
+        max_preferred_pairs(config),
+    );
+    lines.push(("most preferred pairs possible:", most.to_string()));
+    let share = match most {
+        0 => 100,
+        _ => reached * 100 / most,
+    };
+    lines.push((
+        "preferred pairs reached:",
+        format!("{} ({}%)", reached, share),
+    ));
+    lines
+}
+
+fn write_text<W: Write>(
+    solution: &Solution,
+    config: &Config,
+    title: &str,
+    mut writer: W,
+) -> Result<()> {
+    writeln!(writer, "{}", title.green().bold())?;
+    for line in room_table(solution, config).lines() {
+        writeln!(writer, " {}", line)?;
+    }
+    let lines = summary(solution, config);
+    let width = lines.iter().map(|x| x.0.len()).max().unwrap_or_default() + 2;
+    for (label, value) in lines {
+        writeln!(writer, " {:<width$}{}", label, value.blue())?;
+    }
+    Ok(())
+}
+
+/// Lays out the rooms as a table with one row per room, so that long
+/// rosters line up. Rooms are numbered from 1 unless they have names
+fn room_table(solution: &Solution, config: &Config) -> Table {
+    let size = solution.rooms.iter().map(Vec::len).max().unwrap_or(2);
+    let header = room_header(size, config);
+
+    let mut table = Table::new();
+    table.load_style(UTF8_FULL_CONDENSED);
+    table.set_header(header.iter().map(|x| x.bold().to_string()));
+    for (i, room) in solution.rooms.iter().enumerate() {
+        let name = match solution.names.get(i) {
+            Some(name) => name.bold().to_string(),
+            None => (i + 1).to_string().bold().to_string(),
+        };
+        let mut row = vec![name];
+        row.extend(room.iter().map(|x| x.blue().to_string()));
+        row.resize(size + 1, String::new());
+        row.push(label(classify_room(room, config)).to_string());
+        if config.weighted() {
+            row.push(room_weight(room, config).to_string());
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+/// The columns of a table of rooms with at most `size` people in each, and
+/// how much each room's preferred matchups weigh under weighted preferences
+fn room_header(size: usize, config: &Config) -> Vec<String> {
+    let mut header = vec!["room".to_string()];
+    header.extend(
+        (0..size).map(|i| match u8::try_from(i).ok().filter(|&x| x < 26) {
+            Some(letter) => format!("person {}", (b'A' + letter) as char),
+            None => format!("person {}", i + 1),
+        }),
+    );
+    header.push("match".to_string());
+    if config.weighted() {
+        header.push("weight".to_string());
+    }
+    header
+}
+
+/// Writes a solution as a Markdown heading, a table of its rooms and a list
+/// of its counts
+fn write_markdown<W: Write>(
+    solution: &Solution,
+    config: &Config,
+    heading: &str,
+    mut writer: W,
+) -> Result<()> {
+    writeln!(writer, "{}", heading)?;
+    writeln!(writer)?;
+    let size = solution.rooms.iter().map(Vec::len).max().unwrap_or(2);
+    let header = room_header(size, config);
+    writeln!(writer, "| {} |", header.join(" | "))?;
+    writeln!(writer, "|{}", " --- |".repeat(header.len()))?;
+    for (i, room) in solution.rooms.iter().enumerate() {
+        let mut row = vec![match solution.names.get(i) {
+            Some(name) => escape_markdown(name),
+            None => (i + 1).to_string(),
+        }];
+        row.extend(room.iter().map(|x| escape_markdown(x)));
+        row.resize(size + 1, String::new());
+        row.push(classify_room(room, config).repr().to_string());
+        if config.weighted() {
+            row.push(room_weight(room, config).to_string());
+        }
+        writeln!(writer, "| {} |", row.join(" | "))?;
+    }
+    writeln!(writer)?;
+    for (label, value) in summary(solution, config) {
+        writeln!(writer, "- **{}:** {}", heading_label(label), value)?;
+    }
+    Ok(())
+}
+
+/// Turns a label like "preferred matchups:" into one that stands on its own,
+/// like "Preferred matchups"
+pub fn heading_label(label: &str) -> String {
+    let mut label = label.trim_end_matches(':').to_string();
+    if let Some(first) = label.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    label
+}
+
+/// Keeps a name from breaking out of its table cell or being read as
+/// formatting
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\|*_`[]<>#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Writes the text output of something without a table of rooms, without
+/// colors, in a Markdown code block or a standalone HTML page
+pub fn write_plain(
+    format: Format,
+    writer: &mut dyn Write,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    match format {
+        Format::Markdown => {
+            writeln!(writer, "```")?;
+            log::without_color(|| write(writer))?;
+            writeln!(writer, "```")?;
+            Ok(())
+        }
+        Format::Html => {
+            let mut text = vec![];
+            log::without_color(|| write(&mut text))?;
+            html::write_preformatted(String::from_utf8(text)?, writer)
+        }
+        _ => write(writer),
+    }
+}
+
+/// The name of a kind of matchup, colored by how good it is
+pub fn label(category: Category) -> ColoredString {
+    match category {
+        Category::StronglyPreferred | Category::Preferred => category.repr().green(),
+        Category::Acceptable | Category::Accepted => category.repr().yellow(),
+        Category::Unpreferred => category.repr().red(),
+    }
+}
+
+/// Lists who is in a room, after the room's name if it has one
+pub fn describe_room(solution: &Solution, index: usize) -> String {
+    let people = solution.rooms[index]
+        .iter()
+        .map(|x| x.blue().to_string())
+        .collect::<Vec<_>>()
+        .join(" & ");
+    match solution.names.get(index) {
+        Some(name) => format!("{}: {}", name.bold(), people),
+        None => people,
+    }
+}
+
+fn write_json<T: serde::Serialize + ?Sized, W: Write>(value: &T, mut writer: W) -> Result<()> {
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Writes one row per room. If `numbered` names a column, each row starts
+/// with the number of the solution it is from, and then its date if there
+/// are dates
+fn write_csv<W: Write>(
+    solutions: &[&Solution],
+    config: &Config,
+    numbered: Option<&str>,
+    dates: Option<&[String]>,
+    writer: W,
+) -> Result<()> {
+    // Rooms that have space left over get empty cells, so every row has a
+    // column for each person in the largest room
+    let size = solutions
+        .iter()
+        .flat_map(|x| &x.rooms)
+        .map(Vec::len)
+        .max()
+        .unwrap_or(2);
+    let named = solutions.iter().any(|x| !x.names.is_empty());
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut header = vec![numbered.unwrap_or_default().to_string()];
+    if dates.is_some() {
+        header.push("date".to_string());
+    }
+    if named {
+        header.push("room".to_string());
+    }
+    header.extend((b'a'..).take(size).map(|x| format!("person_{}", x as char)));
+    header.push("category".to_string());
+    if config.weighted() {
+        header.push("weight".to_string());
+    }
+    if numbered.is_some() {
+        writer.write_record(&header)?;
+    } else {
+        writer.write_record(&header[1..])?;
+    }
+    for (i, solution) in solutions.iter().enumerate() {
+        let number = (i + 1).to_string();
+        for (j, room) in solution.rooms.iter().enumerate() {
+            let weight = room_weight(room, config).to_string();
+            let mut record = vec![];
+            if numbered.is_some() {
+                record.push(number.as_str());
+            }
+            if let Some(dates) = dates {
+                record.push(dates[i].as_str());
+            }
+            if named {
+                record.push(solution.names.get(j).map_or("", String::as_str));
+            }
+            record.extend(room.iter().map(String::as_str));
+            record.resize(record.len() + size - room.len(), "");
+            record.push(classify_room(room, config).repr());
+            if config.weighted() {
+                record.push(&weight);
+            }
+            writer.write_record(record)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Irving's stable roommates algorithm. A matching is stable if no two people
+//! would both rather share a room with each other than with who they got.
+//! Preference lists can leave people out, as long as they do so both ways
+
+/// Everyone's preference lists, as they get cut down
+struct Lists {
+    /// Each person's preference list, best first
+    preferences: Vec<Vec<usize>>,
+    /// Where each person ranks everyone else, or `usize::MAX` if they
+    /// don't list them
+    rank: Vec<Vec<usize>>,
+    /// Whether each pair has been removed from each other's lists
+    removed: Vec<Vec<bool>>,
+}
+
+impl Lists {
+    fn new(preferences: &[Vec<usize>]) -> Self {
+        let people = preferences.len();
+        let mut rank = vec![vec![usize::MAX; people]; people];
+        for (a, list) in preferences.iter().enumerate() {
+            for (i, &b) in list.iter().enumerate() {
+                rank[a][b] = i;
+            }
+        }
+        Self {
+            preferences: preferences.to_vec(),
+            rank,
+            removed: vec![vec![false; people]; people],
+        }
+    }
+
+    fn remaining(&self, a: usize) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.preferences[a]
+            .iter()
+            .copied()
+            .filter(move |&b| !self.removed[a][b])
+    }
+
+    fn first(&self, a: usize) -> Option<usize> {
+        self.remaining(a).next()
+    }
+
+    fn second(&self, a: usize) -> Option<usize> {
+        self.remaining(a).nth(1)
+    }
+
+    fn last(&self, a: usize) -> Option<usize> {
+        self.remaining(a).next_back()
+    }
+
+    fn remove(&mut self, a: usize, b: usize) {
+        self.removed[a][b] = true;
+        self.removed[b][a] = true;
+    }
+
+    /// Removes everyone `a` likes less than `b` from `a`'s list
+    fn remove_after(&mut self, a: usize, b: usize) {
+        let worse = self
+            .remaining(a)
+            .filter(|&x| self.rank[a][x] > self.rank[a][b])
+            .collect::<Vec<_>>();
+        for x in worse {
+            self.remove(a, x);
+        }
+    }
+}
+
+/// Finds a stable matching given everyone's preference lists, best first.
+/// Returns each person's roommate, or `None` for people left on their own.
+/// Returns `None` overall if no stable matching exists
+pub fn stable_roommates(preferences: &[Vec<usize>]) -> Option<Vec<Option<usize>>> {
+    let people = preferences.len();
+    let mut lists = Lists::new(preferences);
+
+    // Phase 1: everyone proposes down their list, and each person holds on to
+    // the best proposal they have had, cutting everyone worse from their list
+    let mut holding = vec![None::<usize>; people];
+    let mut free = (0..people).rev().collect::<Vec<_>>();
+    while let Some(a) = free.pop() {
+        while let Some(b) = lists.first(a) {
+            let better = holding[b].is_none_or(|x| lists.rank[b][a] < lists.rank[b][x]);
+            if better {
+                if let Some(previous) = holding[b].replace(a) {
+                    free.push(previous);
+                }
+                lists.remove_after(b, a);
+                break;
+            }
+            lists.remove(a, b);
+        }
+    }
+
+    // People whose list ran out in phase 1 are on their own in every stable
+    // matching, but anyone else's list running out means there is none
+    let matched = (0..people)
+        .filter(|&x| lists.first(x).is_some())
+        .collect::<Vec<_>>();
+
+    // Phase 2: while someone still has a choice, find and eliminate a
+    // rotation, a cycle of people each moving on to the second person left on
+    // their list
+    while let Some(start) = (0..people).find(|&x| lists.second(x).is_some()) {
+        let mut sequence = vec![start];
+        let cycle = loop {
+            let second = lists.second(*sequence.last().unwrap())?;
+            let next = lists.last(second)?;
+            if let Some(i) = sequence.iter().position(|&x| x == next) {
+                break sequence.split_off(i);
+            }
+            sequence.push(next);
+        };
+        let seconds = cycle
+            .iter()
+            .map(|&x| lists.second(x))
+            .collect::<Option<Vec<_>>>()?;
+        for (&x, &second) in cycle.iter().zip(&seconds) {
+            lists.remove_after(second, x);
+        }
+        if matched.iter().any(|&x| lists.first(x).is_none()) {
+            return None;
+        }
+    }
+
+    let mates = (0..people).map(|x| lists.first(x)).collect::<Vec<_>>();
+    let consistent = mates
+        .iter()
+        .enumerate()
+        .all(|(a, b)| b.is_none_or(|b| mates[b] == Some(a)));
+    consistent.then_some(mates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    /// Whether `a` would rather share a room with `b` than with who they got
+    fn prefers(preferences: &[Vec<usize>], mates: &[Option<usize>], a: usize, b: usize) -> bool {
+        let rank = |x| preferences[a].iter().position(|&y| y == x);
+        match (rank(b), mates[a].and_then(rank)) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(x), Some(y)) => x < y,
+        }
+    }
+
+    #[test]
+    fn leaves_no_blocking_pairs() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let mut stable = 0;
+        for people in [2, 4, 6, 8] {
+            for _ in 0..50 {
+                let preferences = (0..people)
+                    .map(|a| {
+                        let mut list = (0..people).filter(|&b| b != a).collect::<Vec<_>>();
+                        list.shuffle(&mut rng);
+                        list
+                    })
+                    .collect::<Vec<_>>();
+                let Some(mates) = stable_roommates(&preferences) else {
+                    continue;
+                };
+                stable += 1;
+                for a in 0..people {
+                    assert!(mates[a].is_some());
+                    for b in a + 1..people {
+                        assert!(
+                            !prefers(&preferences, &mates, a, b)
+                                || !prefers(&preferences, &mates, b, a),
+                            "{} and {} would rather share a room in {:?}",
+                            a,
+                            b,
+                            preferences
+                        );
+                    }
+                }
+            }
+        }
+        assert!(stable > 0);
+    }
+
+    #[test]
+    fn finds_no_stable_matching_when_there_is_none() {
+        // Whoever shares a room with 3 is always wanted more by someone else
+        let preferences = vec![vec![1, 2, 3], vec![2, 0, 3], vec![0, 1, 3], vec![0, 1, 2]];
+        assert_eq!(stable_roommates(&preferences), None);
+    }
+}
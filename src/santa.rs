@@ -0,0 +1,164 @@
+use anyhow::Result;
+use colored::Colorize;
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+    calendar,
+    cli::Format,
+    config::Config,
+    matching::max_weight_matching,
+    output::write_plain,
+    score::{classify_directed, Category},
+    solver::pack_keys,
+};
+
+/// One person giving a gift to another
+#[derive(Serialize, Debug, Clone)]
+pub struct Gift {
+    pub giver: String,
+    pub receiver: String,
+    /// What the giver thinks of the receiver
+    pub category: Category,
+}
+
+/// Gives everyone exactly one other person to give a gift to, so that
+/// everyone also receives exactly one. Nobody gives to someone they can't
+/// share a room with under [never_pair] or [sides], and otherwise as few
+/// people as possible give to someone they listed as unpreferred. Then givers
+/// are matched with who they would most like to give to, with as many
+/// strongly preferred gifts as possible, then preferred and then acceptable
+/// ones, and finally by weight. Ties are broken at random. Returns `None` if
+/// there is no way to assign everyone
+pub fn assign(config: &Config, rng: &mut impl Rng) -> Result<Option<Vec<Gift>>> {
+    let mut people = config.people();
+    people.shuffle(rng);
+    let count = people.len();
+    if count < 2 {
+        return Ok(None);
+    }
+
+    // Givers are vertices 0 to count and receivers come after them, so that
+    // a perfect matching is a way of assigning everyone
+    let mut keys = vec![];
+    for (i, a) in people.iter().enumerate() {
+        for (j, b) in people.iter().enumerate() {
+            if i == j || config.forbids(a, b) {
+                continue;
+            }
+            let category = classify_directed(a, b, config);
+            let key = [
+                -((category == Category::Unpreferred) as i64),
+                (category == Category::StronglyPreferred) as i64,
+                (category == Category::Preferred) as i64,
+                (category == Category::Acceptable) as i64,
+                config.weight(a, b) as i64,
+            ];
+            keys.push((i, count + j, key));
+        }
+    }
+    let edges = pack_keys(&keys, count as i128);
+    let mates = max_weight_matching(2 * count, &edges, true);
+
+    let mut gifts = vec![];
+    for (i, giver) in people.iter().enumerate() {
+        let Some(j) = mates[i] else {
+            return Ok(None);
+        };
+        let receiver = &people[j - count];
+        gifts.push(Gift {
+            giver: giver.clone(),
+            receiver: receiver.clone(),
+            category: classify_directed(giver, receiver, config),
+        });
+    }
+    gifts.sort_by(|a, b| a.giver.cmp(&b.giver));
+    Ok(Some(gifts))
+}
+
+/// Prints who gives to whom, in the given format
+pub fn write_assignment<W: Write>(gifts: &[Gift], format: Format, mut writer: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            writeln!(writer, "{}", "SANTA".green().bold())?;
+            for category in [
+                Category::StronglyPreferred,
+                Category::Preferred,
+                Category::Acceptable,
+                Category::Accepted,
+                Category::Unpreferred,
+            ] {
+                let count = gifts.iter().filter(|x| x.category == category).count();
+                if count > 0 {
+                    writeln!(
+                        writer,
+                        " {:<22}{}",
+                        format!("{} gifts:", category.repr()),
+                        count.to_string().blue()
+                    )?;
+                }
+            }
+            for gift in gifts {
+                writeln!(
+                    writer,
+                    " {} gives to {}",
+                    gift.giver.blue(),
+                    gift.receiver.blue()
+                )?;
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_assignment(gifts, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, gifts)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record(["giver", "receiver", "category"])?;
+            for gift in gifts {
+                writer.write_record([&gift.giver, &gift.receiver, gift.category.repr()])?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_toml;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn avoids_unpreferred_gifts_before_giving_preferred_ones() {
+        let config = parse_toml(
+            r#"
+            [settings]
+            solutions = 100
+
+            [preferred]
+            a = ["b"]
+            b = []
+            c = []
+
+            [unpreferred]
+            b = ["c"]
+            "#,
+        )
+        .unwrap();
+        for seed in 0..10 {
+            let gifts = assign(&config, &mut StdRng::seed_from_u64(seed))
+                .unwrap()
+                .unwrap();
+            assert!(gifts.iter().all(|x| x.category != Category::Unpreferred));
+            assert!(gifts.iter().all(|x| x.giver != x.receiver));
+        }
+    }
+}
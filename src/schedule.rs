@@ -0,0 +1,188 @@
+use anyhow::{bail, Result};
+use rand::{seq::SliceRandom, Rng};
+use std::{cmp, collections::HashMap};
+
+use crate::{
+    config::{Config, COUNTS},
+    interrupt::interrupted,
+    score::{pairs, Solution},
+    solver::{generate_solutions, pairing_solution},
+};
+
+/// Every pair of people who have already shared a room, in name order, with
+/// the latest round they shared one in. Pairs from [must_pair] share one
+/// every round, so they are left out
+type Met = HashMap<(String, String), usize>;
+
+fn meet(met: &mut Met, round: usize, solution: &Solution, config: &Config) {
+    for (a, b) in solution.rooms.iter().flat_map(|x| pairs(x)) {
+        if config.partner(a) != Some(b.as_str()) {
+            met.insert(pair(a, b), round);
+        }
+    }
+}
+
+/// A copy of the config where the pairs met since the given round can't
+/// share a room
+fn avoiding(config: &Config, met: &Met, since: usize) -> Config {
+    let mut avoiding = config.clone();
+    for ((a, b), _) in met.iter().filter(|x| *x.1 >= since) {
+        avoiding
+            .never_pair
+            .entry(a.clone())
+            .or_default()
+            .push(b.clone());
+    }
+    avoiding
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    let (a, b) = if a < b { (a, b) } else { (b, a) };
+    (a.to_string(), b.to_string())
+}
+
+/// How many pairs in a solution have already shared a room
+fn repeats(solution: &Solution, met: &Met) -> usize {
+    solution
+        .rooms
+        .iter()
+        .flat_map(|x| pairs(x))
+        .filter(|(a, b)| met.contains_key(&pair(a, b)))
+        .count()
+}
+
+/// Solves the config once for each round, keeping apart anyone who already
+/// shared a room in an earlier round. Each round is first solved with those
+/// pairs added to [never_pair], and if that leaves no solution, solved with
+/// only the pairs from the latest `settings.repeat_window` rounds added, or
+/// as usual if there's no window. Either way the solution with the fewest
+/// repeated pairs is picked, and then the best one by `settings.objective`.
+/// Stops early if the search is interrupted
+pub fn schedule(config: &Config, rounds: usize, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    let mut met = Met::new();
+    let mut schedule = vec![];
+    for round in 0..rounds {
+        if interrupted() {
+            break;
+        }
+        let solutions = match generate_solutions(&avoiding(config, &met, 0), rng) {
+            Ok(solutions) if !solutions.is_empty() => solutions,
+            _ => match config.settings.repeat_window {
+                Some(window) => {
+                    let solutions = generate_solutions(
+                        &avoiding(config, &met, round.saturating_sub(window)),
+                        rng,
+                    )?;
+                    if solutions.is_empty() && !interrupted() {
+                        bail!(
+                            "Couldn't schedule round {} without repeating a pair from the \
+                             latest {} rounds, so try a smaller settings.repeat_window",
+                            round + 1,
+                            window
+                        );
+                    }
+                    solutions
+                }
+                None => generate_solutions(config, rng)?,
+            },
+        };
+
+        let key = |x: &Solution| (cmp::Reverse(repeats(x, &met)), x.rank_key(config));
+        let Some(best) = solutions.iter().map(key).max() else {
+            break;
+        };
+        let chosen = solutions
+            .iter()
+            .filter(|x| key(x) == best)
+            .collect::<Vec<_>>()
+            .choose(rng)
+            .copied()
+            .cloned()
+            .unwrap();
+        meet(&mut met, round, &chosen, config);
+        schedule.push(chosen);
+    }
+    Ok(schedule)
+}
+
+/// How many pairs share a room again after an earlier round, apart from
+/// pairs from [must_pair]
+pub fn total_repeats(schedule: &[Solution], config: &Config) -> usize {
+    let mut met = Met::new();
+    let mut total = 0;
+    for (i, round) in schedule.iter().enumerate() {
+        total += repeats(round, &met);
+        meet(&mut met, i, round, config);
+    }
+    total
+}
+
+/// Schedules every pair of people to share a room exactly once, over one
+/// round fewer than there are people, or one round per person when there's
+/// an odd number and someone has to sit each round out on their own. Uses
+/// the circle method on each of `settings.restarts` random orders of
+/// everyone, sorts each schedule's rounds best first by `settings.objective`
+/// and keeps the schedule whose rounds are best earliest
+pub fn round_robin(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    if !config.pair_rooms() {
+        bail!("A round robin only works when every room is for two people");
+    }
+    if config.has_forbidden() || !config.must_pair.is_empty() {
+        bail!(
+            "A round robin pairs everyone with everyone, so it can't keep to \
+             [never_pair], [sides] or [must_pair]"
+        );
+    }
+    let mut people = config.people().into_iter().map(Some).collect::<Vec<_>>();
+    if people.len() % 2 == 1 {
+        people.push(None);
+    }
+
+    let mut best: Option<(Vec<[i64; COUNTS]>, Vec<Solution>)> = None;
+    for _ in 0..config.settings.restarts.max(1) {
+        if interrupted() {
+            break;
+        }
+        people.shuffle(rng);
+        let mut rounds = circle(&people)
+            .into_iter()
+            .map(|x| pairing_solution(x, config))
+            .collect::<Result<Vec<_>>>()?;
+        for round in &mut rounds {
+            round.sort();
+        }
+        rounds.sort_by_key(|x| cmp::Reverse(x.rank_key(config)));
+        let keys = rounds
+            .iter()
+            .map(|x| x.rank_key(config))
+            .collect::<Vec<_>>();
+        if best.as_ref().is_none_or(|x| keys > x.0) {
+            best = Some((keys, rounds));
+        }
+    }
+    Ok(best.map(|x| x.1).unwrap_or_default())
+}
+
+/// Pairs everyone once with everyone else by keeping the first person in
+/// place and rotating the rest one step each round. `None` stands for an
+/// empty bed, and whoever is paired with it has a room to themselves
+fn circle(people: &[Option<String>]) -> Vec<Vec<Vec<String>>> {
+    let count = people.len();
+    let mut order = people.to_vec();
+    let mut rounds = vec![];
+    for _ in 1..count {
+        let round = (0..count / 2)
+            .map(|i| {
+                [&order[i], &order[count - 1 - i]]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|x| !x.is_empty())
+            .collect();
+        rounds.push(round);
+        order[1..].rotate_right(1);
+    }
+    rounds
+}
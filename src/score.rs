@@ -0,0 +1,385 @@
+use crate::{
+    config::{Config, PreferenceMode, COUNTS},
+    matching::max_weight_matching,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp, mem};
+
+/// The kinds of matchup a pair can be, from best to worst. Serialized the
+/// same way as `Category::repr` names them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    #[serde(rename = "strongly preferred")]
+    StronglyPreferred,
+    Preferred,
+    Acceptable,
+    Accepted,
+    Unpreferred,
+}
+
+impl Category {
+    pub const fn repr(&self) -> &'static str {
+        match self {
+            Self::StronglyPreferred => "strongly preferred",
+            Self::Preferred => "preferred",
+            Self::Acceptable => "acceptable",
+            Self::Accepted => "accepted",
+            Self::Unpreferred => "unpreferred",
+        }
+    }
+}
+
+fn from_tier(tier: u8, unpreferred: bool) -> Category {
+    match tier {
+        3 => Category::StronglyPreferred,
+        2 => Category::Preferred,
+        1 => Category::Acceptable,
+        _ if unpreferred => Category::Unpreferred,
+        _ => Category::Accepted,
+    }
+}
+
+/// Works out which kind of matchup a pair of people would be. In the default
+/// mutual mode, a pair is only as good as the lower of the tiers they listed
+/// each other in, otherwise it is as good as the higher one
+pub fn classify(a: &str, b: &str, config: &Config) -> Category {
+    let tiers = (config.tier(a, b), config.tier(b, a));
+    let tier = match config.settings.preference_mode {
+        PreferenceMode::Mutual => tiers.0.min(tiers.1),
+        PreferenceMode::Either | PreferenceMode::Directed => tiers.0.max(tiers.1),
+    };
+    from_tier(tier, config.unprefers(a, b) || config.unprefers(b, a))
+}
+
+/// How `a` feels about sharing a room with `b`, ignoring what `b` thinks
+pub fn classify_directed(a: &str, b: &str, config: &Config) -> Category {
+    from_tier(config.tier(a, b), config.unprefers(a, b))
+}
+
+/// Every pair of people sharing a room
+pub fn pairs(room: &[String]) -> impl Iterator<Item = (&String, &String)> {
+    room.iter()
+        .enumerate()
+        .flat_map(move |(i, a)| room[i + 1..].iter().map(move |b| (a, b)))
+}
+
+/// A room is only as good as the worst matchup in it
+pub fn classify_room(room: &[String], config: &Config) -> Category {
+    pairs(room)
+        .map(|(a, b)| classify(a, b, config))
+        .max()
+        .unwrap_or(Category::Accepted)
+}
+
+/// How one person did in a solution
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Outcome {
+    pub name: String,
+    /// Unpreferred if they share a room with anyone they listed as
+    /// unpreferred, and otherwise the best of how they listed their
+    /// roommates. People on their own count as accepted
+    pub category: Category,
+}
+
+/// How everyone did in a solution, judged by their own lists alone, worst
+/// off first
+pub fn outcomes(solution: &Solution, config: &Config) -> Vec<Outcome> {
+    let mut outcomes = vec![];
+    for room in &solution.rooms {
+        for person in room {
+            let feelings = room
+                .iter()
+                .filter(|x| *x != person)
+                .map(|x| classify_directed(person, x, config))
+                .collect::<Vec<_>>();
+            let category = if feelings.contains(&Category::Unpreferred) {
+                Category::Unpreferred
+            } else {
+                feelings.into_iter().min().unwrap_or(Category::Accepted)
+            };
+            outcomes.push(Outcome {
+                name: person.clone(),
+                category,
+            });
+        }
+    }
+    outcomes.sort_by(|a, b| b.category.cmp(&a.category).then(a.name.cmp(&b.name)));
+    outcomes
+}
+
+/// How many pairs in a solution are preferred or better
+pub fn preferred_pairs(solution: &Solution, config: &Config) -> usize {
+    solution
+        .rooms
+        .iter()
+        .flat_map(|x| pairs(x))
+        .filter(|(a, b)| classify(a, b, config) <= Category::Preferred)
+        .count()
+}
+
+/// The most pairs that could be preferred or better in any solution. When
+/// every room is for at most two people this is exact: the pairs from
+/// [must_pair] that are preferred, plus a maximum matching of everyone else
+/// with the people they'd be a preferred pair with. Otherwise it's an upper
+/// bound, from how many of those people each person could share a room with
+/// and how many pairs the rooms hold
+pub fn max_preferred_pairs(config: &Config) -> usize {
+    let people = config.people();
+    let mut paired = vec![false; people.len()];
+    let mut fixed = 0;
+    for (a, b) in config.must_pairs() {
+        for person in [&a, &b] {
+            if let Some(i) = people.iter().position(|x| x == person) {
+                paired[i] = true;
+            }
+        }
+        if classify(&a, &b, config) <= Category::Preferred {
+            fixed += 1;
+        }
+    }
+    let mut edges = vec![];
+    let mut degrees = vec![0; people.len()];
+    for (i, a) in people.iter().enumerate() {
+        for (j, b) in people.iter().enumerate().skip(i + 1) {
+            if classify(a, b, config) <= Category::Preferred && !config.forbids(a, b) {
+                edges.push((i, j, 1));
+                degrees[i] += 1;
+                degrees[j] += 1;
+            }
+        }
+    }
+    let capacities = config.capacities();
+    let slots = capacities
+        .iter()
+        .map(|x| x.1 * x.1.saturating_sub(1) / 2)
+        .sum::<usize>();
+    let largest = capacities.iter().map(|x| x.1).max().unwrap_or(0);
+    let bound = if largest <= 2 {
+        // Everyone in [must_pair] already has their roommate
+        edges.retain(|&(i, j, _)| !paired[i] && !paired[j]);
+        let mates = max_weight_matching(people.len(), &edges, false);
+        fixed + mates.iter().flatten().count() / 2
+    } else {
+        degrees
+            .iter()
+            .map(|&x| cmp::min(x, largest - 1))
+            .sum::<usize>()
+            / 2
+    };
+    bound.min(slots)
+}
+
+/// The total weight of the preferred matchups in a room, which is what the
+/// room adds to `Solution::weight`
+pub fn room_weight(room: &[String], config: &Config) -> u64 {
+    Solution::new(vec![room.to_vec()], vec![], config).weight
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Solution {
+    pub rooms: Vec<Vec<String>>,
+    /// The name of each room from [rooms], in the same order as `rooms`.
+    /// Empty if the config doesn't name its rooms
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub strongly_preferred: u64,
+    pub preferred: u64,
+    #[serde(default)]
+    pub acceptable: u64,
+    pub accepted: u64,
+    pub unpreferred: u64,
+    /// Total weight of the preferred matchups, see `Config::pair_weight`
+    #[serde(default)]
+    pub weight: u64,
+    /// How each person feels about their least wanted roommate, for the
+    /// worst-off person. People on their own count as accepted
+    #[serde(skip)]
+    pub worst_off: Option<Category>,
+    /// How many people feel each way about their least wanted roommate, as
+    /// digits in base `UNFAIRNESS_BASE` from the happiest at the bottom to the
+    /// unhappiest at the top. Comparing these compares the worst-off people
+    /// first, and then how many people are that unhappy, and can still be
+    /// added up room by room
+    #[serde(skip)]
+    pub unfairness: u64,
+}
+
+/// A solution along with the run of `picker solve` that chose it, as written
+/// with `--format json`. The solution's fields sit alongside the run's, so
+/// anything that reads a solution can read this too
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunResult {
+    /// The seed the run started from, which gives the same solution again
+    /// when passed to `--seed` with the same config
+    pub seed: u64,
+    /// A hash of the config file's contents, see `store::hash_config`.
+    /// Missing when the config was read from stdin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// When the run started, in RFC 3339
+    pub started: String,
+    /// When the run finished, in RFC 3339
+    pub finished: String,
+    /// How each person did, if it was asked for with `--people`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub people: Option<Vec<Outcome>>,
+    #[serde(flatten)]
+    pub solution: Solution,
+}
+
+/// Big enough for a digit of `Solution::unfairness` to count everyone in any
+/// realistic roster, while five of them still fit in a u64
+pub const UNFAIRNESS_BASE: u64 = 4096;
+
+impl Solution {
+    /// Scores a set of rooms against the config, counting every pair of
+    /// people that share a room as a matchup
+    pub fn new(rooms: Vec<Vec<String>>, names: Vec<String>, config: &Config) -> Self {
+        let mut solution = Self {
+            rooms: vec![],
+            names,
+            strongly_preferred: 0,
+            preferred: 0,
+            acceptable: 0,
+            accepted: 0,
+            unpreferred: 0,
+            weight: 0,
+            worst_off: None,
+            unfairness: 0,
+        };
+        // Directed mode scores each person's view of each roommate, instead
+        // of each pair as a whole
+        let mut matchups = vec![];
+        for (a, b) in rooms.iter().flat_map(|x| pairs(x)) {
+            if config.settings.preference_mode == PreferenceMode::Directed {
+                matchups.push((classify_directed(a, b, config), config.weight(a, b)));
+                matchups.push((classify_directed(b, a, config), config.weight(b, a)));
+            } else {
+                matchups.push((classify(a, b, config), config.pair_weight(a, b)));
+            }
+        }
+        for (category, weight) in matchups {
+            match category {
+                Category::StronglyPreferred => solution.strongly_preferred += 1,
+                Category::Preferred => solution.preferred += 1,
+                Category::Acceptable => solution.acceptable += 1,
+                Category::Accepted => solution.accepted += 1,
+                Category::Unpreferred => solution.unpreferred += 1,
+            }
+            if category <= Category::Preferred {
+                solution.weight += weight;
+            }
+        }
+        for room in &rooms {
+            for a in room {
+                let feeling = room
+                    .iter()
+                    .filter(|&b| b != a)
+                    .map(|b| classify_directed(a, b, config))
+                    .max()
+                    .unwrap_or(Category::Accepted);
+                solution.worst_off = solution.worst_off.max(Some(feeling));
+                solution.unfairness += UNFAIRNESS_BASE.pow(feeling as u32);
+            }
+        }
+        solution.rooms = rooms;
+        solution
+    }
+
+    /// The solution's counts, in the order of `Count`
+    pub fn counts(&self) -> [i64; COUNTS] {
+        [
+            self.unpreferred,
+            self.strongly_preferred,
+            self.weight,
+            self.preferred,
+            self.acceptable,
+            self.accepted,
+            self.unfairness,
+        ]
+        .map(|x| x as i64)
+    }
+
+    /// The key solutions are compared by under `settings.objective`, where a
+    /// larger key is a better solution. By default that is fewest unpreferred
+    /// matchups first, then the most strongly preferred matchups, the highest
+    /// preferred weight, the most preferred matchups and finally the most
+    /// acceptable ones
+    pub fn rank_key(&self, config: &Config) -> [i64; COUNTS] {
+        config.settings.objective.key(self.counts())
+    }
+
+    /// Puts the solution in a set order, so that the same rooms always look
+    /// the same however they were found: everyone in a room by name, and
+    /// then named rooms in the config's order and unnamed ones by their
+    /// first member
+    pub fn sort(&mut self) {
+        for room in &mut self.rooms {
+            room.sort();
+        }
+        if self.names.is_empty() {
+            self.rooms.sort();
+        } else if self.names.len() == self.rooms.len() {
+            let mut rooms = mem::take(&mut self.names)
+                .into_iter()
+                .zip(mem::take(&mut self.rooms))
+                .collect::<Vec<_>>();
+            rooms.sort();
+            (self.names, self.rooms) = rooms.into_iter().unzip();
+        }
+    }
+
+    /// What makes two solutions the same, whatever order their rooms and
+    /// roommates are in. A named room counts as different from the same
+    /// people in another room
+    pub fn identity(&self) -> Vec<(Option<&String>, Vec<&String>)> {
+        let mut rooms = self
+            .rooms
+            .iter()
+            .enumerate()
+            .map(|(i, room)| {
+                let mut people = room.iter().collect::<Vec<_>>();
+                people.sort();
+                (self.names.get(i), people)
+            })
+            .collect::<Vec<_>>();
+        rooms.sort();
+        rooms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_toml;
+
+    #[test]
+    fn must_pairs_limit_the_preferred_pairs_possible() {
+        let config = |must_pair: &str| {
+            parse_toml(&format!(
+                r#"
+                people = ["a", "b", "c", "d"]
+
+                [settings]
+                solutions = 1
+
+                [preferred]
+                a = ["b"]
+                b = ["a"]
+                c = ["d"]
+                d = ["c"]
+
+                [must_pair]
+                {}
+                "#,
+                must_pair
+            ))
+            .unwrap()
+        };
+        assert_eq!(max_preferred_pairs(&config("")), 2);
+        assert_eq!(max_preferred_pairs(&config("a = \"b\"")), 2);
+        assert_eq!(max_preferred_pairs(&config("a = \"c\"")), 0);
+    }
+}
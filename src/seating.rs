@@ -0,0 +1,180 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+    calendar,
+    cli::Format,
+    config::{Config, Layout, COUNTS},
+    interrupt::interrupted,
+    output::write_plain,
+    score::Solution,
+};
+
+/// Everyone's seat in a grid, with `None` for empty seats
+#[derive(Serialize, Debug, Clone)]
+pub struct Seating {
+    pub rows: Vec<Vec<Option<String>>>,
+    /// The neighbouring pairs, scored as though each shared a room
+    #[serde(skip)]
+    pub neighbours: Solution,
+}
+
+impl Seating {
+    fn new(seats: &[Option<String>], layout: Layout, config: &Config) -> Self {
+        let rows = seats.chunks(layout.cols).map(<[_]>::to_vec).collect();
+        let neighbours = Solution::new(neighbours(seats, layout), vec![], config);
+        Self { rows, neighbours }
+    }
+}
+
+/// Every pair of people sitting next to each other, across a row or down a
+/// column
+fn neighbours(seats: &[Option<String>], layout: Layout) -> Vec<Vec<String>> {
+    let mut pairs = vec![];
+    for (i, seat) in seats.iter().enumerate() {
+        let Some(a) = seat else {
+            continue;
+        };
+        let right = (i % layout.cols + 1 < layout.cols).then_some(i + 1);
+        let below = Some(i + layout.cols).filter(|&x| x < seats.len());
+        for j in right.into_iter().chain(below) {
+            if let Some(b) = &seats[j] {
+                pairs.push(vec![a.clone(), b.clone()]);
+            }
+        }
+    }
+    pairs
+}
+
+/// How good a seating is, with fewer forbidden neighbours first and then
+/// `settings.objective` over the neighbouring pairs
+fn key(seats: &[Option<String>], layout: Layout, config: &Config) -> (i64, [i64; COUNTS]) {
+    let pairs = neighbours(seats, layout);
+    let forbidden = pairs
+        .iter()
+        .filter(|x| config.forbids(&x[0], &x[1]))
+        .count();
+    let key = Solution::new(pairs, vec![], config).rank_key(config);
+    (-(forbidden as i64), key)
+}
+
+/// Seats everyone in the grid from [layout], so that people sit next to who
+/// they prefer and away from who they don't, scoring each pair of neighbours
+/// the way a shared room would be. Each of `settings.restarts` random
+/// seatings is improved by swapping seats until no swap helps, and the best
+/// is kept. Returns `None` if every seating found puts a [never_pair] pair
+/// next to each other
+pub fn seat(config: &Config, rng: &mut impl Rng) -> Result<Option<Seating>> {
+    let Some(layout) = config.layout else {
+        bail!("Seating people needs a [layout] with the number of rows and cols");
+    };
+    let people = config.people();
+    let count = layout.rows * layout.cols;
+    if people.len() > count {
+        bail!(
+            "There are {} people, but only {} seats in [layout]",
+            people.len(),
+            count
+        );
+    }
+
+    let mut best: Option<(_, Vec<_>)> = None;
+    for _ in 0..config.settings.restarts.max(1) {
+        if interrupted() {
+            break;
+        }
+        let mut seats = people.iter().cloned().map(Some).collect::<Vec<_>>();
+        seats.resize(count, None);
+        seats.shuffle(rng);
+        let mut current = key(&seats, layout, config);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..count {
+                for j in i + 1..count {
+                    if seats[i].is_none() && seats[j].is_none() {
+                        continue;
+                    }
+                    seats.swap(i, j);
+                    let swapped = key(&seats, layout, config);
+                    if swapped > current {
+                        current = swapped;
+                        improved = true;
+                    } else {
+                        seats.swap(i, j);
+                    }
+                }
+            }
+        }
+        if best.as_ref().is_none_or(|x| current > x.0) {
+            best = Some((current, seats));
+        }
+    }
+
+    Ok(best
+        .filter(|x| x.0 .0 == 0)
+        .map(|x| Seating::new(&x.1, layout, config)))
+}
+
+/// Prints the seating chart, in the given format
+pub fn write_seating<W: Write>(seating: &Seating, format: Format, mut writer: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            let neighbours = &seating.neighbours;
+            let lines = [
+                ("strongly preferred", neighbours.strongly_preferred),
+                ("preferred", neighbours.preferred),
+                ("acceptable", neighbours.acceptable),
+                ("accepted", neighbours.accepted),
+                ("unpreferred", neighbours.unpreferred),
+            ]
+            .into_iter()
+            .filter(|x| x.1 > 0 || x.0 == "preferred" || x.0 == "unpreferred")
+            .map(|x| (format!("{} neighbours:", x.0), x.1))
+            .collect::<Vec<_>>();
+            writeln!(writer, "{}", "SEATING".green().bold())?;
+            let width = lines.iter().map(|x| x.0.len()).max().unwrap_or_default() + 2;
+            for (label, count) in lines {
+                writeln!(writer, " {:<width$}{}", label, count.to_string().blue())?;
+            }
+            let width = seating
+                .rows
+                .iter()
+                .flatten()
+                .map(|x| x.as_ref().map_or(1, String::len))
+                .max()
+                .unwrap_or_default();
+            for row in &seating.rows {
+                let seats = row
+                    .iter()
+                    .map(|x| match x {
+                        Some(name) => format!("{:<width$}", name).blue().to_string(),
+                        None => format!("{:<width$}", "-"),
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(writer, " {}", seats.join("  "))?;
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_seating(seating, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, seating)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in &seating.rows {
+                writer.write_record(row.iter().map(|x| x.as_deref().unwrap_or("")))?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
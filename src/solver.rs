@@ -0,0 +1,1654 @@
+use std::{
+    cmp,
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+#[cfg(feature = "ilp")]
+use crate::ilp::solve_ilp;
+use crate::{
+    config::{Algorithm, Config, Count, Objective, OddPolicy, COUNTS},
+    interrupt::interrupted,
+    marriage::stable_marriage,
+    matching::max_weight_matching,
+    roommates::stable_roommates,
+    score::{classify, pairs, Solution, UNFAIRNESS_BASE},
+};
+use anyhow::{anyhow, bail, Result};
+use rand::{seq::SliceRandom, Rng};
+
+fn find_index<T: cmp::PartialEq>(item: &T, array: &[T]) -> Result<usize> {
+    array
+        .iter()
+        .position(|x| x == item)
+        .ok_or_else(|| anyhow!("Error choosing random item from list. Array empty."))
+}
+
+fn choose_person(
+    list: &[String],
+    index_list: &mut Vec<String>,
+    rng: &mut impl Rng,
+) -> Result<String> {
+    let person = list
+        .choose(rng)
+        .ok_or_else(|| anyhow!("Error choosing random person"))?;
+    let index = find_index(person, index_list)?;
+    Ok(index_list.remove(index))
+}
+
+/// The rooms started so far, and everyone still to be placed
+type Started = (Vec<Vec<String>>, Vec<String>);
+
+/// Starts a room for each pair that must share one. Returns the started rooms
+/// and everyone left to place, or `None` if one of the pairs is also forbidden
+/// from sharing a room
+fn start_must_pairs(config: &Config) -> Result<Option<Started>> {
+    let mut people = config.people();
+    let mut started = vec![];
+    for (a, b) in config.must_pairs() {
+        for person in [&a, &b] {
+            let index = people.iter().position(|x| x == person).ok_or_else(|| {
+                anyhow!(
+                    "'{}' is in [must_pair] more than once, or isn't in the people list",
+                    person
+                )
+            })?;
+            people.remove(index);
+        }
+        if config.forbids(&a, &b) {
+            return Ok(None);
+        }
+        started.push(vec![a, b]);
+    }
+    Ok(Some((started, people)))
+}
+
+/// Fails unless every room is for two people, see `Config::pair_rooms`,
+/// which the solvers that work with pairings rather than rooms need
+pub fn check_pair_rooms(config: &Config, solver: &str) -> Result<()> {
+    if !config.pair_rooms() {
+        bail!(
+            "The {} solver only works when every room is for two people",
+            solver
+        );
+    }
+    Ok(())
+}
+
+/// Turns a pairing into a solution, naming the rooms in order
+pub fn pairing_solution(rooms: Vec<Vec<String>>, config: &Config) -> Result<Solution> {
+    let capacities = config.capacities();
+    if rooms.len() > capacities.len() {
+        bail!("There aren't enough beds in [rooms] for everyone");
+    }
+    let names = capacities
+        .iter()
+        .take(rooms.len())
+        .filter_map(|x| x.0.map(String::from))
+        .collect();
+    Ok(Solution::new(rooms, names, config))
+}
+
+/// Builds one solution by going through everyone in a random order, filling
+/// each person's room with random roommates from the best kind of matchup
+/// left. A candidate is only as good as their worst matchup with someone
+/// already in the room. Rooms for pairs that must share one are started first,
+/// and named rooms are filled in a random order, leaving any spare beds in
+/// whichever come last. Returns `None` if a room was left with only forbidden
+/// candidates
+pub fn solve(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    let Some((started, mut people)) = start_must_pairs(config)? else {
+        return Ok(None);
+    };
+    people.shuffle(rng);
+
+    let mut capacities = config.capacities();
+    if !config.rooms.is_empty() {
+        capacities.shuffle(rng);
+    }
+
+    let mut started = started.into_iter();
+    let mut rooms = vec![];
+    let mut names = vec![];
+    for (name, capacity) in capacities {
+        let room = if capacity >= 2 { started.next() } else { None };
+        let Some(mut room) = room.or_else(|| people.pop().map(|x| vec![x])) else {
+            continue;
+        };
+        while room.len() < capacity && !people.is_empty() {
+            let fit = |x: &String| room.iter().map(|y| classify(y, x, config)).max();
+            let allowed = people
+                .iter()
+                .filter(|x| room.iter().all(|y| !config.forbids(y, x)))
+                .collect::<Vec<_>>();
+            let Some(best) = allowed.iter().map(|x| fit(x)).min() else {
+                return Ok(None);
+            };
+            let candidates = allowed
+                .into_iter()
+                .filter(|x| fit(x) == best)
+                .cloned()
+                .collect::<Vec<_>>();
+            room.push(choose_person(&candidates, &mut people, rng)?);
+        }
+        rooms.push(room);
+        names.extend(name.map(String::from));
+    }
+    if !people.is_empty() || started.next().is_some() {
+        bail!("There aren't enough beds in [rooms] for everyone");
+    }
+
+    Ok(Some(Solution::new(rooms, names, config)))
+}
+
+/// Every pair of people allowed to share a room, as indexes into `people`,
+/// weighted so that out of the pairings with as many pairs as possible, a
+/// heavier one is always a better solution by `Solution::rank_key`
+pub fn pair_weights(people: &[String], config: &Config) -> Vec<(usize, usize, i128)> {
+    let mut keys = vec![];
+    for (i, a) in people.iter().enumerate() {
+        for (j, b) in people.iter().enumerate().skip(i + 1) {
+            if !config.forbids(a, b) {
+                keys.push((i, j, rank_counts(&[vec![a.clone(), b.clone()]], config)));
+            }
+        }
+    }
+
+    pack_keys(&keys, people.len() as i128 / 2 + 1)
+}
+
+/// Packs a key for each edge into a single weight, so that of two matchings
+/// of the same size, the one whose keys add up to more, compared part by
+/// part in order, is always heavier. `pairs` is the most edges a matching
+/// can have
+pub fn pack_keys<const N: usize>(
+    keys: &[(usize, usize, [i64; N])],
+    pairs: i128,
+) -> Vec<(usize, usize, i128)> {
+    // How many edges a matching has bounds how far apart two matchings'
+    // totals can be for each part of the key. A base above that keeps each
+    // part from spilling into the next one
+    let bases = (0..N).map(|c| {
+        let largest = keys
+            .iter()
+            .map(|x| x.2[c].unsigned_abs())
+            .max()
+            .unwrap_or(0);
+        2 * pairs * largest as i128 + 1
+    });
+    let bases = bases.collect::<Vec<_>>();
+    let weights = keys
+        .iter()
+        .map(|(i, j, key)| {
+            let weight = key
+                .iter()
+                .zip(&bases)
+                .fold(0, |x, (y, base)| x * base + *y as i128);
+            (*i, *j, weight)
+        })
+        .collect::<Vec<_>>();
+
+    // Matchings are always as large as possible, so shifting every weight by
+    // the same amount changes nothing but keeps them from being negative
+    let lightest = weights.iter().map(|x| x.2).min().unwrap_or(0).min(0);
+    weights
+        .into_iter()
+        .map(|(i, j, x)| (i, j, x - lightest))
+        .collect()
+}
+
+/// Finds an optimal solution directly, as a maximum weight matching between
+/// everyone not already paired up by [must_pair], weighted by
+/// `pair_weights`. Returns `None` if [never_pair] leaves no way to fill every
+/// room
+pub fn solve_exact(config: &Config) -> Result<Option<Solution>> {
+    check_pair_rooms(config, "exact")?;
+    let Some((mut rooms, people)) = start_must_pairs(config)? else {
+        return Ok(None);
+    };
+    let edges = pair_weights(&people, config);
+    let mates = max_weight_matching(people.len(), &edges, true);
+    let mut paired = 0;
+    for (i, mate) in mates.iter().enumerate() {
+        match mate {
+            Some(j) if *j < i => continue,
+            Some(j) => {
+                rooms.push(vec![people[i].clone(), people[*j].clone()]);
+                paired += 1;
+            }
+            None => rooms.push(vec![people[i].clone()]),
+        }
+    }
+    if paired < people.len() / 2 {
+        return Ok(None);
+    }
+    pairing_solution(rooms, config).map(Some)
+}
+
+/// How `a` ranks everyone they could share a room with, best first: by the
+/// tier they listed them in, then by weight, with anyone they listed as
+/// unpreferred last. Ties are broken in the order `people` is in
+fn ranking(a: &str, people: &[String], config: &Config) -> Vec<usize> {
+    let mut ranking = (0..people.len())
+        .filter(|&b| people[b] != a && !config.forbids(a, &people[b]))
+        .collect::<Vec<_>>();
+    ranking.sort_by_key(|&b| {
+        let b = &people[b];
+        (
+            cmp::Reverse(config.tier(a, b)),
+            cmp::Reverse(config.weight(a, b)),
+            config.unprefers(a, b),
+        )
+    });
+    ranking
+}
+
+/// Finds a stable pairing with Irving's stable roommates algorithm, where no
+/// two people would both rather share a room with each other than with who
+/// they got. Ties in people's rankings are broken at random. Fails if no
+/// stable pairing exists
+pub fn solve_stable(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    check_pair_rooms(config, "stable")?;
+    let Some((mut rooms, mut people)) = start_must_pairs(config)? else {
+        return Ok(None);
+    };
+    people.shuffle(rng);
+
+    let rankings = people
+        .iter()
+        .map(|x| ranking(x, &people, config))
+        .collect::<Vec<_>>();
+    let Some(mates) = stable_roommates(&rankings) else {
+        bail!("No stable pairing exists with ties broken this way, as there is always a pair that would both rather swap. Try another seed, or --solver exact for the best pairing instead");
+    };
+    for (i, mate) in mates.iter().enumerate() {
+        match mate {
+            Some(j) if *j < i => continue,
+            Some(j) => rooms.push(vec![people[i].clone(), people[*j].clone()]),
+            None => rooms.push(vec![people[i].clone()]),
+        }
+    }
+    pairing_solution(rooms, config).map(Some)
+}
+
+/// Finds a stable pairing across the two sides in [sides] with the
+/// Gale–Shapley algorithm, where no two people on opposite sides would both
+/// rather share a room with each other than with who they got. The side in
+/// `settings.proposing_side` proposes, and gets the best stable pairing it
+/// can. Ties in people's rankings are broken at random
+pub fn solve_gale_shapley(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    check_pair_rooms(config, "gale-shapley")?;
+    if config.sides.len() != 2 {
+        bail!("The gale-shapley solver needs exactly two sides in [sides]");
+    }
+    let proposing = match &config.settings.proposing_side {
+        Some(side) if !config.sides.contains_key(side) => {
+            bail!(
+                "settings.proposing_side is '{}', which isn't a side in [sides]",
+                side
+            )
+        }
+        Some(side) => side.as_str(),
+        None => config.sides.keys().next().unwrap(),
+    };
+    let Some((mut rooms, people)) = start_must_pairs(config)? else {
+        return Ok(None);
+    };
+    if let Some(person) = people
+        .iter()
+        .find(|x| !config.sides.values().any(|side| side.contains(x)))
+    {
+        bail!(
+            "'{}' isn't on either side in [sides], which the gale-shapley solver needs",
+            person
+        );
+    }
+
+    let mut side = |proposes: bool| {
+        let mut members = people
+            .iter()
+            .filter(|x| config.sides[proposing].contains(x) == proposes)
+            .cloned()
+            .collect::<Vec<_>>();
+        members.shuffle(rng);
+        members
+    };
+    let proposers = side(true);
+    let receivers = side(false);
+    let mates = stable_marriage(
+        &proposers
+            .iter()
+            .map(|x| ranking(x, &receivers, config))
+            .collect::<Vec<_>>(),
+        &receivers
+            .iter()
+            .map(|x| ranking(x, &proposers, config))
+            .collect::<Vec<_>>(),
+    );
+
+    let mut matched = vec![false; receivers.len()];
+    for (a, mate) in proposers.iter().zip(&mates) {
+        match mate {
+            Some(b) => {
+                matched[*b] = true;
+                rooms.push(vec![a.clone(), receivers[*b].clone()]);
+            }
+            None => rooms.push(vec![a.clone()]),
+        }
+    }
+    for (b, _) in matched.iter().enumerate().filter(|x| !x.1) {
+        rooms.push(vec![receivers[b].clone()]);
+    }
+    pairing_solution(rooms, config).map(Some)
+}
+
+/// How many people fit in the `i`th room of a solution with the given room
+/// names. Unnamed rooms stay in the order of `Config::capacities`
+fn room_capacity(config: &Config, names: &[String], i: usize) -> usize {
+    names.get(i).map_or_else(
+        || config.capacities().get(i).map_or(0, |x| x.1),
+        |x| config.rooms[x].capacity,
+    )
+}
+
+/// Starts from a random solution and improves it by moving and swapping
+/// people between rooms. Worse moves are accepted with a chance that shrinks
+/// as the temperature cools from `settings.temperature`, so that the search
+/// can climb out of dead ends early on. How much worse a move is comes from
+/// the first part of `Solution::rank_key` it changes, so that a lot of a less
+/// important criterion never makes up for the more important ones. Pairs
+/// from [must_pair] stay put, and moves into a forbidden pair are never made.
+/// Returns the best solution seen
+pub fn solve_anneal(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    let temperature = config.settings.temperature;
+    if !temperature.is_finite() || temperature <= 0.0 {
+        bail!("settings.temperature must be above 0");
+    }
+    let mut start = None;
+    for _ in 0..100 {
+        start = solve(config, rng)?;
+        if start.is_some() {
+            break;
+        }
+    }
+    let Some(Solution {
+        mut rooms, names, ..
+    }) = start
+    else {
+        return Ok(None);
+    };
+
+    let capacity = |i: usize| room_capacity(config, &names, i);
+    let movable = |person: &String| config.partner(person).is_none();
+    let unfairness = config.settings.objective.worst_off_slot();
+    // Room keys add up, so only the two rooms a move changes need scoring
+    let mut keys = rooms
+        .iter()
+        .map(|x| rank_counts(std::slice::from_ref(x), config))
+        .collect::<Vec<_>>();
+    let mut total = keys.iter().fold([0; COUNTS], |x, y| add_counts(x, *y, 1));
+    let mut best = (total, rooms.clone());
+
+    // Cools down to a thousandth of the starting temperature by the end
+    let iterations = config.settings.iterations;
+    let cooling = 0.001f64.powf(1.0 / iterations.max(1) as f64);
+    let mut temperature = temperature;
+    for _ in 0..iterations {
+        if interrupted() {
+            break;
+        }
+        temperature *= cooling;
+        if rooms.len() < 2 {
+            break;
+        }
+        let a = rng.gen_range(0..rooms.len());
+        let b = (a + rng.gen_range(1..rooms.len())) % rooms.len();
+        let i = rng.gen_range(0..rooms[a].len());
+        if !movable(&rooms[a][i]) {
+            continue;
+        }
+
+        // Moves into a spare bed, or swaps with someone in the other room
+        let mut room_a = rooms[a].clone();
+        let mut room_b = rooms[b].clone();
+        if room_b.len() < capacity(b) && room_a.len() > 1 && rng.gen_bool(0.5) {
+            room_b.push(room_a.remove(i));
+        } else {
+            let j = rng.gen_range(0..room_b.len());
+            if !movable(&room_b[j]) {
+                continue;
+            }
+            std::mem::swap(&mut room_a[i], &mut room_b[j]);
+        }
+        let forbidden = [&room_a, &room_b]
+            .iter()
+            .any(|x| pairs(x).any(|(x, y)| config.forbids(x, y)));
+        if forbidden {
+            continue;
+        }
+
+        let key_a = rank_counts(std::slice::from_ref(&room_a), config);
+        let key_b = rank_counts(std::slice::from_ref(&room_b), config);
+        let delta = add_counts(
+            add_counts(key_a, key_b, 1),
+            add_counts(keys[a], keys[b], 1),
+            -1,
+        );
+        let first = delta.iter().position(|&x| x != 0);
+        let mut worse = first.map_or(0, |x| delta[x]);
+        // How unfair the rooms are is packed into digits, unhappiest first,
+        // so only the leading digit of the change says how much worse it is
+        if first.is_some() && first == unfairness {
+            while worse.unsigned_abs() >= UNFAIRNESS_BASE {
+                worse /= UNFAIRNESS_BASE as i64;
+            }
+        }
+        if worse < 0 && rng.gen::<f64>() >= (worse as f64 / temperature).exp() {
+            continue;
+        }
+        total = add_counts(total, delta, 1);
+        rooms[a] = room_a;
+        rooms[b] = room_b;
+        keys[a] = key_a;
+        keys[b] = key_b;
+        if total > best.0 {
+            best = (total, rooms.clone());
+        }
+    }
+    Ok(Some(Solution::new(best.1, names, config)))
+}
+
+/// How many solutions each genetic tournament picks between
+const TOURNAMENT: usize = 3;
+
+/// The chance of each child in the genetic solver being mutated
+const MUTATION: f64 = 0.3;
+
+/// Picks the best of a few random members of the population
+fn tournament<'a>(population: &'a [Solution], config: &Config, rng: &mut impl Rng) -> &'a Solution {
+    (0..TOURNAMENT)
+        .map(|_| population.choose(rng).unwrap())
+        .max_by_key(|x| x.rank_key(config))
+        .unwrap()
+}
+
+/// Breeds a child from two solutions. A random half of `a`'s rooms are kept
+/// as they are, and everyone else is rehoused in `a`'s other rooms, keeping
+/// together whoever shared a room in `b`. Returns `None` if a [never_pair] or
+/// [must_pair] pair got in the way
+fn crossover(a: &Solution, b: &Solution, config: &Config, rng: &mut impl Rng) -> Option<Solution> {
+    let mut rooms = a.rooms.clone();
+    let mut emptied = vec![];
+    for (i, room) in rooms.iter_mut().enumerate() {
+        if rng.gen_bool(0.5) {
+            room.clear();
+            emptied.push(i);
+        }
+    }
+    let placed = rooms.iter().flatten().cloned().collect::<HashSet<_>>();
+
+    let mut groups = b
+        .rooms
+        .iter()
+        .map(|x| {
+            x.iter()
+                .filter(|x| !placed.contains(*x))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>();
+    groups.shuffle(rng);
+    groups.sort_by_key(|x| cmp::Reverse(x.len()));
+
+    let fits = |group: &[String], room: &[String], capacity: usize| {
+        room.len() + group.len() <= capacity
+            && group
+                .iter()
+                .all(|x| room.iter().all(|y| !config.forbids(x, y)))
+    };
+    for group in groups {
+        // Whole groups are placed where possible, otherwise they are split
+        // up, but never between a [must_pair] pair
+        let mut units = vec![];
+        if emptied
+            .iter()
+            .all(|&i| !fits(&group, &rooms[i], room_capacity(config, &a.names, i)))
+        {
+            for person in &group {
+                match config.partner(person) {
+                    Some(partner) if partner < person.as_str() => {}
+                    Some(partner) => units.push(vec![person.clone(), partner.to_string()]),
+                    None => units.push(vec![person.clone()]),
+                }
+            }
+        } else {
+            units.push(group);
+        }
+        for unit in units {
+            let i = *emptied
+                .iter()
+                .find(|&&i| fits(&unit, &rooms[i], room_capacity(config, &a.names, i)))?;
+            rooms[i].extend(unit);
+        }
+    }
+    // Rooms left empty are dropped, along with their names
+    let names = a
+        .names
+        .iter()
+        .zip(&rooms)
+        .filter(|x| !x.1.is_empty())
+        .map(|x| x.0.clone())
+        .collect();
+    rooms.retain(|x| !x.is_empty());
+    Some(Solution::new(rooms, names, config))
+}
+
+/// Swaps two random people between rooms, unless either of them is in a
+/// [must_pair] pair or the swap would make a forbidden pair
+fn mutate(solution: &Solution, config: &Config, rng: &mut impl Rng) -> Solution {
+    let mut rooms = solution.rooms.clone();
+    if rooms.len() >= 2 {
+        let a = rng.gen_range(0..rooms.len());
+        let b = (a + rng.gen_range(1..rooms.len())) % rooms.len();
+        let i = rng.gen_range(0..rooms[a].len());
+        let j = rng.gen_range(0..rooms[b].len());
+        let (x, y) = (&rooms[a][i], &rooms[b][j]);
+        let allowed = config.partner(x).is_none()
+            && config.partner(y).is_none()
+            && rooms[a].iter().all(|z| z == x || !config.forbids(y, z))
+            && rooms[b].iter().all(|z| z == y || !config.forbids(x, z));
+        if allowed {
+            let x = x.clone();
+            rooms[a][i] = y.clone();
+            rooms[b][j] = x;
+        }
+    }
+    Solution::new(rooms, solution.names.clone(), config)
+}
+
+/// Evolves a population of random solutions over `settings.generations`
+/// generations. Each generation keeps its best solution, and breeds the rest
+/// from parents picked by tournament, with some children mutated. Returns
+/// the final generation
+pub fn solve_genetic(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    let size = config.settings.population.max(2);
+    let mut population = vec![];
+    // Gives up on finding a starting population if most attempts run into a
+    // forbidden pair
+    for _ in 0..size * 10 {
+        population.extend(solve(config, rng)?);
+        if population.len() == size {
+            break;
+        }
+    }
+    if population.is_empty() {
+        return Ok(population);
+    }
+
+    for _ in 0..config.settings.generations {
+        if interrupted() {
+            break;
+        }
+        let best = population
+            .iter()
+            .max_by_key(|x| x.rank_key(config))
+            .unwrap();
+        let mut next = vec![best.clone()];
+        while next.len() < size {
+            let a = tournament(&population, config, rng);
+            let b = tournament(&population, config, rng);
+            let child = crossover(a, b, config, rng).unwrap_or_else(|| a.clone());
+            let child = if rng.gen_bool(MUTATION) {
+                mutate(&child, config, rng)
+            } else {
+                child
+            };
+            next.push(child);
+        }
+        population = next;
+    }
+    Ok(population)
+}
+
+/// How many of the best random candidates get polished
+const POLISHED: usize = 20;
+
+/// The `Solution::rank_key` of some rooms. Keys can be added up, so the key
+/// of a whole solution is the sum of its rooms' keys
+fn rank_counts(rooms: &[Vec<String>], config: &Config) -> [i64; COUNTS] {
+    Solution::new(rooms.to_vec(), vec![], config).rank_key(config)
+}
+
+/// Swaps people between rooms for as long as a swap strictly improves the
+/// solution. Pairs from [must_pair] stay put, and swaps into a forbidden pair
+/// are never made. As the other rooms don't change, only the two rooms being
+/// swapped between need scoring
+pub fn polish(solution: &Solution, config: &Config) -> Solution {
+    let mut rooms = solution.rooms.clone();
+    let movable = |person: &String| config.partner(person).is_none();
+    let fits = |person: &String, room: &[String]| room.iter().all(|x| !config.forbids(person, x));
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for a in 0..rooms.len() {
+            for b in a + 1..rooms.len() {
+                let mut best = rank_counts(&[rooms[a].clone(), rooms[b].clone()], config);
+                for i in 0..rooms[a].len() {
+                    for j in 0..rooms[b].len() {
+                        let mut room_a = rooms[a].clone();
+                        let mut room_b = rooms[b].clone();
+                        if !movable(&room_a[i]) || !movable(&room_b[j]) {
+                            continue;
+                        }
+                        let x = room_a.remove(i);
+                        let y = room_b.remove(j);
+                        if !fits(&x, &room_b) || !fits(&y, &room_a) {
+                            continue;
+                        }
+                        room_a.insert(i, y);
+                        room_b.insert(j, x);
+                        let swapped = [room_a, room_b];
+                        let counts = rank_counts(&swapped, config);
+                        if counts > best {
+                            best = counts;
+                            [rooms[a], rooms[b]] = swapped;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Solution::new(rooms, solution.names.clone(), config)
+}
+
+/// Improves random solutions with `polish` from `settings.restarts` starting
+/// points, or from as many as fit in the timeout if there is one. Starting
+/// points that ran into a forbidden pair are dropped
+pub fn solve_hillclimb(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    repeat(config, config.settings.restarts, || {
+        Ok(solve(config, rng)?.map(|x| polish(&x, config)))
+    })
+}
+
+/// Whether no solution could beat this one, with every matchup in the best
+/// tier the config uses. Configs with weighted preferences or a custom
+/// `settings.objective` never count, since another solution could always
+/// score higher
+pub fn is_perfect(solution: &Solution, config: &Config) -> bool {
+    let matchups = solution.strongly_preferred
+        + solution.preferred
+        + solution.acceptable
+        + solution.accepted
+        + solution.unpreferred;
+    let best = if config.strongly_preferred.is_empty() {
+        solution.preferred
+    } else {
+        solution.strongly_preferred
+    };
+    config.settings.objective.is_default() && !config.weighted() && best == matchups
+}
+
+/// Keeps making attempts at a solution, either `count` of them or as many as
+/// fit in the timeout, dropping any that ran into a forbidden pair. With
+/// `settings.convergence`, attempts carry on until the best solution hasn't
+/// improved for a whole window of them, still stopping at the timeout. Stops
+/// as soon as a perfect solution turns up, unless `settings.early_stop` is off.
+/// With `settings.pareto`, only the solutions on the Pareto front are kept
+fn repeat(
+    config: &Config,
+    count: u64,
+    mut attempt: impl FnMut() -> Result<Option<Solution>>,
+) -> Result<Vec<Solution>> {
+    // Some configs are never perfect, so don't check every attempt
+    let early_stop =
+        config.settings.early_stop && config.settings.objective.is_default() && !config.weighted();
+    let start = Instant::now();
+    let mut result = vec![];
+    let mut front = Front::default();
+    let mut attempts = 0;
+    let mut best = None;
+    let mut stale = 0;
+    let convergence = config.settings.convergence;
+    if convergence.is_some_and(|x| x.window == 0) {
+        bail!("settings.convergence.window must be at least 1");
+    }
+    let limit = |attempts: u64| convergence.is_some() || attempts < count;
+    while !interrupted()
+        && config
+            .settings
+            .timeout
+            .map_or(limit(attempts), |x| start.elapsed() < x)
+    {
+        attempts += 1;
+        stale += 1;
+        if convergence.is_some_and(|x| stale > x.window) {
+            break;
+        }
+        let Some(solution) = attempt()? else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|x| solution.rank_key(config) > *x) {
+            best = Some(solution.rank_key(config));
+            stale = 0;
+        }
+        let perfect = early_stop && is_perfect(&solution, config);
+        if config.settings.pareto {
+            front.insert(tradeoff(solution.counts()), || solution);
+        } else {
+            result.push(solution);
+        }
+        if perfect {
+            break;
+        }
+    }
+    result.extend(front.into_sorted());
+    Ok(result)
+}
+
+/// A swap tabu search could make, between person `people[0]` of room
+/// `rooms[0]` and person `people[1]` of room `rooms[1]`
+struct Swap {
+    /// How the swap changes the solution's rank counts
+    delta: [i64; COUNTS],
+    rooms: [usize; 2],
+    people: [usize; 2],
+    /// Both rooms' rank counts after the swap
+    counts: [[i64; COUNTS]; 2],
+}
+
+/// Starts from a random solution and makes the best swap between two rooms
+/// over and over, even when every swap makes things worse, so that the search
+/// can walk out of a local optimum. Anyone swapped sits out the next
+/// `settings.tabu_tenure` moves so the search doesn't walk straight back,
+/// unless swapping them would beat the best solution seen. Pairs from
+/// [must_pair] stay put, and swaps into a forbidden pair are never made.
+/// Returns the best solution seen
+pub fn solve_tabu(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    let mut start = None;
+    for _ in 0..100 {
+        start = solve(config, rng)?;
+        if start.is_some() {
+            break;
+        }
+    }
+    let Some(Solution {
+        mut rooms, names, ..
+    }) = start
+    else {
+        return Ok(None);
+    };
+
+    let movable = |person: &String| config.partner(person).is_none();
+    let fits = |person: &String, room: &[String]| room.iter().all(|x| !config.forbids(person, x));
+    let mut counts = rooms
+        .iter()
+        .map(|x| rank_counts(std::slice::from_ref(x), config))
+        .collect::<Vec<_>>();
+    let mut total = counts.iter().fold([0; COUNTS], |x, y| add_counts(x, *y, 1));
+    let mut best = (total, rooms.clone());
+    let mut tabu = HashMap::<String, u64>::new();
+
+    for iteration in 0..config.settings.tabu_iterations {
+        if interrupted() {
+            break;
+        }
+        let allowed = |person: &String, after: [i64; COUNTS]| {
+            tabu.get(person).is_none_or(|&x| x <= iteration) || after > best.0
+        };
+        let mut chosen: Option<Swap> = None;
+        for a in 0..rooms.len() {
+            for b in a + 1..rooms.len() {
+                for i in 0..rooms[a].len() {
+                    for j in 0..rooms[b].len() {
+                        let (x, y) = (&rooms[a][i], &rooms[b][j]);
+                        if !movable(x) || !movable(y) {
+                            continue;
+                        }
+                        let mut room_a = rooms[a].clone();
+                        let mut room_b = rooms[b].clone();
+                        room_a[i] = y.clone();
+                        room_b[j] = x.clone();
+                        let others_a = room_a
+                            .iter()
+                            .filter(|z| *z != y)
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        let others_b = room_b
+                            .iter()
+                            .filter(|z| *z != x)
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        if !fits(y, &others_a) || !fits(x, &others_b) {
+                            continue;
+                        }
+                        let new = [
+                            rank_counts(&[room_a], config),
+                            rank_counts(&[room_b], config),
+                        ];
+                        let delta = add_counts(
+                            add_counts(new[0], new[1], 1),
+                            add_counts(counts[a], counts[b], 1),
+                            -1,
+                        );
+                        let after = add_counts(total, delta, 1);
+                        if !allowed(x, after) || !allowed(y, after) {
+                            continue;
+                        }
+                        if chosen.as_ref().is_none_or(|x| delta > x.delta) {
+                            chosen = Some(Swap {
+                                delta,
+                                rooms: [a, b],
+                                people: [i, j],
+                                counts: new,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        let Some(Swap {
+            delta,
+            rooms: [a, b],
+            people: [i, j],
+            counts: new,
+        }) = chosen
+        else {
+            break;
+        };
+
+        let x = rooms[a][i].clone();
+        let y = std::mem::replace(&mut rooms[b][j], x.clone());
+        rooms[a][i] = y.clone();
+        let until = iteration + 1 + config.settings.tabu_tenure;
+        tabu.insert(x, until);
+        tabu.insert(y, until);
+        counts[a] = new[0];
+        counts[b] = new[1];
+        total = add_counts(total, delta, 1);
+        if total > best.0 {
+            best = (total, rooms.clone());
+        }
+    }
+    Ok(Some(Solution::new(best.1, names, config)))
+}
+
+/// Polishes the best few candidates in place, as random solutions are often
+/// only a swap or two away from a better one
+fn polish_best(solutions: &mut [Solution], config: &Config) {
+    let mut order = (0..solutions.len()).collect::<Vec<_>>();
+    order.sort_by_cached_key(|&i| cmp::Reverse(solutions[i].rank_key(config)));
+    for i in order.into_iter().take(POLISHED) {
+        solutions[i] = polish(&solutions[i], config);
+    }
+}
+
+/// A depth first search that places one person, or one [must_pair] pair, at
+/// a time
+struct Branching<'a> {
+    config: &'a Config,
+    people: Vec<String>,
+    /// The people in each unit to place, in the order they are placed
+    units: Vec<Vec<usize>>,
+    /// The rank counts of each pair that is allowed to share a room
+    counts: Vec<Vec<Option<[i64; COUNTS]>>>,
+    /// The most the units from each one onwards could possibly add to the rank
+    /// counts, counted separately for each count
+    potential: Vec<[i64; COUNTS]>,
+    /// The best rank counts of any one pair, or nothing if no pair is better
+    /// than staying apart
+    best_pair: [i64; COUNTS],
+    /// The most pairs a solution can have, with the largest rooms filled first
+    max_pairs: i64,
+    capacities: Vec<usize>,
+    rooms: Vec<Vec<usize>>,
+    total: [i64; COUNTS],
+    best: [i64; COUNTS],
+    best_rooms: Option<Vec<Vec<usize>>>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl Branching<'_> {
+    fn search(&mut self, k: usize) {
+        if interrupted() || self.deadline.is_some_and(|x| Instant::now() >= x) {
+            self.timed_out = true;
+            return;
+        }
+        if k == self.units.len() {
+            if self.total > self.best {
+                self.best = self.total;
+                self.best_rooms = Some(self.rooms.clone());
+            }
+            return;
+        }
+        // Even if everyone left got their best possible roommates, the
+        // solution couldn't beat the best one so far. In particular, this
+        // prunes as soon as there are more unpreferred matchups than it has.
+        // Only so many pairs are left to form, and none of them can beat the
+        // best pair, which is a second limit on what is left to gain
+        let formed = self
+            .rooms
+            .iter()
+            .map(|x| x.len() * x.len().saturating_sub(1) / 2);
+        let left = self.max_pairs - formed.sum::<usize>() as i64;
+        let potential = self.potential[k].min(self.best_pair.map(|x| x * left));
+        if add_counts(self.total, potential, 1) <= self.best {
+            return;
+        }
+
+        // Empty rooms of the same size are interchangeable, so only one of
+        // them is tried. The most promising rooms are tried first
+        let unit = self.units[k].clone();
+        let mut empty = HashSet::new();
+        let mut moves = vec![];
+        for (r, room) in self.rooms.iter().enumerate() {
+            if room.len() + unit.len() > self.capacities[r]
+                || (room.is_empty() && !empty.insert(self.capacities[r]))
+            {
+                continue;
+            }
+            let mut delta = Some([0; COUNTS]);
+            for (i, &a) in unit.iter().enumerate() {
+                for &b in room.iter().chain(&unit[i + 1..]) {
+                    delta = delta
+                        .zip(self.counts[a][b])
+                        .map(|x| add_counts(x.0, x.1, 1));
+                }
+            }
+            if let Some(delta) = delta {
+                moves.push((delta, r));
+            }
+        }
+        moves.sort_by_key(|x| cmp::Reverse(x.0));
+
+        for (delta, r) in moves {
+            self.rooms[r].extend(&unit);
+            self.total = add_counts(self.total, delta, 1);
+            self.search(k + 1);
+            self.total = add_counts(self.total, delta, -1);
+            let len = self.rooms[r].len() - unit.len();
+            self.rooms[r].truncate(len);
+            if self.timed_out {
+                return;
+            }
+        }
+    }
+}
+
+/// Searches every way of filling the rooms for an optimal solution, placing
+/// one person at a time and skipping any branch that can't beat the best
+/// solution found so far. A polished random solution is the one to beat at
+/// first. If `settings.timeout` is set, the search stops there and gives the
+/// best solution it found, which might not be optimal
+pub fn solve_branch_and_bound(config: &Config, rng: &mut impl Rng) -> Result<Option<Solution>> {
+    // Rooms are scored a pair at a time, but how the worst-off person feels
+    // only adds up that way when each room is a single pair
+    if config.settings.objective.uses(Count::WorstOff)
+        && config.capacities().iter().any(|x| x.1 > 2)
+    {
+        bail!("The branch_and_bound solver can only compare the worst-off person when every room is for two people");
+    }
+    let mut incumbent = None;
+    for _ in 0..100 {
+        incumbent = solve(config, rng)?.map(|x| polish(&x, config));
+        if incumbent.is_some() {
+            break;
+        }
+    }
+
+    let people = config.people();
+    let index = |name: &str| people.iter().position(|x| x == name);
+    let mut units = vec![];
+    for (a, b) in config.must_pairs() {
+        let (Some(a), Some(b)) = (index(&a), index(&b)) else {
+            bail!(
+                "'{}' is in [must_pair] more than once, or isn't in the people list",
+                a
+            );
+        };
+        units.push(vec![a, b]);
+    }
+    let paired = units.iter().flatten().copied().collect::<HashSet<_>>();
+    units.extend(
+        (0..people.len())
+            .filter(|x| !paired.contains(x))
+            .map(|x| vec![x]),
+    );
+
+    let counts = people
+        .iter()
+        .map(|a| {
+            people
+                .iter()
+                .map(|b| {
+                    (a != b && !config.forbids(a, b)).then(|| {
+                        Solution::new(vec![vec![a.clone(), b.clone()]], vec![], config).counts()
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let capacities = config.capacities().iter().map(|x| x.1).collect::<Vec<_>>();
+    let roommates = capacities.iter().max().copied().unwrap_or(1) as i64 - 1;
+    // Each pair is counted by whoever of them is placed last, so only the
+    // people placed before someone, or after them in their own unit, can add
+    // to their counts
+    let mut potential = vec![[0; COUNTS]; units.len() + 1];
+    for k in (0..units.len()).rev() {
+        potential[k] = potential[k + 1];
+        for (i, &a) in units[k].iter().enumerate() {
+            let before = units[..k].iter().flatten().chain(&units[k][i + 1..]);
+            let best = before
+                .filter_map(|&b| counts[a][b])
+                .fold([0; COUNTS], |x, y| std::array::from_fn(|c| x[c].max(y[c])));
+            for (c, best) in best.iter().enumerate() {
+                potential[k][c] += roommates * best;
+            }
+        }
+    }
+
+    let best_pair = counts
+        .iter()
+        .flatten()
+        .flatten()
+        .fold([0; COUNTS], |x, y| x.max(*y));
+    let mut sizes = capacities.clone();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    let mut unplaced = people.len();
+    let mut max_pairs = 0;
+    for size in sizes {
+        let filled = size.min(unplaced);
+        max_pairs += (filled * filled.saturating_sub(1) / 2) as i64;
+        unplaced -= filled;
+    }
+
+    let mut branching = Branching {
+        config,
+        units,
+        counts,
+        potential,
+        best_pair,
+        max_pairs,
+        rooms: vec![vec![]; capacities.len()],
+        capacities,
+        total: [0; COUNTS],
+        best: incumbent
+            .as_ref()
+            .map_or([i64::MIN; COUNTS], |x| rank_counts(&x.rooms, config)),
+        best_rooms: None,
+        deadline: config.settings.timeout.map(|x| Instant::now() + x),
+        timed_out: false,
+        people,
+    };
+    branching.search(0);
+
+    let Some(best) = branching.best_rooms else {
+        return Ok(incumbent);
+    };
+    let mut rooms = vec![];
+    let mut names = vec![];
+    for ((name, _), room) in branching.config.capacities().into_iter().zip(best) {
+        if !room.is_empty() {
+            rooms.push(
+                room.into_iter()
+                    .map(|x| branching.people[x].clone())
+                    .collect(),
+            );
+            names.extend(name.map(String::from));
+        }
+    }
+    Ok(Some(Solution::new(rooms, names, config)))
+}
+
+#[cfg(not(feature = "ilp"))]
+fn solve_ilp(_: &Config) -> Result<Option<Solution>> {
+    bail!("picker was built without the `ilp` feature, which the ilp solver needs")
+}
+
+/// Rosters up to this size are solved exhaustively when no solver is chosen
+const EXHAUSTIVE_DEFAULT: usize = 12;
+
+/// The exhaustive solver refuses larger rosters, as the number of pairings
+/// grows too quickly to check them all
+const EXHAUSTIVE_LIMIT: usize = 16;
+
+/// The solver to use, which for small rosters in rooms of two defaults to
+/// checking every pairing rather than sampling them, unless a number of
+/// solutions or a time limit asked for sampling, see `Settings::limit`
+pub fn chosen_solver(config: &Config) -> Algorithm {
+    config.settings.solver.unwrap_or_else(|| {
+        let small = config.people().len() <= EXHAUSTIVE_DEFAULT;
+        if small && config.pair_rooms() {
+            Algorithm::Exhaustive
+        } else {
+            Algorithm::Random
+        }
+    })
+}
+
+fn add_counts(a: [i64; COUNTS], b: [i64; COUNTS], sign: i64) -> [i64; COUNTS] {
+    std::array::from_fn(|i| a[i] + sign * b[i])
+}
+
+/// Who each person is paired with, or `None` for a room of their own
+type Pairing = Vec<(usize, Option<usize>)>;
+
+/// A depth first walk through every pairing, keeping the optimal ones, or
+/// the ones on the Pareto front if there is one
+struct Enumeration<'a> {
+    /// The counts of each pair that is allowed to share a room
+    counts: Vec<Vec<Option<[i64; COUNTS]>>>,
+    objective: &'a Objective,
+    used: Vec<bool>,
+    current: Pairing,
+    total: [i64; COUNTS],
+    best: Option<[i64; COUNTS]>,
+    optimal: Vec<Pairing>,
+    front: Option<Front<Pairing>>,
+}
+
+impl Enumeration<'_> {
+    /// Pairs up the first person left with each possible roommate in turn,
+    /// or puts them in a room on their own while `singles` allows it
+    fn search(&mut self, singles: usize) {
+        let Some(a) = self.used.iter().position(|x| !x) else {
+            if let Some(front) = &mut self.front {
+                front.insert(tradeoff(self.total), || self.current.clone());
+                return;
+            }
+            // Keys are linear in the counts, so the total's key is the sum of
+            // the pairs' keys
+            let key = self.objective.key(self.total);
+            match self.best {
+                Some(best) if best > key => {}
+                Some(best) if best == key => self.optimal.push(self.current.clone()),
+                _ => {
+                    self.best = Some(key);
+                    self.optimal = vec![self.current.clone()];
+                }
+            }
+            return;
+        };
+        self.used[a] = true;
+        if singles > 0 {
+            self.current.push((a, None));
+            self.search(singles - 1);
+            self.current.pop();
+        }
+        for b in a + 1..self.used.len() {
+            let Some(counts) = self.counts[a][b].filter(|_| !self.used[b]) else {
+                continue;
+            };
+            self.used[b] = true;
+            self.total = add_counts(self.total, counts, 1);
+            self.current.push((a, Some(b)));
+            self.search(singles);
+            self.current.pop();
+            self.total = add_counts(self.total, counts, -1);
+            self.used[b] = false;
+        }
+        self.used[a] = false;
+    }
+}
+
+/// Checks every way of pairing people up, giving every optimal solution, or
+/// every solution on the Pareto front with `settings.pareto`. Everyone shares
+/// a room with someone, apart from one person when there is an odd number of
+/// people. Gives no solutions if [never_pair] rules out every pairing
+pub fn solve_exhaustive(config: &Config) -> Result<Vec<Solution>> {
+    check_pair_rooms(config, "exhaustive")?;
+    let Some((rooms, people)) = start_must_pairs(config)? else {
+        return Ok(vec![]);
+    };
+    if people.len() > EXHAUSTIVE_LIMIT {
+        bail!(
+            "The exhaustive solver only works for up to {} people outside of [must_pair], but there are {}",
+            EXHAUSTIVE_LIMIT,
+            people.len()
+        );
+    }
+
+    let counts = people
+        .iter()
+        .map(|a| {
+            people
+                .iter()
+                .map(|b| {
+                    (a != b && !config.forbids(a, b)).then(|| {
+                        Solution::new(vec![vec![a.clone(), b.clone()]], vec![], config).counts()
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    let mut enumeration = Enumeration {
+        counts,
+        objective: &config.settings.objective,
+        used: vec![false; people.len()],
+        current: vec![],
+        total: [0; COUNTS],
+        best: None,
+        optimal: vec![],
+        front: config.settings.pareto.then(Front::default),
+    };
+    enumeration.search(people.len() % 2);
+
+    let pairings = match enumeration.front {
+        Some(front) => front.into_sorted(),
+        None => enumeration.optimal,
+    };
+    let mut solutions = vec![];
+    for pairing in pairings {
+        let mut rooms = rooms.clone();
+        for (a, b) in pairing {
+            let mut room = vec![people[a].clone()];
+            room.extend(b.map(|b| people[b].clone()));
+            rooms.push(room);
+        }
+        solutions.push(pairing_solution(rooms, config)?);
+    }
+    Ok(solutions)
+}
+
+/// A way of finding candidate solutions for a config
+pub trait Solver {
+    /// Finds candidate solutions for the config, giving none if no solution
+    /// keeps every forbidden pair apart. Solvers that find several equally
+    /// good solutions give all of them, so one can be picked at random
+    fn solve(&self, config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>>;
+}
+
+/// Fills rooms at random, following preferences where it can, either a fixed
+/// number of times or as many as fit in the timeout, then polishes the best
+/// few with `polish`. Stops early if a perfect solution turns up
+pub struct RandomGreedy;
+
+impl Solver for RandomGreedy {
+    fn solve(&self, config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+        let mut result = repeat(config, config.settings.solutions, || solve(config, rng))?;
+        if config.settings.pareto {
+            // Polishing only climbs towards the best solution, which would
+            // lose the trade-offs on the front, so both are kept
+            let mut polished = result.clone();
+            polish_best(&mut polished, config);
+            result.extend(polished);
+        } else {
+            polish_best(&mut result, config);
+        }
+        Ok(result)
+    }
+}
+
+/// The exhaustive solver gives every optimal solution, the hill climbing
+/// solver the end of every climb, the genetic solver its final generation,
+/// and the random solver every attempt that didn't run into a forbidden pair.
+/// The other solvers only ever give the one solution they find
+impl Solver for Algorithm {
+    fn solve(&self, config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+        match self {
+            Algorithm::Random => RandomGreedy.solve(config, rng),
+            Algorithm::Exhaustive => solve_exhaustive(config),
+            Algorithm::Exact => Ok(solve_exact(config)?.into_iter().collect()),
+            Algorithm::Stable => Ok(solve_stable(config, rng)?.into_iter().collect()),
+            Algorithm::GaleShapley => Ok(solve_gale_shapley(config, rng)?.into_iter().collect()),
+            Algorithm::Hillclimb => solve_hillclimb(config, rng),
+            Algorithm::Tabu => Ok(solve_tabu(config, rng)?.into_iter().collect()),
+            Algorithm::Anneal => Ok(solve_anneal(config, rng)?.into_iter().collect()),
+            Algorithm::Genetic => solve_genetic(config, rng),
+            Algorithm::Ilp => Ok(solve_ilp(config)?.into_iter().collect()),
+            Algorithm::BranchAndBound => {
+                Ok(solve_branch_and_bound(config, rng)?.into_iter().collect())
+            }
+        }
+    }
+}
+
+/// Takes the rooms from [locked] out of the config, solves everyone else,
+/// and puts the locked rooms back at the front of every solution
+fn solve_locked(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    let people = config.people();
+    let mut sub = config.clone();
+    sub.locked.clear();
+    let mut rooms = vec![];
+    let mut names = vec![];
+    let mut locked = HashSet::new();
+    for (key, members) in &config.locked {
+        for member in members {
+            if !people.contains(member) {
+                bail!("'{}' is in [locked], but isn't in the people list", member);
+            }
+            if !locked.insert(member) {
+                bail!("'{}' is in more than one room in [locked]", member);
+            }
+            if let Some(partner) = config.partner(member) {
+                if !members.iter().any(|x| x == partner) {
+                    bail!(
+                        "'{}' is locked in a room without '{}', who they are in [must_pair] with",
+                        member,
+                        partner
+                    );
+                }
+            }
+        }
+        let size = members.len();
+        if !config.rooms.is_empty() {
+            let name = match sub.rooms.get(key) {
+                Some(_) => key.clone(),
+                None => sub
+                    .rooms
+                    .iter()
+                    .filter(|x| x.1.capacity >= size && !config.locked.contains_key(x.0))
+                    .min_by_key(|x| x.1.capacity)
+                    .map(|x| x.0.clone())
+                    .ok_or_else(|| anyhow!("No room in [rooms] is free for {} people", size))?,
+            };
+            let capacity = sub.rooms.remove(&name).unwrap().capacity;
+            if capacity < size {
+                bail!(
+                    "The room '{}' is for {} people, but {} are locked in it",
+                    name,
+                    capacity,
+                    size
+                );
+            }
+            names.push(name);
+        } else if let Some(sizes) = &mut sub.settings.group_sizes {
+            let Some(i) = sizes.iter().position(|&x| x == size) else {
+                bail!("settings.group_sizes has no group of {} for [locked]", size);
+            };
+            sizes.remove(i);
+        } else if size > config.settings.room_size {
+            bail!(
+                "[locked] has a room of {} people, but rooms are for {}",
+                size,
+                config.settings.room_size
+            );
+        }
+        rooms.push(members.clone());
+    }
+
+    let rest = people
+        .into_iter()
+        .filter(|x| !locked.contains(x))
+        .collect::<Vec<_>>();
+    if rest.is_empty() {
+        return Ok(vec![Solution::new(rooms, names, config)]);
+    }
+    sub.people = rest;
+    sub.must_pair.retain(|a, _| !locked.contains(a));
+    Ok(generate_solutions(&sub, rng)?
+        .into_iter()
+        .map(|solution| {
+            let rooms = rooms.iter().cloned().chain(solution.rooms).collect();
+            let names = names.iter().cloned().chain(solution.names).collect();
+            Solution::new(rooms, names, config)
+        })
+        .collect())
+}
+
+/// Generates candidate solutions with the chosen solver, each with its rooms
+/// sorted, see `Solution::sort`
+pub fn generate_solutions(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    let mut solutions = candidates(config, rng)?;
+    for solution in &mut solutions {
+        solution.sort();
+    }
+    Ok(solutions)
+}
+
+fn candidates(config: &Config, rng: &mut impl Rng) -> Result<Vec<Solution>> {
+    if !config.locked.is_empty() {
+        return solve_locked(config, rng);
+    }
+    let people = config.people().len();
+    if let (true, Some(sizes)) = (config.rooms.is_empty(), &config.settings.group_sizes) {
+        let places = sizes.iter().sum::<usize>();
+        if places != people {
+            bail!(
+                "settings.group_sizes has places for {} people, but there are {}",
+                places,
+                people
+            );
+        }
+        return chosen_solver(config).solve(config, rng);
+    }
+    if config.settings.room_size < 2 {
+        bail!("settings.room_size must be at least 2");
+    }
+    let size = config.settings.room_size;
+    if config.rooms.is_empty()
+        && !people.is_multiple_of(size)
+        && config.settings.odd_policy == Some(OddPolicy::Error)
+    {
+        bail!(
+            "There are {} people, which can't be split into rooms of {}, and settings.odd_policy is \"error\"",
+            people,
+            size
+        );
+    }
+    chosen_solver(config).solve(config, rng)
+}
+
+/// A solution's unpreferred, preferred and accepted matchups, which the
+/// Pareto front is found on
+type Tradeoff = (u64, u64, u64);
+
+fn tradeoff(counts: [i64; COUNTS]) -> Tradeoff {
+    let count = |x: Count| counts[x as usize] as u64;
+    (
+        count(Count::Unpreferred),
+        count(Count::Preferred),
+        count(Count::Accepted),
+    )
+}
+
+/// The solutions found so far that no other beats on fewer unpreferred, more
+/// preferred and fewer accepted matchups all at once, with one for each set
+/// of those counts. Accepted matchups are ones nobody asked for, so fewer of
+/// them is better. The front stays small however many solutions are added,
+/// so it can be kept up to date while solving
+pub struct Front<T> {
+    members: Vec<(Tradeoff, T)>,
+}
+
+impl<T> Default for Front<T> {
+    fn default() -> Self {
+        Self { members: vec![] }
+    }
+}
+
+impl<T> Front<T> {
+    /// Adds a solution unless one already on the front is at least as good,
+    /// dropping any it beats. `item` is only made if it is kept
+    pub fn insert(&mut self, counts: Tradeoff, item: impl FnOnce() -> T) {
+        let covers = |a: &Tradeoff, b: &Tradeoff| a.0 <= b.0 && a.1 >= b.1 && a.2 <= b.2;
+        if self.members.iter().any(|x| covers(&x.0, &counts)) {
+            return;
+        }
+        self.members.retain(|x| !covers(&counts, &x.0));
+        self.members.push((counts, item()));
+    }
+
+    /// Everything on the front, from fewest unpreferred matchups up
+    pub fn into_sorted(mut self) -> Vec<T> {
+        self.members
+            .sort_by_key(|x| (x.0 .0, cmp::Reverse(x.0 .1), x.0 .2));
+        self.members.into_iter().map(|x| x.1).collect()
+    }
+}
+
+/// Keeps the solutions on the Pareto front, see `Front`, sorted from fewest
+/// unpreferred matchups up
+pub fn pareto_front(solutions: &[Solution]) -> Vec<&Solution> {
+    let mut front = Front::default();
+    for solution in solutions {
+        front.insert(tradeoff(solution.counts()), || solution);
+    }
+    front.into_sorted()
+}
+
+/// Keeps only the solutions that share the best score. The same rooms can be
+/// found many times over, so each distinct solution is kept once, and
+/// choosing from the result is fair between them
+pub fn best<'a>(solutions: &'a [Solution], config: &Config) -> Vec<&'a Solution> {
+    let Some(best) = solutions.iter().map(|x| x.rank_key(config)).max() else {
+        return vec![];
+    };
+    let mut seen = HashSet::new();
+    solutions
+        .iter()
+        .filter(|x| x.rank_key(config) == best && seen.insert(x.identity()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::random_config;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn exact_matches_exhaustive() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for settings in [
+            "",
+            "objective = { kind = \"lexicographic\", order = [\"worst_off\", \"preferred\"] }",
+        ] {
+            for people in [2, 5, 6, 9, 10] {
+                for _ in 0..5 {
+                    let config = random_config(&mut rng, people, settings);
+                    let exact = solve_exact(&config).unwrap().unwrap();
+                    let exhaustive = solve_exhaustive(&config).unwrap();
+                    let best = exhaustive.iter().map(|x| x.rank_key(&config)).max();
+                    assert_eq!(Some(exact.rank_key(&config)), best);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn anneal_is_never_worse_than_exact() {
+        let mut rng = StdRng::seed_from_u64(48);
+        for settings in [
+            "iterations = 2000",
+            "iterations = 2000\nobjective = { kind = \"lexicographic\", order = [\"worst_off\", \"preferred\"] }",
+        ] {
+            for people in [4, 5, 6, 8] {
+                for _ in 0..5 {
+                    let config = random_config(&mut rng, people, settings);
+                    let exact = solve_exact(&config).unwrap().unwrap();
+                    let anneal = solve_anneal(&config, &mut rng).unwrap().unwrap();
+                    assert!(anneal.rank_key(&config) >= exact.rank_key(&config));
+                }
+            }
+        }
+
+        // A heavy preferred pair doesn't make up for an unpreferred one
+        let config = crate::config::parse_toml(
+            r#"
+            people = ["a", "b", "c", "d"]
+
+            [settings]
+            solutions = 1
+            iterations = 2000
+
+            [preferred]
+            a = { b = 20 }
+            b = { a = 20 }
+
+            [unpreferred]
+            c = ["d"]
+            d = ["c"]
+            "#,
+        )
+        .unwrap();
+        for _ in 0..20 {
+            let anneal = solve_anneal(&config, &mut rng).unwrap().unwrap();
+            assert_eq!(anneal.unpreferred, 0);
+        }
+    }
+
+    #[test]
+    fn front_keeps_exactly_the_undominated() {
+        let mut rng = StdRng::seed_from_u64(62);
+        for _ in 0..100 {
+            let points = (0..50)
+                .map(|_| {
+                    (
+                        rng.gen_range(0..5),
+                        rng.gen_range(0..5),
+                        rng.gen_range(0..5),
+                    )
+                })
+                .collect::<Vec<Tradeoff>>();
+            let mut front = Front::default();
+            for &point in &points {
+                front.insert(point, || point);
+            }
+            let beats =
+                |a: &Tradeoff, b: &Tradeoff| a.0 <= b.0 && a.1 >= b.1 && a.2 <= b.2 && a != b;
+            let mut expected = points
+                .iter()
+                .filter(|x| !points.iter().any(|y| beats(y, x)))
+                .copied()
+                .collect::<Vec<_>>();
+            expected.sort_by_key(|x| (x.0, cmp::Reverse(x.1), x.2));
+            expected.dedup();
+            assert_eq!(front.into_sorted(), expected);
+        }
+    }
+
+    #[test]
+    fn exhaustive_keeps_every_tradeoff_for_pareto() {
+        let mut config = crate::config::parse_toml(
+            r#"
+            people = ["a", "b", "c", "d"]
+
+            [settings]
+            solutions = 1
+
+            [preferred]
+            a = ["b"]
+            b = ["a"]
+
+            [unpreferred]
+            c = ["d"]
+            d = ["c"]
+            "#,
+        )
+        .unwrap();
+        config.settings.pareto = true;
+        let solutions = solve_exhaustive(&config).unwrap();
+        let front = pareto_front(&solutions)
+            .iter()
+            .map(|x| (x.unpreferred, x.preferred))
+            .collect::<Vec<_>>();
+        assert_eq!(front, [(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn packed_weights_keep_keys_in_order() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let keys = (0..30)
+            .map(|i| (i, i, [0; 3].map(|_| rng.gen_range(-20..=20))))
+            .collect::<Vec<_>>();
+        let pairs = 4;
+        let weights = pack_keys(&keys, pairs as i128);
+        for _ in 0..1000 {
+            let mut pick = || {
+                let edges = (0..pairs)
+                    .map(|_| rng.gen_range(0..keys.len()))
+                    .collect::<Vec<_>>();
+                let key = edges.iter().fold([0; 3], |total, &x| {
+                    [0, 1, 2].map(|c| total[c] + keys[x].2[c])
+                });
+                let weight = edges.iter().map(|&x| weights[x].2).sum::<i128>();
+                (key, weight)
+            };
+            let (a, b) = (pick(), pick());
+            assert_eq!(a.0.cmp(&b.0), a.1.cmp(&b.1), "{:?} and {:?}", a, b);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, io::Write};
+
+use crate::{
+    config::Config,
+    score::{classify, pairs, Category, Solution},
+};
+use anyhow::Result;
+use colored::Colorize;
+
+fn write_distribution<W: Write>(
+    writer: &mut W,
+    name: &str,
+    values: impl Iterator<Item = u64> + Clone,
+) -> Result<()> {
+    let count = values.clone().count().max(1) as f64;
+    let min = values.clone().min().unwrap_or(0);
+    let max = values.clone().max().unwrap_or(0);
+    let mean = values.sum::<u64>() as f64 / count;
+    writeln!(
+        writer,
+        " {:<22} min {}, max {}, mean {}",
+        format!("{} matchups:", name),
+        min.to_string().blue(),
+        max.to_string().blue(),
+        format!("{:.2}", mean).blue()
+    )?;
+    Ok(())
+}
+
+/// The most rows a histogram has, so that wide ranges of counts are grouped
+const BUCKETS: u64 = 16;
+
+/// How many characters the longest bar in a histogram takes up
+const BAR_WIDTH: usize = 40;
+
+/// Prints how many solutions had each count, or each range of counts if
+/// there are too many to give each its own row. A candidate pool whose
+/// best counts only turn up once or twice is probably too small
+fn write_histogram<W: Write>(
+    writer: &mut W,
+    name: &str,
+    values: impl Iterator<Item = u64> + Clone,
+) -> Result<()> {
+    let (Some(min), Some(max)) = (values.clone().min(), values.clone().max()) else {
+        return Ok(());
+    };
+    let step = (max - min) / BUCKETS + 1;
+    let mut buckets = vec![0usize; ((max - min) / step + 1) as usize];
+    for value in values {
+        buckets[((value - min) / step) as usize] += 1;
+    }
+    let largest = buckets.iter().copied().max().unwrap_or(1);
+    let labels = (0..buckets.len() as u64)
+        .map(|i| match step {
+            1 => (min + i).to_string(),
+            _ => format!("{}-{}", min + i * step, (min + (i + 1) * step - 1).min(max)),
+        })
+        .collect::<Vec<_>>();
+    let width = labels.iter().map(String::len).max().unwrap_or(0);
+
+    writeln!(writer, " solutions by {} matchups:", name)?;
+    for (label, count) in labels.iter().zip(buckets) {
+        // Any count at all gets a bar, however short, so that rare counts
+        // still show up
+        let bar = (count * BAR_WIDTH).div_ceil(largest);
+        writeln!(
+            writer,
+            "  {:>width$} {} {}",
+            label,
+            "█".repeat(bar).blue(),
+            count
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints how the candidate solutions are distributed, to help judge whether
+/// a set of preferences is workable. `top` is how many people to list by
+/// unpreferred matchups, and `top_pairs` how many pairs to list by the
+/// optimal solutions they're in
+pub fn write_stats<W: Write>(
+    solutions: &[Solution],
+    optimal: &[&Solution],
+    config: &Config,
+    top: usize,
+    top_pairs: usize,
+    mut writer: W,
+) -> Result<()> {
+    writeln!(writer, "{}", "STATS".green().bold())?;
+    writeln!(
+        writer,
+        " {:<22} {}",
+        "candidates:",
+        solutions.len().to_string().blue()
+    )?;
+    write_distribution(
+        &mut writer,
+        "unpreferred",
+        solutions.iter().map(|x| x.unpreferred),
+    )?;
+    write_distribution(
+        &mut writer,
+        "preferred",
+        solutions.iter().map(|x| x.preferred),
+    )?;
+    write_distribution(
+        &mut writer,
+        "accepted",
+        solutions.iter().map(|x| x.accepted),
+    )?;
+    // The optimal solutions are distinct, but every time one was found
+    // counts towards how likely the search is to find an optimum
+    let found = optimal.first().map_or(0, |best| {
+        solutions
+            .iter()
+            .filter(|x| x.rank_key(config) == best.rank_key(config))
+            .count()
+    });
+    writeln!(
+        writer,
+        " {:<22} {} ({})",
+        "optimal solutions:",
+        found.to_string().blue(),
+        format!("{:.2}%", found as f64 / solutions.len() as f64 * 100.0).blue()
+    )?;
+    write_histogram(
+        &mut writer,
+        "unpreferred",
+        solutions.iter().map(|x| x.unpreferred),
+    )?;
+    write_histogram(
+        &mut writer,
+        "preferred",
+        solutions.iter().map(|x| x.preferred),
+    )?;
+
+    let mut counts = HashMap::<&str, u64>::new();
+    for solution in solutions {
+        for (a, b) in solution.rooms.iter().flat_map(|x| pairs(x)) {
+            if classify(a, b, config) == Category::Unpreferred {
+                *counts.entry(a).or_default() += 1;
+                *counts.entry(b).or_default() += 1;
+            }
+        }
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    writeln!(writer, " most often in unpreferred matchups:")?;
+    if counts.is_empty() {
+        writeln!(writer, "  nobody")?;
+    }
+    for (person, count) in counts.into_iter().take(top) {
+        writeln!(
+            writer,
+            "  {}: {} solutions ({})",
+            person.blue(),
+            count.to_string().blue(),
+            format!("{:.2}%", count as f64 / solutions.len() as f64 * 100.0).blue()
+        )?;
+    }
+    write_pair_frequencies(&mut writer, optimal, top_pairs)
+}
+
+/// Prints how many of the distinct optimal solutions each pair shares a room
+/// in. Pairs in every one of them are forced by the preferences, while pairs
+/// in only some are down to how ties were broken
+fn write_pair_frequencies<W: Write>(
+    writer: &mut W,
+    optimal: &[&Solution],
+    top: usize,
+) -> Result<()> {
+    let mut counts = HashMap::<(&str, &str), usize>::new();
+    for solution in optimal {
+        for (a, b) in solution.rooms.iter().flat_map(|x| pairs(x)) {
+            let pair = if a < b { (a, b) } else { (b, a) };
+            *counts
+                .entry((pair.0.as_str(), pair.1.as_str()))
+                .or_default() += 1;
+        }
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let total = optimal.len().max(1);
+    let forced = counts.iter().filter(|x| x.1 == total).count();
+    writeln!(
+        writer,
+        " {:<22} {}",
+        "distinct optima:",
+        optimal.len().to_string().blue()
+    )?;
+    writeln!(
+        writer,
+        " {:<22} {}",
+        "pairs in every one:",
+        forced.to_string().blue()
+    )?;
+    writeln!(writer, " pairs most often in optimal solutions:")?;
+    if counts.is_empty() {
+        writeln!(writer, "  none")?;
+    }
+    for ((a, b), count) in counts.into_iter().take(top) {
+        writeln!(
+            writer,
+            "  {} & {}: {} of {} ({})",
+            a.blue(),
+            b.blue(),
+            count.to_string().blue(),
+            total.to_string().blue(),
+            format!("{:.0}%", count as f64 / total as f64 * 100.0).blue()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::Serialize;
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    calendar,
+    cli::Format,
+    output::{describe_room, write_plain},
+    score::Solution,
+};
+
+/// One recorded run of `picker solve`
+#[derive(Serialize, Debug)]
+pub struct Run {
+    pub id: i64,
+    /// When the run finished, in RFC 3339
+    pub date: String,
+    /// The config file solved, or `-` for stdin
+    pub config: String,
+    /// A hash of the config file's contents, so that runs of the same config
+    /// can be told apart from runs of an edited one. Missing for stdin
+    pub config_hash: Option<String>,
+    pub seed: u64,
+    pub solver: String,
+    pub solution: Solution,
+}
+
+impl Run {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let solution = row.get::<_, String>(6)?;
+        Ok(Self {
+            id: row.get(0)?,
+            date: row.get(1)?,
+            config: row.get(2)?,
+            config_hash: row.get(3)?,
+            // SQLite only has signed integers, so seeds are stored with the
+            // same bits as an i64
+            seed: row.get::<_, i64>(4)? as u64,
+            solver: row.get(5)?,
+            solution: serde_json::from_str(&solution).map_err(|x| {
+                rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, x.into())
+            })?,
+        })
+    }
+}
+
+const COLUMNS: &str = "id, date, config, config_hash, seed, solver, solution";
+
+/// Where runs are recorded if `--db` isn't given, following the XDG base
+/// directory spec
+pub fn default_path() -> Result<PathBuf> {
+    let data = match env::var_os("XDG_DATA_HOME").filter(|x| !x.is_empty()) {
+        Some(data) => PathBuf::from(data),
+        None => env::var_os("HOME")
+            .filter(|x| !x.is_empty())
+            .map(|x| PathBuf::from(x).join(".local/share"))
+            .ok_or_else(|| anyhow!("Couldn't find a home directory, so pass --db instead"))?,
+    };
+    Ok(data.join("picker/history.db"))
+}
+
+/// Hashes a config file's contents with 64 bit FNV-1a, which stays the same
+/// between versions of picker unlike the standard library's hasher
+pub fn hash_config(contents: &[u8]) -> String {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in contents {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// A database of earlier runs, kept in SQLite
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    /// Opens the database to record a run in, creating it if needed
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create {}", parent.display()))?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("Couldn't open the history database {}", path.display()))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS runs (
+                id INTEGER PRIMARY KEY,
+                date TEXT NOT NULL,
+                config TEXT NOT NULL,
+                config_hash TEXT,
+                seed INTEGER NOT NULL,
+                solver TEXT NOT NULL,
+                solution TEXT NOT NULL
+            )",
+        )?;
+        Ok(Self { connection })
+    }
+
+    /// Opens an existing database to read runs from
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_existing(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+    }
+
+    /// Opens an existing database to remove runs from
+    pub fn open_writable(path: &Path) -> Result<Self> {
+        Self::open_existing(path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+    }
+
+    fn open_existing(path: &Path, flags: OpenFlags) -> Result<Self> {
+        if !path.exists() {
+            bail!(
+                "There is no history database at {}. Runs are recorded by `picker solve --record`",
+                path.display()
+            );
+        }
+        let connection = Connection::open_with_flags(path, flags)
+            .with_context(|| format!("Couldn't open the history database {}", path.display()))?;
+        Ok(Self { connection })
+    }
+
+    /// Records a run, returning its id
+    pub fn record(
+        &self,
+        config: &str,
+        config_hash: Option<&str>,
+        seed: u64,
+        solver: &str,
+        solution: &Solution,
+    ) -> Result<i64> {
+        let date = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        self.connection.execute(
+            "INSERT INTO runs (date, config, config_hash, seed, solver, solution)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                date,
+                config,
+                config_hash,
+                seed as i64,
+                solver,
+                serde_json::to_string(solution)?
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Every recorded run, oldest first
+    pub fn runs(&self) -> Result<Vec<Run>> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {} FROM runs ORDER BY id", COLUMNS))?;
+        let runs = statement
+            .query_map([], Run::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(runs)
+    }
+
+    /// The id of the most recent run, if there are any
+    pub fn latest(&self) -> Result<Option<i64>> {
+        Ok(self
+            .connection
+            .query_row("SELECT MAX(id) FROM runs", [], |row| row.get(0))?)
+    }
+
+    pub fn remove(&self, id: i64) -> Result<()> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM runs WHERE id = ?1", [id])?;
+        if removed == 0 {
+            bail!("There is no run {} in the history database", id);
+        }
+        Ok(())
+    }
+
+    pub fn run(&self, id: i64) -> Result<Run> {
+        self.connection
+            .query_row(
+                &format!("SELECT {} FROM runs WHERE id = ?1", COLUMNS),
+                [id],
+                Run::from_row,
+            )
+            .optional()?
+            .ok_or_else(|| anyhow!("There is no run {} in the history database", id))
+    }
+}
+
+/// Prints one line per run, in the given format
+pub fn write_runs<W: Write>(runs: &[Run], format: Format, mut writer: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            if runs.is_empty() {
+                writeln!(writer, "No runs have been recorded yet")?;
+            }
+            for run in runs {
+                writeln!(
+                    writer,
+                    "{:>4}  {}  {}  {} unpreferred",
+                    run.id.to_string().bold(),
+                    run.date,
+                    run.config.blue(),
+                    run.solution.unpreferred
+                )?;
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_runs(runs, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, runs)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record([
+                "id",
+                "date",
+                "config",
+                "config_hash",
+                "seed",
+                "solver",
+                "preferred",
+                "accepted",
+                "unpreferred",
+            ])?;
+            for run in runs {
+                writer.write_record([
+                    run.id.to_string(),
+                    run.date.clone(),
+                    run.config.clone(),
+                    run.config_hash.clone().unwrap_or_default(),
+                    run.seed.to_string(),
+                    run.solver.clone(),
+                    run.solution.preferred.to_string(),
+                    run.solution.accepted.to_string(),
+                    run.solution.unpreferred.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints everything recorded about a run, in the given format
+pub fn write_run<W: Write>(run: &Run, format: Format, mut writer: W) -> Result<()> {
+    match format {
+        Format::Text => {
+            let solution = &run.solution;
+            let lines = [
+                ("date:", run.date.clone()),
+                ("config:", run.config.clone()),
+                (
+                    "config hash:",
+                    run.config_hash.clone().unwrap_or("-".into()),
+                ),
+                ("seed:", run.seed.to_string()),
+                ("solver:", run.solver.clone()),
+                ("preferred matchups:", solution.preferred.to_string()),
+                ("accepted matchups:", solution.accepted.to_string()),
+                ("unpreferred matchups:", solution.unpreferred.to_string()),
+            ];
+            writeln!(writer, "{}", format!("RUN {}", run.id).green().bold())?;
+            let width = lines.iter().map(|x| x.0.len()).max().unwrap_or_default() + 2;
+            for (label, value) in lines {
+                writeln!(writer, " {:<width$}{}", label, value.blue())?;
+            }
+            for i in 0..solution.rooms.len() {
+                writeln!(writer, " {}", describe_room(solution, i))?;
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_run(run, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, run)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            let size = run.solution.rooms.iter().map(Vec::len).max().unwrap_or(0);
+            for room in &run.solution.rooms {
+                let mut row = room.clone();
+                row.resize(size, String::new());
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
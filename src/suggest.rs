@@ -0,0 +1,37 @@
+/// How many single character edits it takes to turn `a` into `b`, where
+/// swapping two neighbouring characters counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The known name closest to `name`, if it is close enough to be a typo.
+/// Case is ignored, and longer names are allowed more mistakes
+pub fn closest<'a>(name: &str, known: impl IntoIterator<Item = &'a String>) -> Option<&'a String> {
+    let name = name.trim().to_lowercase();
+    let limit = (name.chars().count().div_ceil(3)).clamp(1, 3);
+    known
+        .into_iter()
+        .map(|x| (edit_distance(&name, &x.to_lowercase()), x))
+        .filter(|x| x.0 <= limit)
+        .min()
+        .map(|x| x.1)
+}
@@ -0,0 +1,421 @@
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+    calendar,
+    cli::Format,
+    config::{Attribute, Config, Count, Teams, COUNTS},
+    interrupt::interrupted,
+    output::{describe_room, write_plain},
+    score::{classify_directed, Category, Solution, UNFAIRNESS_BASE},
+};
+
+/// Everyone split into teams, scored as though each team shared a room
+#[derive(Serialize, Debug, Clone)]
+pub struct Split {
+    pub teams: Vec<Vec<String>>,
+    /// Each team's average of the balanced attribute, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub means: Option<Vec<f64>>,
+    #[serde(skip)]
+    pub solution: Solution,
+}
+
+impl Split {
+    /// How far apart the highest and lowest team averages are
+    pub fn spread(&self) -> Option<f64> {
+        self.means.as_ref().map(|x| spread(x))
+    }
+}
+
+fn spread(means: &[f64]) -> f64 {
+    let max = means.iter().copied().fold(f64::MIN, f64::max);
+    let min = means.iter().copied().fold(f64::MAX, f64::min);
+    (max - min).max(0.0)
+}
+
+/// Reads everyone's value of the attribute being balanced
+fn values(config: &Config, people: &[String], attribute: &str) -> Result<Vec<f64>> {
+    people
+        .iter()
+        .map(
+            |person| match config.attributes.get(person).and_then(|x| x.get(attribute)) {
+                Some(Attribute::Integer(value)) => Ok(*value as f64),
+                Some(Attribute::Float(value)) => Ok(*value),
+                Some(value) => Err(anyhow!(
+                    "'{}' has a {} of '{}', which isn't a number",
+                    person,
+                    attribute,
+                    value
+                )),
+                None => Err(anyhow!("'{}' has no {} in [attributes]", person, attribute)),
+            },
+        )
+        .collect()
+}
+
+/// Turns an amount into thousandths, so that it can go in a key
+fn thousandths(x: f64) -> i64 {
+    (x * 1000.0).round() as i64
+}
+
+/// How good a split is: first by how few [never_pair] pairs share a team and
+/// [must_pair] pairs are split up, then by how little the averages go past
+/// the tolerance, then by `settings.objective` and finally by how even the
+/// averages are
+type Key = (i64, i64, [i64; COUNTS], i64);
+
+/// What a team adds to its split's key
+#[derive(Clone, Copy)]
+struct Tally {
+    counts: [i64; COUNTS],
+    broken: i64,
+    /// The team's total of the balanced attribute
+    total: f64,
+}
+
+/// Everything that scoring a team needs, worked out once up front so that
+/// each swap only has to re-score the two teams it touches
+struct Search<'a> {
+    config: &'a Config,
+    teams: &'a Teams,
+    people: &'a [String],
+    /// The counts of each pair of people sharing a team, apart from
+    /// unfairness, which depends on the whole team
+    pairs: Vec<Vec<[i64; COUNTS]>>,
+    forbidden: Vec<Vec<bool>>,
+    /// How each person feels about sharing a team with each other person
+    feelings: Vec<Vec<Category>>,
+    partners: Vec<Option<usize>>,
+    values: Option<Vec<f64>>,
+}
+
+impl<'a> Search<'a> {
+    fn new(config: &'a Config, teams: &'a Teams, people: &'a [String]) -> Result<Self> {
+        let pairs = people
+            .iter()
+            .map(|a| {
+                people
+                    .iter()
+                    .map(|b| {
+                        let mut counts =
+                            Solution::new(vec![vec![a.clone(), b.clone()]], vec![], config)
+                                .counts();
+                        counts[Count::WorstOff as usize] = 0;
+                        counts
+                    })
+                    .collect()
+            })
+            .collect();
+        let forbidden = people
+            .iter()
+            .map(|a| people.iter().map(|b| config.forbids(a, b)).collect())
+            .collect();
+        let feelings = people
+            .iter()
+            .map(|a| {
+                people
+                    .iter()
+                    .map(|b| classify_directed(a, b, config))
+                    .collect()
+            })
+            .collect();
+        let partners = people
+            .iter()
+            .map(|a| {
+                config
+                    .partner(a)
+                    .and_then(|b| people.iter().position(|x| x == b))
+            })
+            .collect();
+        let values = match &teams.balance {
+            Some(attribute) => Some(values(config, people, attribute)?),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            teams,
+            people,
+            pairs,
+            forbidden,
+            feelings,
+            partners,
+            values,
+        })
+    }
+
+    fn names(&self, team: &[usize]) -> Vec<String> {
+        team.iter().map(|&x| self.people[x].clone()).collect()
+    }
+
+    fn tally(&self, team: &[usize]) -> Tally {
+        let mut counts = [0; COUNTS];
+        let mut broken = 0;
+        for (i, &a) in team.iter().enumerate() {
+            for &b in &team[i + 1..] {
+                for (count, pair) in counts.iter_mut().zip(self.pairs[a][b]) {
+                    *count += pair;
+                }
+                broken += self.forbidden[a][b] as i64;
+            }
+            if let Some(partner) = self.partners[a] {
+                if a < partner && !team.contains(&partner) {
+                    broken += 1;
+                }
+            }
+        }
+        // Matches how `Solution::new` scores how unfair a room is
+        if self.config.settings.objective.uses(Count::WorstOff) {
+            counts[Count::WorstOff as usize] = team
+                .iter()
+                .map(|&a| {
+                    let feeling = team
+                        .iter()
+                        .filter(|&&b| b != a)
+                        .map(|&b| self.feelings[a][b])
+                        .max()
+                        .unwrap_or(Category::Accepted);
+                    UNFAIRNESS_BASE.pow(feeling as u32) as i64
+                })
+                .sum();
+        }
+        let total = self
+            .values
+            .as_ref()
+            .map_or(0.0, |values| team.iter().map(|&x| values[x]).sum());
+        Tally {
+            counts,
+            broken,
+            total,
+        }
+    }
+
+    fn means(&self, teams: &[Vec<usize>], tallies: &[Tally]) -> Option<Vec<f64>> {
+        self.values.as_ref()?;
+        Some(
+            teams
+                .iter()
+                .zip(tallies)
+                .map(|(team, tally)| tally.total / team.len().max(1) as f64)
+                .collect(),
+        )
+    }
+
+    fn key(&self, teams: &[Vec<usize>], tallies: &[Tally]) -> Key {
+        let mut counts = [0; COUNTS];
+        for tally in tallies {
+            for (count, team) in counts.iter_mut().zip(tally.counts) {
+                *count += team;
+            }
+        }
+        let broken = tallies.iter().map(|x| x.broken).sum::<i64>();
+        let spread = self.means(teams, tallies).map_or(0.0, |x| spread(&x));
+        let excess = (spread - self.teams.tolerance).max(0.0);
+        (
+            -broken,
+            -thousandths(excess),
+            self.config.settings.objective.key(counts),
+            -thousandths(spread),
+        )
+    }
+}
+
+/// Splits everyone into `[teams].count` teams whose sizes differ by at most
+/// one, so that friends end up together and, if `[teams].balance` names an
+/// attribute, every team's average of it is within `[teams].tolerance` of the
+/// others. Each of `settings.restarts` random splits is improved by swapping
+/// people between teams until no swap helps, and the best is kept. Returns
+/// `None` if every split found breaks a [never_pair] or [must_pair] pair
+pub fn split(config: &Config, rng: &mut impl Rng) -> Result<Option<Split>> {
+    let Some(teams) = &config.teams else {
+        bail!("Forming teams needs a [teams] table with the number of teams");
+    };
+    if teams.count == 0 {
+        bail!("[teams] needs a count of at least 1");
+    }
+    let people = config.people();
+    let search = Search::new(config, teams, &people)?;
+
+    let mut best: Option<(Key, Vec<Vec<usize>>, Vec<Tally>)> = None;
+    for _ in 0..config.settings.restarts.max(1) {
+        if interrupted() {
+            break;
+        }
+        let mut order = (0..people.len()).collect::<Vec<_>>();
+        order.shuffle(rng);
+        let mut current = vec![vec![]; teams.count];
+        for (i, person) in order.into_iter().enumerate() {
+            current[i % teams.count].push(person);
+        }
+        let mut tallies = current.iter().map(|x| search.tally(x)).collect::<Vec<_>>();
+
+        let mut key = search.key(&current, &tallies);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for a in 0..current.len() {
+                for b in a + 1..current.len() {
+                    for i in 0..current[a].len() {
+                        for j in 0..current[b].len() {
+                            swap(&mut current, [a, b], [i, j]);
+                            let before = (tallies[a], tallies[b]);
+                            tallies[a] = search.tally(&current[a]);
+                            tallies[b] = search.tally(&current[b]);
+                            let swapped = search.key(&current, &tallies);
+                            if swapped > key {
+                                key = swapped;
+                                improved = true;
+                            } else {
+                                swap(&mut current, [a, b], [i, j]);
+                                (tallies[a], tallies[b]) = before;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if best.as_ref().is_none_or(|x| key > x.0) {
+            best = Some((key, current, tallies));
+        }
+    }
+
+    Ok(best.filter(|x| x.0 .0 == 0).map(|(_, teams, tallies)| {
+        let means = search.means(&teams, &tallies);
+        // Sorted as `Solution::sort` sorts rooms, so that the same split
+        // always looks the same, keeping each team's average with it
+        let mut order = teams
+            .iter()
+            .map(|x| {
+                let mut names = search.names(x);
+                names.sort();
+                names
+            })
+            .enumerate()
+            .collect::<Vec<_>>();
+        order.sort_by(|a, b| a.1.cmp(&b.1));
+        let means = means.map(|x| order.iter().map(|y| x[y.0]).collect());
+        let teams = order.into_iter().map(|x| x.1).collect::<Vec<_>>();
+        Split {
+            means,
+            solution: Solution::new(teams.clone(), vec![], config),
+            teams,
+        }
+    }))
+}
+
+fn swap(teams: &mut [Vec<usize>], [a, b]: [usize; 2], [i, j]: [usize; 2]) {
+    let (left, right) = teams.split_at_mut(b);
+    std::mem::swap(&mut left[a][i], &mut right[0][j]);
+}
+
+/// Prints the teams, in the given format
+pub fn write_split<W: Write>(
+    split: &Split,
+    config: &Config,
+    format: Format,
+    mut writer: W,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            let solution = &split.solution;
+            let mut lines = vec![
+                ("preferred matchups:", solution.preferred.to_string()),
+                ("accepted matchups:", solution.accepted.to_string()),
+                ("unpreferred matchups:", solution.unpreferred.to_string()),
+            ];
+            if let (Some(spread), Some(teams)) = (split.spread(), &config.teams) {
+                lines.push(("average spread:", format!("{:.2}", spread)));
+                lines.push(("tolerance:", format!("{:.2}", teams.tolerance)));
+            }
+            writeln!(writer, "{}", "TEAMS".green().bold())?;
+            let width = lines.iter().map(|x| x.0.len()).max().unwrap_or_default() + 2;
+            for (label, value) in lines {
+                writeln!(writer, " {:<width$}{}", label, value.blue())?;
+            }
+            for i in 0..split.teams.len() {
+                match &split.means {
+                    Some(means) => writeln!(
+                        writer,
+                        " {} {}",
+                        format!("({:.2})", means[i]).bold(),
+                        describe_room(solution, i)
+                    )?,
+                    None => writeln!(writer, " {}", describe_room(solution, i))?,
+                }
+            }
+        }
+        Format::Markdown | Format::Html => {
+            write_plain(format, &mut writer, |writer| {
+                write_split(split, config, Format::Text, writer)
+            })?;
+        }
+        Format::Ics => return Err(calendar::unsupported()),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, split)?;
+            writeln!(writer)?;
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            writer.write_record(["team", "person"])?;
+            for (i, team) in split.teams.iter().enumerate() {
+                let number = (i + 1).to_string();
+                for person in team {
+                    writer.write_record([number.as_str(), person])?;
+                }
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_toml;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn sorts_teams_and_keeps_their_averages() {
+        let config = parse_toml(
+            r#"
+            people = ["f", "e", "d", "c", "b", "a"]
+
+            [settings]
+            solutions = 10
+
+            [attributes]
+            a = { skill = 1 }
+            b = { skill = 2 }
+            c = { skill = 3 }
+            d = { skill = 4 }
+            e = { skill = 5 }
+            f = { skill = 6 }
+
+            [teams]
+            count = 2
+            balance = "skill"
+            tolerance = 10
+            "#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(98);
+        for _ in 0..10 {
+            let split = split(&config, &mut rng).unwrap().unwrap();
+            let mut sorted = split.teams.clone();
+            for team in &mut sorted {
+                team.sort();
+            }
+            sorted.sort();
+            assert_eq!(split.teams, sorted);
+            for (team, mean) in split.teams.iter().zip(split.means.unwrap()) {
+                let skill = |x: &String| (x.as_bytes()[0] - b'a' + 1) as f64;
+                let total = team.iter().map(skill).sum::<f64>();
+                assert_eq!(mean, total / team.len() as f64);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use minijinja::{value::Serde, Environment};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    path::Path,
+};
+
+use crate::{
+    config::{Attribute, Config},
+    output::summary,
+    score::{
+        classify_room, max_preferred_pairs, outcomes, preferred_pairs, room_weight, Category,
+        RunResult,
+    },
+};
+
+/// Everything a `--template` can use
+#[derive(Serialize)]
+struct Fields<'a> {
+    seed: u64,
+    config_hash: Option<&'a str>,
+    started: &'a str,
+    finished: &'a str,
+    rooms: Vec<RoomView<'a>>,
+    /// Everyone in the solution, worst off first, see `score::outcomes`
+    people: Vec<PersonView<'a>>,
+    scores: Scores,
+    /// The summary printed after the rooms, as label and value
+    summary: Vec<(&'static str, String)>,
+    /// Everyone's entry in [attributes], by name
+    attributes: &'a HashMap<String, BTreeMap<String, Attribute>>,
+}
+
+#[derive(Serialize)]
+struct RoomView<'a> {
+    /// From 1, in the order the rooms are printed
+    number: usize,
+    /// The room's name from [rooms], if the config names its rooms
+    name: Option<&'a str>,
+    people: Vec<PersonView<'a>>,
+    category: Category,
+    weight: u64,
+}
+
+#[derive(Serialize)]
+struct PersonView<'a> {
+    name: String,
+    category: Category,
+    attributes: Option<&'a BTreeMap<String, Attribute>>,
+}
+
+#[derive(Serialize)]
+struct Scores {
+    strongly_preferred: u64,
+    preferred: u64,
+    acceptable: u64,
+    accepted: u64,
+    unpreferred: u64,
+    weight: u64,
+    preferred_pairs: usize,
+    max_preferred_pairs: usize,
+}
+
+/// Fills in a user's template with a solution, such as an announcement of
+/// who is in which room. Templates use Jinja syntax, and are escaped for
+/// HTML if their file name ends in `.html`
+pub fn write_template<W: Write>(
+    path: &Path,
+    result: &RunResult,
+    config: &Config,
+    mut writer: W,
+) -> Result<()> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read the template {}", path.display()))?;
+    let solution = &result.solution;
+    let outcomes = outcomes(solution, config);
+    let person = |name: &str| PersonView {
+        name: name.to_string(),
+        category: outcomes
+            .iter()
+            .find(|x| x.name == name)
+            .map_or(Category::Accepted, |x| x.category),
+        attributes: config.attributes.get(name),
+    };
+    let fields = Fields {
+        seed: result.seed,
+        config_hash: result.config_hash.as_deref(),
+        started: &result.started,
+        finished: &result.finished,
+        rooms: solution
+            .rooms
+            .iter()
+            .enumerate()
+            .map(|(i, room)| RoomView {
+                number: i + 1,
+                name: solution.names.get(i).map(String::as_str),
+                people: room.iter().map(|x| person(x)).collect(),
+                category: classify_room(room, config),
+                weight: room_weight(room, config),
+            })
+            .collect(),
+        people: outcomes.iter().map(|x| person(&x.name)).collect(),
+        scores: Scores {
+            strongly_preferred: solution.strongly_preferred,
+            preferred: solution.preferred,
+            acceptable: solution.acceptable,
+            accepted: solution.accepted,
+            unpreferred: solution.unpreferred,
+            weight: solution.weight,
+            preferred_pairs: preferred_pairs(solution, config),
+            max_preferred_pairs: max_preferred_pairs(config),
+        },
+        summary: summary(solution, config),
+        attributes: &config.attributes,
+    };
+
+    let name = path.display().to_string();
+    let mut environment = Environment::new();
+    environment
+        .add_template(&name, &source)
+        .with_context(|| format!("{} isn't a valid template", name))?;
+    let text = environment
+        .get_template(&name)?
+        .render(Serde(&fields))
+        .with_context(|| format!("Couldn't fill in the template {}", name))?;
+    writeln!(writer, "{}", text)?;
+    Ok(())
+}
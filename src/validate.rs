@@ -0,0 +1,856 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use crate::{
+    config::{
+        Attribute, Config, Objective, OddPolicy, PreferenceMode, Teams, CONFIG_KEYS, SETTINGS_KEYS,
+    },
+    location::Locations,
+    score::{classify, Category},
+    suggest::closest,
+};
+use colored::Colorize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+    /// Where in the config the problem is, e.g. `config.toml:12:1`
+    pub location: Option<String>,
+    /// A likely fix for the problem
+    pub help: Option<String>,
+}
+
+impl Problem {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            location: None,
+            help: None,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            location: None,
+            help: None,
+        }
+    }
+
+    fn at(mut self, location: Option<String>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Suggests the closest known name, if `name` looks like a typo of one
+    fn suggest<'a>(mut self, name: &str, known: impl IntoIterator<Item = &'a String>) -> Self {
+        self.help = closest(name, known).map(|x| format!("did you mean '{}'?", x));
+        self
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = match self.severity {
+            Severity::Error => "ERROR".red().bold(),
+            Severity::Warning => "WARNING".yellow().bold(),
+        };
+        write!(f, "{} {}", tag, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n  {} {}", "-->".blue(), location)?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n  {} {}", "help:".blue(), help)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_table<'a, L: 'a>(
+    table_name: &str,
+    table: &'a HashMap<String, L>,
+    names: impl Fn(&'a L) -> Vec<&'a String>,
+    people: &BTreeSet<&String>,
+    report_missing: bool,
+    locations: &Locations,
+    problems: &mut Vec<Problem>,
+) {
+    let mut keys = table.keys().collect::<Vec<_>>();
+    keys.sort();
+    for person in keys {
+        if !people.contains(person) {
+            problems.push(
+                Problem::error(format!(
+                    "'{}' has an entry in [{}], but isn't in the people list",
+                    person, table_name
+                ))
+                .at(locations.key(table_name, person))
+                .suggest(person, people.iter().copied()),
+            );
+        }
+    }
+
+    for &person in people {
+        let Some(list) = table.get(person).map(&names) else {
+            if report_missing {
+                problems.push(Problem::warning(format!(
+                    "'{}' has no entry in [{}], so they are treated as listing nobody",
+                    person, table_name
+                )));
+            }
+            continue;
+        };
+        for other in list {
+            let location = locations.key(table_name, person);
+            if other == person {
+                problems.push(
+                    Problem::error(format!("'{}' lists themselves in [{}]", person, table_name))
+                        .at(location),
+                );
+            } else if !people.contains(other) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' lists '{}' in [{}], but '{}' is not defined",
+                        person, other, table_name, other
+                    ))
+                    .at(location)
+                    .suggest(other, people.iter().copied()),
+                );
+            }
+        }
+    }
+}
+
+/// Checks a config for anything that would stop it from being solved, or that
+/// is likely to be a mistake
+pub fn validate(config: &Config) -> Vec<Problem> {
+    let mut problems = vec![];
+    let roster = config.people();
+    let people = roster.iter().collect::<BTreeSet<_>>();
+    // An explicit people list or missing policy means missing entries are
+    // deliberate
+    let report_missing = config.people.is_empty() && config.settings.missing_policy.is_none();
+
+    let size = config.settings.room_size;
+    if !config.rooms.is_empty() {
+        check_rooms(config, people.len(), &mut problems);
+        if config.settings.group_sizes.is_some() {
+            problems.push(Problem::warning(
+                "settings.group_sizes is ignored, because the config has [rooms]".to_string(),
+            ));
+        }
+    } else if let Some(sizes) = &config.settings.group_sizes {
+        check_group_sizes(config, sizes, people.len(), &mut problems);
+    } else if size < 2 {
+        problems.push(Problem::error(format!(
+            "settings.room_size is {}, but rooms need space for at least 2 people",
+            size
+        )));
+    } else if !people.len().is_multiple_of(size) {
+        let message = format!(
+            "there are {} people, which can't be split into rooms of {}",
+            people.len(),
+            size
+        );
+        match config.settings.odd_policy {
+            Some(OddPolicy::Error) => problems.push(Problem::error(format!(
+                "{}, and settings.odd_policy is \"error\"",
+                message
+            ))),
+            None => problems.push(Problem::warning(format!(
+                "{}, so the last room will have a spare bed. Set settings.odd_policy to \"triple\" or \"single\" to choose what happens",
+                message
+            ))),
+            Some(OddPolicy::Triple | OddPolicy::Single) => {}
+        }
+    }
+
+    let temperature = config.settings.temperature;
+    if !temperature.is_finite() || temperature <= 0.0 {
+        problems.push(Problem::error(format!(
+            "settings.temperature is {}, but it must be above 0",
+            temperature
+        )));
+    }
+
+    if let Objective::Lexicographic { order } = &config.settings.objective {
+        if order.is_empty() {
+            problems.push(Problem::error(
+                "settings.objective.order is empty, so every solution would tie".to_string(),
+            ));
+        }
+    }
+
+    if config.settings.convergence.is_some_and(|x| x.window == 0) {
+        problems.push(Problem::error(
+            "settings.convergence.window is 0, but it must be at least 1".to_string(),
+        ));
+    }
+
+    // The extra preference tiers are expected to only list a few people
+    for (name, table) in config.tiers() {
+        check_table(
+            name,
+            table,
+            |x| x.names().collect(),
+            &people,
+            report_missing && name == "preferred",
+            &config.locations,
+            &mut problems,
+        );
+    }
+    check_table(
+        "unpreferred",
+        &config.unpreferred,
+        |x| x.iter().collect(),
+        &people,
+        report_missing,
+        &config.locations,
+        &mut problems,
+    );
+    check_table(
+        "never_pair",
+        &config.never_pair,
+        |x| x.iter().collect(),
+        &people,
+        false,
+        &config.locations,
+        &mut problems,
+    );
+
+    for &person in &people {
+        let unpreferred = config.unpreferred.get(person);
+        for (name, table) in config.tiers() {
+            for other in table.get(person).into_iter().flat_map(|x| x.names()) {
+                if unpreferred.is_some_and(|x| x.contains(other)) {
+                    problems.push(
+                        Problem::warning(format!(
+                            "'{}' lists '{}' in both [{}] and [unpreferred]",
+                            person, other, name
+                        ))
+                        .at(config.locations.key(name, person)),
+                    );
+                }
+                let mutual = config.tier(other, person) > 0
+                    || config.settings.preference_mode != PreferenceMode::Mutual;
+                if person != other && people.contains(other) && !mutual {
+                    problems.push(
+                        Problem::warning(format!(
+                            "'{}' lists '{}' in [{}], but '{}' doesn't list '{}' in any preference tier, so they can't be a preferred matchup",
+                            person, other, name, other, person
+                        ))
+                        .at(config.locations.key(name, person)),
+                    );
+                }
+            }
+        }
+    }
+
+    check_must_pairs(config, &people, &mut problems);
+
+    let mut described = config.attributes.keys().collect::<Vec<_>>();
+    described.sort();
+    for person in described {
+        if !people.contains(person) {
+            problems.push(
+                Problem::error(format!(
+                    "'{}' has an entry in [attributes], but isn't in the people list",
+                    person
+                ))
+                .at(config.locations.key("attributes", person))
+                .suggest(person, people.iter().copied()),
+            );
+        }
+    }
+
+    let mut aliased = config.aliases.keys().collect::<Vec<_>>();
+    aliased.sort();
+    for person in aliased {
+        if !people.contains(person) {
+            problems.push(
+                Problem::warning(format!(
+                    "'{}' has aliases in [aliases], but isn't in the people list",
+                    person
+                ))
+                .at(config.locations.key("aliases", person))
+                .suggest(person, people.iter().copied()),
+            );
+        }
+    }
+
+    let mut groups = config.groups.iter().collect::<Vec<_>>();
+    groups.sort();
+    for (group, members) in groups {
+        for member in members {
+            if !people.contains(member) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is in the group '{}', but '{}' is not defined",
+                        member, group, member
+                    ))
+                    .at(config.locations.key("groups", group))
+                    .suggest(member, people.iter().copied()),
+                );
+            }
+        }
+    }
+
+    check_sides(config, &people, &mut problems);
+    check_locked(config, &people, &mut problems);
+    if let Some(teams) = &config.teams {
+        check_teams(config, teams, &people, &mut problems);
+    }
+    if let Some(layout) = config.layout {
+        let seats = layout.rows * layout.cols;
+        if seats == 0 {
+            problems.push(
+                Problem::error("[layout] has no seats, as rows or cols is 0".to_string())
+                    .at(config.locations.table("layout")),
+            );
+        } else if people.len() > seats {
+            problems.push(
+                Problem::error(format!(
+                    "there are {} people, but only {} seats in [layout]",
+                    people.len(),
+                    seats
+                ))
+                .at(config.locations.table("layout")),
+            );
+        }
+    }
+
+    for (table, person, name) in &config.repeated {
+        let problem = match person {
+            Some(person) => Problem::warning(format!(
+                "'{}' lists '{}' more than once in [{}], so only the first is used",
+                person, name, table
+            ))
+            .at(config.locations.key(table, person)),
+            None => Problem::warning(format!("'{}' is in the people list more than once", name))
+                .at(config.locations.table(table)),
+        };
+        problems.push(problem);
+    }
+
+    for key in &config.unknown_keys {
+        let (table, name) = match key.split_once('.') {
+            Some((table, name)) => (Some(table), name),
+            None => (None, key.as_str()),
+        };
+        let (location, known) = match table {
+            Some(table) => (config.locations.key(table, name), SETTINGS_KEYS.as_slice()),
+            None => (config.locations.table(name), CONFIG_KEYS.as_slice()),
+        };
+        let known = known.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        problems.push(
+            Problem::warning(format!("unknown key '{}' is ignored", key))
+                .at(location)
+                .suggest(name, &known),
+        );
+    }
+
+    problems
+}
+
+fn check_rooms(config: &Config, people: usize, problems: &mut Vec<Problem>) {
+    for (name, room) in &config.rooms {
+        if room.capacity == 0 {
+            problems.push(Problem::warning(format!(
+                "room '{}' has a capacity of 0, so nobody can be put in it",
+                name
+            )));
+        }
+    }
+
+    let beds = config.rooms.values().map(|x| x.capacity).sum::<usize>();
+    if beds < people {
+        problems.push(Problem::error(format!(
+            "there are {} people, but only {} beds in [rooms]",
+            people, beds
+        )));
+    }
+    let doubles = config.rooms.values().filter(|x| x.capacity >= 2).count();
+    if config.must_pair.len() > doubles {
+        problems.push(Problem::error(format!(
+            "there are {} pairs in [must_pair], but only {} rooms in [rooms] fit two people",
+            config.must_pair.len(),
+            doubles
+        )));
+    }
+}
+
+fn check_group_sizes(config: &Config, sizes: &[usize], people: usize, problems: &mut Vec<Problem>) {
+    if sizes.contains(&0) {
+        problems.push(Problem::error(
+            "settings.group_sizes has a room of 0, but rooms need space for at least 1 person"
+                .to_string(),
+        ));
+    }
+    let places = sizes.iter().sum::<usize>();
+    if places != people {
+        problems.push(Problem::error(format!(
+            "there are {} people, but settings.group_sizes has places for {}",
+            people, places
+        )));
+    }
+    let doubles = sizes.iter().filter(|&&x| x >= 2).count();
+    if config.must_pair.len() > doubles {
+        problems.push(Problem::error(format!(
+            "there are {} pairs in [must_pair], but only {} rooms in settings.group_sizes fit two people",
+            config.must_pair.len(),
+            doubles
+        )));
+    }
+}
+
+fn check_sides(config: &Config, people: &BTreeSet<&String>, problems: &mut Vec<Problem>) {
+    if let Some(side) = &config.settings.proposing_side {
+        if !config.sides.contains_key(side) {
+            problems.push(
+                Problem::error(format!(
+                    "settings.proposing_side is '{}', which isn't a side in [sides]",
+                    side
+                ))
+                .suggest(side, config.sides.keys()),
+            );
+        }
+    }
+    if config.sides.is_empty() {
+        return;
+    }
+    if config.sides.len() != 2 {
+        problems.push(
+            Problem::error(format!(
+                "[sides] has {} sides, but needs exactly 2",
+                config.sides.len()
+            ))
+            .at(config.locations.table("sides")),
+        );
+    }
+    if config.capacities().iter().any(|x| x.1 > 2) {
+        problems.push(
+            Problem::error(
+                "[sides] needs every room to take one person from each side, so rooms can't be for more than two people"
+                    .to_string(),
+            )
+            .at(config.locations.table("sides")),
+        );
+    }
+
+    let mut seen = HashMap::<&String, &String>::new();
+    for (side, members) in &config.sides {
+        for member in members {
+            let location = || config.locations.key("sides", side);
+            if !people.contains(member) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is on the side '{}', but isn't in the people list",
+                        member, side
+                    ))
+                    .at(location())
+                    .suggest(member, people.iter().copied()),
+                );
+            }
+            if let Some(other) = seen.insert(member, side) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is on both the '{}' and '{}' sides",
+                        member, other, side
+                    ))
+                    .at(location()),
+                );
+            }
+        }
+    }
+    for (a, b) in &config.must_pair {
+        if config
+            .sides
+            .values()
+            .any(|x| x.contains(a) && x.contains(b))
+        {
+            problems.push(
+                Problem::error(format!(
+                    "'{}' and '{}' are in [must_pair], but are on the same side in [sides]",
+                    a, b
+                ))
+                .at(config.locations.key("must_pair", a)),
+            );
+        }
+    }
+}
+
+fn check_locked(config: &Config, people: &BTreeSet<&String>, problems: &mut Vec<Problem>) {
+    let mut seen = HashMap::<&String, &String>::new();
+    for (room, members) in &config.locked {
+        let location = || config.locations.key("locked", room);
+        for member in members {
+            if !people.contains(member) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is locked in '{}', but isn't in the people list",
+                        member, room
+                    ))
+                    .at(location())
+                    .suggest(member, people.iter().copied()),
+                );
+            }
+            if let Some(other) = seen.insert(member, room) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is locked in both '{}' and '{}'",
+                        member, other, room
+                    ))
+                    .at(location()),
+                );
+            }
+        }
+        if let Some(capacity) = config.rooms.get(room).map(|x| x.capacity) {
+            if members.len() > capacity {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is for {} people, but {} are locked in it",
+                        room,
+                        capacity,
+                        members.len()
+                    ))
+                    .at(location()),
+                );
+            }
+        }
+        for (i, a) in members.iter().enumerate() {
+            for b in &members[i + 1..] {
+                if config.forbids(a, b) {
+                    problems.push(
+                        Problem::error(format!(
+                            "'{}' and '{}' are locked in '{}', but can never share a room",
+                            a, b, room
+                        ))
+                        .at(location()),
+                    );
+                }
+            }
+            if let Some(partner) = config
+                .partner(a)
+                .filter(|x| !members.contains(&x.to_string()))
+            {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is locked in '{}' without '{}', who they are in [must_pair] with",
+                        a, room, partner
+                    ))
+                    .at(location()),
+                );
+            }
+        }
+    }
+}
+
+fn check_teams(
+    config: &Config,
+    teams: &Teams,
+    people: &BTreeSet<&String>,
+    problems: &mut Vec<Problem>,
+) {
+    let location = || config.locations.table("teams");
+    if teams.count == 0 {
+        problems
+            .push(Problem::error("[teams] needs a count of at least 1".to_string()).at(location()));
+    } else if teams.count > people.len() {
+        problems.push(
+            Problem::warning(format!(
+                "[teams] has a count of {}, but there are only {} people, so some teams will be empty",
+                teams.count,
+                people.len()
+            ))
+            .at(location()),
+        );
+    }
+    if !teams.tolerance.is_finite() || teams.tolerance < 0.0 {
+        problems.push(
+            Problem::error("[teams] needs a tolerance of at least 0".to_string()).at(location()),
+        );
+    }
+    let Some(attribute) = &teams.balance else {
+        return;
+    };
+    for person in people {
+        let problem = match config
+            .attributes
+            .get(*person)
+            .and_then(|x| x.get(attribute))
+        {
+            Some(Attribute::Integer(_) | Attribute::Float(_)) => continue,
+            Some(value) => format!(
+                "'{}' has a {} of '{}', but [teams] balances it, so it needs to be a number",
+                person, attribute, value
+            ),
+            None => format!(
+                "'{}' has no {} in [attributes], but [teams] balances it",
+                person, attribute
+            ),
+        };
+        problems.push(Problem::error(problem).at(config.locations.key("attributes", person)));
+    }
+}
+
+fn check_must_pairs(config: &Config, people: &BTreeSet<&String>, problems: &mut Vec<Problem>) {
+    let mut pairs = config.must_pair.iter().collect::<Vec<_>>();
+    pairs.sort();
+
+    let mut pair_counts = HashMap::<&String, usize>::new();
+    for (a, b) in pairs {
+        let location = || config.locations.key("must_pair", a);
+        for person in [a, b] {
+            if !people.contains(person) {
+                problems.push(
+                    Problem::error(format!(
+                        "'{}' is in [must_pair], but isn't in the people list",
+                        person
+                    ))
+                    .at(location())
+                    .suggest(person, people.iter().copied()),
+                );
+            }
+            *pair_counts.entry(person).or_default() += 1;
+        }
+        if a == b {
+            problems.push(
+                Problem::error(format!("'{}' is paired with themselves in [must_pair]", a))
+                    .at(location()),
+            );
+        } else if config.forbids(a, b) {
+            problems.push(
+                Problem::error(format!(
+                    "'{}' and '{}' are in both [must_pair] and [never_pair]",
+                    a, b
+                ))
+                .at(location()),
+            );
+        } else if classify(a, b, config) == Category::Unpreferred {
+            problems.push(
+                Problem::warning(format!(
+                    "'{}' and '{}' must share a room, but it is an unpreferred matchup",
+                    a, b
+                ))
+                .at(location()),
+            );
+        }
+    }
+
+    let mut repeated = pair_counts
+        .into_iter()
+        .filter(|x| x.1 > 1)
+        .map(|x| x.0)
+        .collect::<Vec<_>>();
+    repeated.sort();
+    for person in repeated {
+        problems.push(Problem::error(format!(
+            "'{}' is in more than one pair in [must_pair]",
+            person
+        )));
+    }
+}
+
+/// A search for a way to split everyone into rooms
+struct Grouping {
+    /// Whether two people may share a room
+    compatible: Vec<Vec<bool>>,
+    /// Who each person must share a room with, if anyone
+    partners: Vec<Option<usize>>,
+    /// How many people fit in each room, largest first
+    capacities: Vec<usize>,
+}
+
+impl Grouping {
+    /// Fills the rooms that aren't `used` yet, where `spare` is how many beds
+    /// can still be left empty. The first person without a room has to go in
+    /// one of them, so they are tried in a room of every size still free.
+    /// Rooms of the same size are interchangeable, so only one of each is
+    /// tried
+    fn search(
+        &self,
+        used: &mut [bool],
+        spare: usize,
+        matched: &mut [bool],
+        budget: &mut u64,
+    ) -> Option<bool> {
+        let Some(person) = matched.iter().position(|x| !x) else {
+            return Some(true);
+        };
+        let mut result = Some(false);
+        for room in 0..self.capacities.len() {
+            let repeat =
+                room > 0 && !used[room - 1] && self.capacities[room - 1] == self.capacities[room];
+            if used[room] || repeat {
+                continue;
+            }
+            used[room] = true;
+            matched[person] = true;
+            let found = self.fill(room, used, spare, &mut vec![person], matched, budget);
+            matched[person] = false;
+            used[room] = false;
+            match found {
+                Some(true) => return found,
+                None => result = None,
+                Some(false) => {}
+            }
+        }
+        result
+    }
+
+    /// Tries every way of filling up a room, where everyone after the first
+    /// person is added in order so that each room is only tried once
+    fn fill(
+        &self,
+        room: usize,
+        used: &mut [bool],
+        spare: usize,
+        members: &mut Vec<usize>,
+        matched: &mut [bool],
+        budget: &mut u64,
+    ) -> Option<bool> {
+        let empty = self.capacities[room] - members.len();
+        let together = members
+            .iter()
+            .all(|&x| self.partners[x].is_none_or(|y| members.contains(&y)));
+        if empty <= spare && together {
+            let found = self.search(used, spare - empty, matched, budget);
+            if found != Some(false) || empty == 0 {
+                return found;
+            }
+        }
+        if empty == 0 {
+            return Some(false);
+        }
+
+        let last = members[members.len() - 1];
+        for other in last + 1..matched.len() {
+            if matched[other] || !members.iter().all(|&x| self.compatible[x][other]) {
+                continue;
+            }
+            if *budget == 0 {
+                return None;
+            }
+            *budget -= 1;
+            matched[other] = true;
+            members.push(other);
+            let found = self.fill(room, used, spare, members, matched, budget);
+            members.pop();
+            matched[other] = false;
+            if found != Some(false) {
+                return found;
+            }
+        }
+        Some(false)
+    }
+}
+
+/// Searches for a way to split everyone into rooms using only the matchups
+/// `allowed` accepts, keeping the pairs from [must_pair] together
+fn rooming_possible(config: &Config, allowed: impl Fn(&str, &str) -> bool) -> Option<bool> {
+    let people = config.people();
+    let mut capacities = config
+        .capacities()
+        .into_iter()
+        .map(|x| x.1)
+        .filter(|&x| x > 0)
+        .collect::<Vec<_>>();
+    capacities.sort_by(|a, b| b.cmp(a));
+    let Some(spare) = capacities.iter().sum::<usize>().checked_sub(people.len()) else {
+        return Some(false);
+    };
+    if config.rooms.is_empty() && spare > 0 && config.settings.odd_policy == Some(OddPolicy::Error)
+    {
+        return Some(false);
+    }
+
+    let compatible = people
+        .iter()
+        .map(|a| people.iter().map(|b| a != b && allowed(a, b)).collect())
+        .collect();
+    let partners = people
+        .iter()
+        .map(|a| {
+            config
+                .partner(a)
+                .and_then(|b| people.iter().position(|x| x == b))
+        })
+        .collect();
+    let grouping = Grouping {
+        compatible,
+        partners,
+        capacities,
+    };
+    let mut matched = vec![false; people.len()];
+    let mut used = vec![false; grouping.capacities.len()];
+    grouping.search(&mut used, spare, &mut matched, &mut 1_000_000)
+}
+
+/// Works out whether everyone could be put in a room without a single
+/// unpreferred matchup, by searching for rooms that only use accepted pairs.
+/// Returns `None` if the search gave up before finding an answer
+pub fn zero_unpreferred_possible(config: &Config) -> Option<bool> {
+    rooming_possible(config, |a, b| {
+        !config.forbids(a, b) && classify(a, b, config) != Category::Unpreferred
+    })
+}
+
+/// Works out whether everyone could be put in a room without any
+/// [never_pair] matchup sharing one. Returns `None` if the search gave up
+pub fn never_pairs_separable(config: &Config) -> Option<bool> {
+    rooming_possible(config, |a, b| !config.forbids(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_toml;
+
+    /// Two people who can only share a room with each other need the room of
+    /// two, while everyone else fills the room of three
+    const UNEVEN: &str = r#"
+        [settings]
+        solutions = 1000
+        group_sizes = [3, 2]
+
+        [preferred]
+        c = ["d", "e"]
+        d = ["c", "e"]
+        e = ["c", "d"]
+
+        [unpreferred]
+        a = ["c", "d", "e"]
+        b = ["c", "d", "e"]
+    "#;
+
+    #[test]
+    fn finds_rooms_of_every_size() {
+        let config = parse_toml(UNEVEN).unwrap();
+        assert_eq!(zero_unpreferred_possible(&config), Some(true));
+    }
+
+    #[test]
+    fn separates_never_pairs_across_room_sizes() {
+        let text = UNEVEN.replace("[unpreferred]", "[never_pair]");
+        let config = parse_toml(&text).unwrap();
+        assert_eq!(never_pairs_separable(&config), Some(true));
+    }
+
+    #[test]
+    fn reports_impossible_rooms() {
+        let text = UNEVEN.replace(r#"a = ["c", "d", "e"]"#, r#"a = ["b", "c", "d", "e"]"#);
+        let config = parse_toml(&text).unwrap();
+        assert_eq!(zero_unpreferred_possible(&config), Some(false));
+    }
+}